@@ -0,0 +1,73 @@
+//! Compares `ScanlineRasterizer` with testing every pixel of a triangle's
+//! bounds, which is how triangles were rasterized before it.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use graphics::{rectangle, Context};
+use graphics_buffer::*;
+
+const SIZE: u32 = 1024;
+
+/// Tests every pixel in the clip rectangle against the triangle from scratch
+#[derive(Debug)]
+struct PerPixelRasterizer;
+
+fn sign(p1: [f32; 2], p2: [f32; 2], p3: [f32; 2]) -> f32 {
+    (p1[0] - p3[0]) * (p2[1] - p3[1]) - (p2[0] - p3[0]) * (p1[1] - p3[1])
+}
+
+impl Rasterizer for PerPixelRasterizer {
+    fn rasterize(&self, tri: &[[f32; 2]], clip: [u32; 4], plot: &mut dyn FnMut(u32, u32, f32)) {
+        for y in clip[1]..clip[1] + clip[3] {
+            for x in clip[0]..clip[0] + clip[2] {
+                let point = [x as f32, y as f32];
+                let b1 = sign(point, tri[0], tri[1]) < 0.0;
+                let b2 = sign(point, tri[1], tri[2]) < 0.0;
+                let b3 = sign(point, tri[2], tri[0]) < 0.0;
+                if b1 == b2 && b2 == b3 {
+                    plot(x, y, 1.0);
+                }
+            }
+        }
+    }
+}
+
+fn rasterize(c: &mut Criterion) {
+    let tri = [[10.0, 10.0], [1000.0, 40.0], [300.0, 1010.0]];
+    let clip = [0, 0, SIZE, SIZE];
+    let rasterizers: [(&str, &dyn Rasterizer); 2] = [
+        ("rasterize/scanline", &ScanlineRasterizer),
+        ("rasterize/per_pixel", &PerPixelRasterizer),
+    ];
+    for (name, rasterizer) in rasterizers {
+        c.bench_function(name, |b| {
+            b.iter(|| {
+                let mut covered = 0u32;
+                rasterizer.rasterize(black_box(&tri), clip, &mut |_, _, _| covered += 1);
+                covered
+            })
+        });
+    }
+}
+
+fn fill(c: &mut Criterion) {
+    let mut scanline = RenderBuffer::new(SIZE, SIZE);
+    let mut per_pixel = RenderBuffer::new(SIZE, SIZE).with_rasterizer(PerPixelRasterizer);
+    for (name, buffer) in [
+        ("fill/scanline", &mut scanline),
+        ("fill/per_pixel", &mut per_pixel),
+    ] {
+        c.bench_function(name, |b| {
+            b.iter(|| {
+                rectangle(
+                    [0.2, 0.4, 0.8, 1.0],
+                    [0.0, 0.0, f64::from(SIZE), f64::from(SIZE)],
+                    Context::new().transform,
+                    &mut *buffer,
+                )
+            })
+        });
+    }
+}
+
+criterion_group!(benches, rasterize, fill);
+criterion_main!(benches);
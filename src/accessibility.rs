@@ -0,0 +1,137 @@
+use graphics::character::CharacterCache;
+
+use crate::{luminance, srgb_to_linear, RenderBuffer};
+
+/// A WCAG conformance level for text contrast.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WcagLevel {
+    /// Level AA: 4.5:1 for normal text, 3:1 for large text
+    AA,
+    /// Level AAA: 7:1 for normal text, 4.5:1 for large text
+    AAA,
+}
+
+impl WcagLevel {
+    /// Get the minimum contrast ratio required by this level.
+    pub fn required_ratio(self, large_text: bool) -> f32 {
+        match (self, large_text) {
+            (WcagLevel::AA, false) => 4.5,
+            (WcagLevel::AA, true) => 3.0,
+            (WcagLevel::AAA, false) => 7.0,
+            (WcagLevel::AAA, true) => 4.5,
+        }
+    }
+}
+
+/// The result of checking the contrast of a region against a WCAG level.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContrastCheck {
+    /// The measured contrast ratio, from `1.0` to `21.0`
+    pub ratio: f32,
+    /// The minimum ratio required to pass
+    pub required: f32,
+    /// Whether the measured ratio meets the required ratio
+    pub passes: bool,
+}
+
+impl ContrastCheck {
+    fn new(ratio: f32, level: WcagLevel, large_text: bool) -> ContrastCheck {
+        let required = level.required_ratio(large_text);
+        ContrastCheck {
+            ratio,
+            required,
+            passes: ratio >= required,
+        }
+    }
+}
+
+/// Get the WCAG relative luminance of a color.
+pub fn relative_luminance(color: [f32; 4]) -> f32 {
+    luminance(color.map(srgb_to_linear))
+}
+
+/// Get the WCAG contrast ratio between two colors, from `1.0` to `21.0`.
+pub fn contrast_ratio_colors(a: [f32; 4], b: [f32; 4]) -> f32 {
+    luminance_contrast(relative_luminance(a), relative_luminance(b))
+}
+
+fn luminance_contrast(a: f32, b: f32) -> f32 {
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+impl RenderBuffer {
+    /**
+    Get the WCAG contrast ratio between the average luminances of two regions.
+
+    Regions are given as `[x, y, width, height]` and are clamped to the buffer.
+    */
+    pub fn contrast_ratio(&self, region_a: [u32; 4], region_b: [u32; 4]) -> f32 {
+        let average = |region| {
+            let lums = self.region_luminances(region);
+            lums.iter().sum::<f32>() / lums.len().max(1) as f32
+        };
+        luminance_contrast(average(region_a), average(region_b))
+    }
+    /**
+    Estimate the contrast ratio of text within a region.
+
+    The region is assumed to contain both text and background pixels. The
+    darkest and lightest 5% of pixels are taken as the two colors, which makes
+    the estimate robust to anti-aliased glyph edges.
+    */
+    pub fn text_contrast(&self, region: [u32; 4]) -> f32 {
+        let mut lums = self.region_luminances(region);
+        if lums.is_empty() {
+            return 1.0;
+        }
+        lums.sort_by(f32::total_cmp);
+        let tail = lums.len() / 20;
+        luminance_contrast(lums[tail], lums[lums.len() - 1 - tail])
+    }
+    /// Check whether text within a region meets the given WCAG level.
+    pub fn check_contrast(
+        &self,
+        region: [u32; 4],
+        level: WcagLevel,
+        large_text: bool,
+    ) -> ContrastCheck {
+        ContrastCheck::new(self.text_contrast(region), level, large_text)
+    }
+    /**
+    Check whether a string drawn with `graphics::text` meets the given WCAG level.
+
+    `pos` is the position the text was drawn at, which is the left end of its
+    baseline. The checked region spans the text's advance width and one font
+    size above and a quarter below the baseline. Text of at least 24 pixels
+    is considered large.
+    */
+    pub fn check_text_contrast<C>(
+        &self,
+        text: &str,
+        font_size: u32,
+        glyphs: &mut C,
+        pos: [f64; 2],
+        level: WcagLevel,
+    ) -> Result<ContrastCheck, C::Error>
+    where
+        C: CharacterCache,
+    {
+        let width = glyphs.width(font_size, text)?;
+        let size = f64::from(font_size);
+        let region = [
+            pos[0].max(0.0) as u32,
+            (pos[1] - size).max(0.0) as u32,
+            width.ceil() as u32,
+            (size * 1.25).ceil() as u32,
+        ];
+        Ok(self.check_contrast(region, level, font_size >= 24))
+    }
+    fn region_luminances(&self, region: [u32; 4]) -> Vec<f32> {
+        let x_end = region[0].saturating_add(region[2]).min(self.width());
+        let y_end = region[1].saturating_add(region[3]).min(self.height());
+        (region[1]..y_end)
+            .flat_map(|y| (region[0]..x_end).map(move |x| (x, y)))
+            .map(|(x, y)| relative_luminance(self.pixel(x, y)))
+            .collect()
+    }
+}
@@ -0,0 +1,317 @@
+use std::{
+    convert::TryFrom,
+    fs::File,
+    io::{self, Write},
+    path::Path,
+};
+
+use graphics::{character::CharacterCache, math::Matrix2d, types::Color};
+#[cfg(feature = "formats_extra")]
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, Frame,
+};
+use image::{
+    codecs::png::PngEncoder,
+    error::{ParameterError, ParameterErrorKind},
+    ColorType, ImageError, ImageResult,
+};
+
+use crate::RenderBuffer;
+
+/**
+A recorder that collects `RenderBuffer` frames and encodes them as an animation.
+
+Animations can be encoded as APNGs, or as GIFs with the `formats_extra` feature.
+All frames should have the same dimensions.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct Animation {
+    frames: Vec<RenderBuffer>,
+    delay_ms: u32,
+}
+
+impl Animation {
+    /// Create a new empty `Animation` that shows each frame for the given number of milliseconds.
+    pub fn new(delay_ms: u32) -> Animation {
+        Animation {
+            frames: Vec::new(),
+            delay_ms,
+        }
+    }
+    /// Create a new `Animation` from a sequence of frames.
+    pub fn from_frames<I>(delay_ms: u32, frames: I) -> Animation
+    where
+        I: IntoIterator<Item = RenderBuffer>,
+    {
+        Animation {
+            frames: frames.into_iter().collect(),
+            delay_ms,
+        }
+    }
+    /// Add a frame to the end of the animation.
+    pub fn push(&mut self, frame: RenderBuffer) {
+        self.frames.push(frame);
+    }
+    /// Add a copy of the buffer's current contents to the end of the animation.
+    pub fn record(&mut self, buffer: &RenderBuffer) {
+        self.frames.push(buffer.clone());
+    }
+    /// Get the recorded frames.
+    pub fn frames(&self) -> &[RenderBuffer] {
+        &self.frames
+    }
+    /// Get the number of recorded frames.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+    /// Check if no frames have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+    /// Get the number of milliseconds each frame is shown for.
+    pub fn delay_ms(&self) -> u32 {
+        self.delay_ms
+    }
+    /// Set the number of milliseconds each frame is shown for.
+    pub fn set_delay_ms(&mut self, delay_ms: u32) {
+        self.delay_ms = delay_ms;
+    }
+    /// Encode the animation as a looping GIF.
+    #[cfg(feature = "formats_extra")]
+    pub fn encode_gif<W: Write>(&self, writer: W) -> ImageResult<()> {
+        let mut encoder = GifEncoder::new(writer);
+        encoder.set_repeat(Repeat::Infinite)?;
+        let delay = Delay::from_numer_denom_ms(self.delay_ms, 1);
+        encoder.encode_frames(
+            self.frames
+                .iter()
+                .map(|frame| Frame::from_parts(frame.straight_pixels().into_owned(), 0, 0, delay)),
+        )
+    }
+    /// Save the animation to a GIF file.
+    #[cfg(feature = "formats_extra")]
+    pub fn save_gif<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        self.encode_gif(File::create(path)?)
+    }
+    /**
+    Encode the animation as a looping APNG.
+
+    Unlike GIFs, APNGs keep full color and alpha. Programs that do not support
+    animated PNGs show the first frame. Returns an error if there are no frames
+    or if the frames do not all have the same dimensions.
+    */
+    pub fn encode_apng<W: Write>(&self, mut writer: W) -> ImageResult<()> {
+        let first = self.frames.first().ok_or_else(|| {
+            parameter_error(ParameterErrorKind::Generic(
+                "an APNG needs at least one frame".into(),
+            ))
+        })?;
+        let (width, height) = first.dimensions();
+        if self
+            .frames
+            .iter()
+            .any(|frame| frame.dimensions() != (width, height))
+        {
+            return Err(parameter_error(ParameterErrorKind::DimensionMismatch));
+        }
+        // Delays are stored as a fraction of a second with 16-bit parts
+        let delay = match u16::try_from(self.delay_ms) {
+            Ok(ms) => [ms, 1000],
+            Err(_) => [(self.delay_ms / 1000).min(u32::from(u16::MAX)) as u16, 1],
+        };
+        writer.write_all(PNG_SIGNATURE)?;
+        let mut sequence = 0u32;
+        for (i, frame) in self.frames.iter().enumerate() {
+            // Encode each frame as a PNG and move its image data into the APNG
+            let mut png = Vec::new();
+            PngEncoder::new(&mut png).encode(
+                &frame.straight_pixels(),
+                width,
+                height,
+                ColorType::Rgba8,
+            )?;
+            let mut header = None;
+            let mut data = Vec::new();
+            for (kind, chunk) in png_chunks(&png) {
+                match kind {
+                    b"IHDR" => header = Some(chunk),
+                    b"IDAT" => data.extend_from_slice(chunk),
+                    _ => {}
+                }
+            }
+            if i == 0 {
+                let header = header.expect("PNGs start with a header");
+                write_chunk(&mut writer, b"IHDR", header)?;
+                let mut control = (self.frames.len() as u32).to_be_bytes().to_vec();
+                // Loop forever
+                control.extend_from_slice(&0u32.to_be_bytes());
+                write_chunk(&mut writer, b"acTL", &control)?;
+            }
+            let mut control = Vec::with_capacity(26);
+            for value in [sequence, width, height, 0, 0] {
+                control.extend_from_slice(&value.to_be_bytes());
+            }
+            for value in delay {
+                control.extend_from_slice(&value.to_be_bytes());
+            }
+            // Do not dispose of or blend with the previous frame
+            control.extend_from_slice(&[0, 0]);
+            write_chunk(&mut writer, b"fcTL", &control)?;
+            sequence += 1;
+            if i == 0 {
+                write_chunk(&mut writer, b"IDAT", &data)?;
+            } else {
+                let mut frame_data = sequence.to_be_bytes().to_vec();
+                frame_data.extend_from_slice(&data);
+                write_chunk(&mut writer, b"fdAT", &frame_data)?;
+                sequence += 1;
+            }
+        }
+        write_chunk(&mut writer, b"IEND", &[])?;
+        Ok(())
+    }
+    /// Save the animation to an APNG file.
+    pub fn save_apng<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        self.encode_apng(File::create(path)?)
+    }
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+fn parameter_error(kind: ParameterErrorKind) -> ImageError {
+    ImageError::Parameter(ParameterError::from_kind(kind))
+}
+
+/// Iterate over the type and data of the chunks of an encoded PNG
+fn png_chunks(png: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut rest = &png[PNG_SIGNATURE.len()..];
+    std::iter::from_fn(move || {
+        if rest.len() < 12 {
+            return None;
+        }
+        let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let (kind, data) = (&rest[4..8], &rest[8..8 + len]);
+        rest = &rest[12 + len..];
+        Some((kind, data))
+    })
+}
+
+/// Write a PNG chunk with its length and checksum
+fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    crc.update(data);
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    writer.write_all(&crc.finalize().to_be_bytes())
+}
+
+impl Extend<RenderBuffer> for Animation {
+    fn extend<I: IntoIterator<Item = RenderBuffer>>(&mut self, iter: I) {
+        self.frames.extend(iter);
+    }
+}
+
+/// How much more of a string is revealed in each frame of a typewriter animation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TypewriterStep {
+    /// Reveal this many more glyphs each frame
+    Glyphs(usize),
+    /// Reveal this percentage of the string's glyphs each frame
+    Percent(f32),
+}
+
+/**
+Render a string progressively, producing one frame per step.
+
+Each frame is a copy of `background` with a longer prefix of `text` drawn on top.
+The last frame always contains the entire string. Empty text produces a single
+frame of just the background.
+*/
+pub fn typewriter_frames<C>(
+    background: &RenderBuffer,
+    text: &str,
+    color: Color,
+    font_size: u32,
+    glyphs: &mut C,
+    transform: Matrix2d,
+    step: TypewriterStep,
+) -> Result<Vec<RenderBuffer>, C::Error>
+where
+    C: CharacterCache<Texture = RenderBuffer>,
+{
+    let ends: Vec<usize> = text.char_indices().map(|(i, c)| i + c.len_utf8()).collect();
+    if ends.is_empty() {
+        return Ok(vec![background.clone()]);
+    }
+    let per_frame = match step {
+        TypewriterStep::Glyphs(n) => n,
+        TypewriterStep::Percent(percent) => (ends.len() as f32 * percent / 100.0).ceil() as usize,
+    }
+    .max(1);
+    let mut frames = Vec::with_capacity(ends.len() / per_frame + 1);
+    let mut shown = 0;
+    while shown < ends.len() {
+        shown = (shown + per_frame).min(ends.len());
+        let mut frame = background.clone();
+        graphics::text(
+            color,
+            font_size,
+            &text[..ends[shown - 1]],
+            glyphs,
+            transform,
+            &mut frame,
+        )?;
+        frames.push(frame);
+    }
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+
+    fn solid(width: u32, height: u32, color: [u8; 4]) -> RenderBuffer {
+        RgbaImage::from_pixel(width, height, Rgba(color)).into()
+    }
+
+    #[test]
+    fn apng_has_a_frame_for_each_buffer() {
+        let first = solid(3, 2, [255, 0, 0, 255]);
+        let animation =
+            Animation::from_frames(40, vec![first.clone(), solid(3, 2, [0, 0, 255, 128])]);
+        let mut apng = Vec::new();
+        animation.encode_apng(&mut apng).unwrap();
+        let chunks: Vec<_> = png_chunks(&apng).collect();
+        let kinds: Vec<_> = chunks.iter().map(|&(kind, _)| kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                &b"IHDR"[..],
+                b"acTL",
+                b"fcTL",
+                b"IDAT",
+                b"fcTL",
+                b"fdAT",
+                b"IEND"
+            ]
+        );
+        // Two frames that loop forever
+        assert_eq!(chunks[1].1, [0, 0, 0, 2, 0, 0, 0, 0]);
+        // Programs without APNG support show the first frame
+        let still = image::load_from_memory(&apng).unwrap().to_rgba8();
+        assert!(still == *first.straight_pixels());
+    }
+
+    #[test]
+    fn apng_needs_frames_of_one_size() {
+        let mut apng = Vec::new();
+        assert!(Animation::new(40).encode_apng(&mut apng).is_err());
+        let animation = Animation::from_frames(40, vec![solid(3, 2, [0; 4]), solid(2, 3, [0; 4])]);
+        assert!(animation.encode_apng(&mut apng).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use std::path::{Path, PathBuf};
+
+use rayon::{prelude::*, ThreadPoolBuilder};
+
+use crate::{AssetCache, RenderLimits, Scene, SceneError};
+
+/// A single job for a `BatchRenderer`.
+#[derive(Debug, Clone)]
+pub enum BatchJob {
+    /// Render a scene, resolving relative asset paths against a base directory
+    Scene {
+        /// The scene
+        scene: Scene,
+        /// The directory that asset paths are relative to
+        base_dir: PathBuf,
+        /// The path to save the rendered image to
+        output: PathBuf,
+    },
+    /// Load a scene file when the job runs and render it
+    File {
+        /// The path to the `.json` or `.ron` scene file
+        scene: PathBuf,
+        /// The path to save the rendered image to
+        output: PathBuf,
+    },
+}
+
+/**
+Renders many scenes to image files in parallel.
+
+Jobs share an `AssetCache`, so images and fonts used by many scenes are only
+loaded once. Scene files are loaded only when their job runs, and at most one
+buffer exists per thread at a time, which keeps memory use bounded when baking
+thousands of images.
+*/
+#[derive(Debug, Default)]
+pub struct BatchRenderer {
+    jobs: Vec<BatchJob>,
+    threads: usize,
+    cache: AssetCache,
+    limits: RenderLimits,
+}
+
+impl BatchRenderer {
+    /// Create a new `BatchRenderer` with no jobs that uses one thread per core.
+    pub fn new() -> Self {
+        BatchRenderer::default()
+    }
+    /// Set the number of threads to render with. `0` uses one thread per core.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+    /// Set the cache that jobs load images and fonts through.
+    ///
+    /// Use `AssetCache::with_limit` to bound the memory used by cached assets.
+    pub fn cache(mut self, cache: AssetCache) -> Self {
+        self.cache = cache;
+        self
+    }
+    /// Set the limits that every job is rendered with.
+    pub fn limits(mut self, limits: RenderLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+    /// Add a job that renders a scene.
+    pub fn add<B, O>(&mut self, scene: Scene, base_dir: B, output: O)
+    where
+        B: Into<PathBuf>,
+        O: Into<PathBuf>,
+    {
+        self.jobs.push(BatchJob::Scene {
+            scene,
+            base_dir: base_dir.into(),
+            output: output.into(),
+        });
+    }
+    /// Add a job that loads a scene file and renders it.
+    pub fn add_file<S, O>(&mut self, scene: S, output: O)
+    where
+        S: Into<PathBuf>,
+        O: Into<PathBuf>,
+    {
+        self.jobs.push(BatchJob::File {
+            scene: scene.into(),
+            output: output.into(),
+        });
+    }
+    /// Get the jobs.
+    pub fn jobs(&self) -> &[BatchJob] {
+        &self.jobs
+    }
+    /**
+    Run all of the jobs.
+
+    The result of each job is returned in the order the jobs were added.
+    A failed job does not stop the others.
+    */
+    pub fn run(&self) -> Vec<Result<(), SceneError>> {
+        let run_all = || self.jobs.par_iter().map(|job| self.run_job(job)).collect();
+        match ThreadPoolBuilder::new().num_threads(self.threads).build() {
+            Ok(pool) => pool.install(run_all),
+            Err(_) => run_all(),
+        }
+    }
+    fn run_job(&self, job: &BatchJob) -> Result<(), SceneError> {
+        let (buffer, output) = match job {
+            BatchJob::Scene {
+                scene,
+                base_dir,
+                output,
+            } => (
+                scene.render_with_limits(base_dir, &self.cache, &self.limits)?,
+                output,
+            ),
+            BatchJob::File { scene, output } => {
+                let base_dir = scene.parent().unwrap_or_else(|| Path::new(""));
+                let buffer =
+                    Scene::open(scene)?.render_with_limits(base_dir, &self.cache, &self.limits)?;
+                (buffer, output)
+            }
+        };
+        buffer
+            .save(output)
+            .map_err(|e| SceneError::Image(output.clone(), e))
+    }
+}
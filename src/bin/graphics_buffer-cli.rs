@@ -0,0 +1,111 @@
+use std::{collections::HashMap, env, error::Error, path::Path, process};
+
+use graphics::{text, Transformed};
+use graphics_buffer::*;
+use image::imageops::{self, FilterType};
+
+const USAGE: &str = "\
+Usage:
+    graphics_buffer-cli render <scene> <output> [<name>=<value>]...
+    graphics_buffer-cli resize <input> <width> <height> <output>
+    graphics_buffer-cli annotate <input> <font> <x> <y> <size> <text> <output>
+    graphics_buffer-cli diff <a> <b> <output>
+    graphics_buffer-cli montage <columns> <output> <inputs>...
+
+Scenes are .json or .ron files. Template variables like {{title}} in a scene
+are bound with <name>=<value> arguments. The output format is chosen by its extension.";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(error) = run(&args) {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["render", scene, output, bindings @ ..] => {
+            let mut variables = HashMap::new();
+            for binding in bindings {
+                let mut parts = binding.splitn(2, '=');
+                let (name, value) = match (parts.next(), parts.next()) {
+                    (Some(name), Some(value)) => (name, value),
+                    _ => return Err(format!("Expected <name>=<value>, got {}", binding).into()),
+                };
+                variables.insert(name.to_string(), value.to_string());
+            }
+            let path = Path::new(scene);
+            let mut scene = Scene::open(path)?;
+            // Scenes without bindings are rendered as they are, so text that
+            // happens to contain braces is left alone
+            if !variables.is_empty() {
+                scene = scene.bind(&variables)?;
+            }
+            scene
+                .render(path.parent().unwrap_or_else(|| Path::new("")))?
+                .save(output)?;
+        }
+        ["resize", input, width, height, output] => {
+            let buffer = RenderBuffer::open(input)?;
+            let resized = imageops::resize(
+                &*buffer,
+                width.parse()?,
+                height.parse()?,
+                FilterType::Lanczos3,
+            );
+            RenderBuffer::from(resized).save(output)?;
+        }
+        ["annotate", input, font, x, y, size, annotation, output] => {
+            let mut buffer = RenderBuffer::open(input)?;
+            let font_data = std::fs::read(font)?;
+            let mut glyphs = buffer_glyphs_from_bytes(&font_data)?;
+            text(
+                [1.0; 4],
+                size.parse()?,
+                annotation,
+                &mut glyphs,
+                IDENTITY.trans(x.parse()?, y.parse()?),
+                &mut buffer,
+            )?;
+            buffer.save(output)?;
+        }
+        ["diff", a, b, output] => {
+            let a = RenderBuffer::open(a)?;
+            let b = RenderBuffer::open(b)?;
+            if a.dimensions() != b.dimensions() {
+                return Err(format!(
+                    "Images have different sizes: {:?} and {:?}",
+                    a.dimensions(),
+                    b.dimensions()
+                )
+                .into());
+            }
+            let mut diff = RenderBuffer::new(a.width(), a.height());
+            let mut differing = 0;
+            for (x, y, pa) in a.enumerate_pixels() {
+                let pb = b.get_pixel(x, y);
+                let mut d = [0.0; 4];
+                for ((channel, ca), cb) in d.iter_mut().zip(pa.0).zip(pb.0) {
+                    *channel = f32::from(ca.abs_diff(cb)) / 255.0;
+                }
+                if pa != pb {
+                    differing += 1;
+                }
+                diff.set_pixel(x, y, [d[0].max(d[3]), d[1].max(d[3]), d[2].max(d[3]), 1.0]);
+            }
+            println!("{} pixels differ", differing);
+            diff.save(output)?;
+        }
+        ["montage", columns, output, inputs @ ..] if !inputs.is_empty() => {
+            let frames = inputs
+                .iter()
+                .map(|input| RenderBuffer::open(Path::new(input)))
+                .collect::<Result<Vec<_>, _>>()?;
+            bake_grid(&frames, columns.parse()?).save(output)?;
+        }
+        _ => return Err(USAGE.into()),
+    }
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use std::error;
+
+use image::RgbaImage;
+
+use crate::{Error, RenderBuffer};
+
+impl RenderBuffer {
+    /// Capture the contents of the primary monitor.
+    pub fn capture_screen() -> Result<RenderBuffer, Box<dyn error::Error>> {
+        let monitors = xcap::Monitor::all()?;
+        let mut primary = None;
+        for monitor in monitors {
+            if monitor.is_primary()? || primary.is_none() {
+                primary = Some(monitor);
+            }
+        }
+        let monitor =
+            primary.ok_or_else(|| Error::CaptureTarget("no monitors were found".into()))?;
+        convert_capture(monitor.capture_image()?)
+    }
+    /// Capture the contents of the first window whose title contains the given string.
+    pub fn capture_window(title: &str) -> Result<RenderBuffer, Box<dyn error::Error>> {
+        for window in xcap::Window::all()? {
+            if window.title()?.contains(title) && !window.is_minimized()? {
+                return convert_capture(window.capture_image()?);
+            }
+        }
+        Err(Error::CaptureTarget(format!("no window with a title containing {:?}", title)).into())
+    }
+}
+
+/// Convert an image captured by `xcap`, which may use a different version of `image`
+fn convert_capture(
+    captured: xcap::image::RgbaImage,
+) -> Result<RenderBuffer, Box<dyn error::Error>> {
+    let (width, height) = captured.dimensions();
+    let len = captured.len();
+    RgbaImage::from_raw(width, height, captured.into_raw())
+        .map(RenderBuffer::from)
+        .ok_or_else(|| Error::SizeMismatch(len, (width * height) as usize).into())
+}
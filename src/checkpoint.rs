@@ -0,0 +1,192 @@
+use std::{collections::HashMap, mem};
+
+use crate::{memory::Allocation, BufferPixel, PixelStore, RenderBuffer, TILE_SIZE};
+
+/**
+A snapshot of everything about a `RenderBuffer` that affects rendering.
+
+This includes the pixels, the stencil plane used for clipping, the coverage
+and object ID planes, and all rendering settings. Capturing a state before
+speculative drawing makes it easy to throw that drawing away, for example
+to try a layout and revert it if it overflows.
+
+The snapshot is a full copy, so it counts against the `MemoryBudget` like
+any other buffer.
+*/
+#[derive(Debug, Clone)]
+pub struct RendererState {
+    buffer: RenderBuffer,
+}
+
+impl RendererState {
+    /// Capture the current state of a buffer.
+    pub fn capture(buffer: &RenderBuffer) -> RendererState {
+        RendererState {
+            buffer: buffer.clone(),
+        }
+    }
+    /**
+    Restore a buffer to this state.
+
+    The state can be restored any number of times, and to any buffer, which
+    takes on the captured size if it differs.
+    */
+    pub fn restore(&self, buffer: &mut RenderBuffer) {
+        buffer.clone_from(&self.buffer);
+        buffer.mark_all_dirty();
+    }
+    /// Restore a buffer to this state, consuming the state to avoid a copy.
+    pub fn restore_into(self, buffer: &mut RenderBuffer) {
+        *buffer = self.buffer;
+        buffer.mark_all_dirty();
+    }
+    /// Get the width and height of the captured buffer.
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.buffer.dimensions()
+    }
+    /// Get the approximate number of bytes of heap memory used by the snapshot.
+    pub fn memory_usage(&self) -> usize {
+        self.buffer.memory_usage()
+    }
+}
+
+impl RenderBuffer {
+    /**
+    Run some drawing code as a transaction, rolling the buffer back if it fails.
+
+    If `draw` returns an error, the buffer is restored to the state it was in
+    before, including its pixels, planes and settings, and the error is
+    returned. This is useful when rendering user-provided templates that may
+    fail halfway through.
+
+    Only the pixels that `draw` changes are backed up, a tile at a time as
+    they are first changed, so a transaction that touches a small part of a
+    large buffer needs little extra memory. Coverage, stencil and ID planes
+    are copied whole if they are allocated. The dirty rectangle only grows by
+    what a successful transaction changed.
+
+    Replacing the whole buffer inside `draw`, such as with
+    `RendererState::restore`, cannot be rolled back.
+    */
+    pub fn transaction<T, E, F>(&mut self, draw: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut RenderBuffer) -> Result<T, E>,
+    {
+        let dirty = self.take_dirty_rect();
+        // An enclosing transaction's journal is set aside and merged back after
+        let outer = self.journal.take();
+        // Back up everything but the pixels
+        let pixels = mem::take(&mut self.inner);
+        let mut state = self.clone();
+        state._allocation = Allocation::new(0);
+        self.inner = pixels;
+        self.journal = Some(PixelJournal::default());
+        let result = draw(self);
+        let journal = self.journal.take();
+        let changed = self.take_dirty_rect();
+        if result.is_err() {
+            if let Some(journal) = journal {
+                journal.restore(&mut self.inner);
+            }
+            mem::swap(&mut state.inner, &mut self.inner);
+            mem::swap(&mut state._allocation, &mut self._allocation);
+            *self = state;
+            self.track_memory();
+            self.journal = outer;
+            self.dirty = dirty;
+        } else {
+            self.journal = match (outer, journal) {
+                (Some(outer), Some(journal)) => Some(outer.merge(journal)),
+                (outer, _) => outer,
+            };
+            self.dirty = dirty;
+            if let Some(changed) = changed {
+                self.mark_dirty(changed);
+            }
+        }
+        result
+    }
+}
+
+/// The original pixels of the tiles changed during a transaction
+#[derive(Debug, Clone)]
+pub(crate) struct PixelJournal<P: BufferPixel> {
+    dimensions: (u32, u32),
+    /// Saved tiles by their column and row, with their pixels packed row by row
+    tiles: HashMap<(u32, u32), Vec<P::Subpixel>>,
+}
+
+impl<P: BufferPixel> Default for PixelJournal<P> {
+    fn default() -> Self {
+        PixelJournal {
+            dimensions: (0, 0),
+            tiles: HashMap::new(),
+        }
+    }
+}
+
+impl<P: BufferPixel> PixelJournal<P> {
+    /// Save the tiles that a rectangle touches, if they have not been saved yet
+    ///
+    /// This must be called before the pixels in the rectangle are changed.
+    pub(crate) fn save(&mut self, pixels: &dyn PixelStore<P>, rect: [u32; 4]) {
+        let (width, height) = pixels.dimensions();
+        if self.tiles.is_empty() {
+            self.dimensions = (width, height);
+        }
+        let right = (rect[0].saturating_add(rect[2])).min(width);
+        let bottom = (rect[1].saturating_add(rect[3])).min(height);
+        if rect[0] >= right || rect[1] >= bottom {
+            return;
+        }
+        let (stride, channels) = (pixels.stride(), usize::from(P::CHANNEL_COUNT));
+        let bytes = pixels.bytes();
+        for ty in rect[1] / TILE_SIZE..=(bottom - 1) / TILE_SIZE {
+            for tx in rect[0] / TILE_SIZE..=(right - 1) / TILE_SIZE {
+                self.tiles.entry((tx, ty)).or_insert_with(|| {
+                    let [x, y, w, h] = tile_rect(tx, ty, (width, height));
+                    (y..y + h)
+                        .flat_map(move |row| {
+                            let start = row as usize * stride + x as usize * channels;
+                            bytes[start..start + w as usize * channels].iter().copied()
+                        })
+                        .collect()
+                });
+            }
+        }
+    }
+    /// Write the saved tiles back
+    fn restore(self, pixels: &mut dyn PixelStore<P>) {
+        let (width, height) = pixels.dimensions();
+        if (width, height) != self.dimensions {
+            return;
+        }
+        let (stride, channels) = (pixels.stride(), usize::from(P::CHANNEL_COUNT));
+        let bytes = pixels.bytes_mut();
+        for ((tx, ty), saved) in self.tiles {
+            let [x, y, w, h] = tile_rect(tx, ty, (width, height));
+            let row_len = w as usize * channels;
+            for (row, saved) in (y..y + h).zip(saved.chunks_exact(row_len)) {
+                let start = row as usize * stride + x as usize * channels;
+                bytes[start..start + row_len].copy_from_slice(saved);
+            }
+        }
+    }
+    /// Combine with the journal of a transaction nested in this one
+    fn merge(mut self, nested: PixelJournal<P>) -> Self {
+        if self.tiles.is_empty() {
+            return nested;
+        }
+        // Tiles already saved here hold older pixels than the nested journal's
+        for (tile, saved) in nested.tiles {
+            self.tiles.entry(tile).or_insert(saved);
+        }
+        self
+    }
+}
+
+/// Get the rectangle of a tile, clipped to an image of the given size
+fn tile_rect(tx: u32, ty: u32, (width, height): (u32, u32)) -> [u32; 4] {
+    let (x, y) = (tx * TILE_SIZE, ty * TILE_SIZE);
+    [x, y, TILE_SIZE.min(width - x), TILE_SIZE.min(height - y)]
+}
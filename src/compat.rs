@@ -0,0 +1,225 @@
+/*!
+Trait impls for newer versions of Piston's graphics and texture libraries.
+
+These forward to the same drawing operations as the impls for the versions the
+crate is built against, so a `RenderBuffer` can be used as a render target and
+glyph cache texture by downstreams on either Piston stack.
+*/
+
+use graphics::draw_state::{Blend as CurrentBlend, Stencil as CurrentStencil};
+use graphics_0_44::{
+    draw_state::{Blend, DrawState, Stencil},
+    types::Color,
+    Graphics, ImageSize,
+};
+use texture_0_9::{CreateTexture, Filter, Format, TextureOp, TextureSettings, UpdateTexture, Wrap};
+
+use crate::{
+    draw_state::DrawParams, BufferPixel, Error, PixelStore, RenderBuffer, RenderTarget,
+    TextureFilter, Viewport, WrapMode,
+};
+
+/// Convert a newer `DrawState` to the parts used for drawing
+fn draw_params(draw_state: &DrawState) -> DrawParams {
+    DrawParams {
+        blend: draw_state.blend.map(|blend| match blend {
+            Blend::Alpha => CurrentBlend::Alpha,
+            Blend::Add => CurrentBlend::Add,
+            Blend::Lighter => CurrentBlend::Lighter,
+            Blend::Multiply => CurrentBlend::Multiply,
+            Blend::Invert => CurrentBlend::Invert,
+        }),
+        stencil: draw_state.stencil.map(|stencil| match stencil {
+            Stencil::Clip(value) => CurrentStencil::Clip(value),
+            Stencil::Inside(value) => CurrentStencil::Inside(value),
+            Stencil::Outside(value) => CurrentStencil::Outside(value),
+            Stencil::Increment => CurrentStencil::Increment,
+        }),
+        scissor: draw_state.scissor,
+    }
+}
+
+/// Convert a newer texture filter
+fn texture_filter(filter: Filter) -> TextureFilter {
+    match filter {
+        Filter::Nearest => TextureFilter::Nearest,
+        Filter::Linear => TextureFilter::Linear,
+    }
+}
+
+/// Convert a newer texture wrap mode
+fn wrap_mode(wrap: Wrap) -> WrapMode {
+    match wrap {
+        Wrap::ClampToEdge | Wrap::ClampToBorder => WrapMode::ClampToEdge,
+        Wrap::Repeat => WrapMode::Repeat,
+        Wrap::MirroredRepeat => WrapMode::MirroredRepeat,
+    }
+}
+
+impl<P: BufferPixel> ImageSize for RenderBuffer<P> {
+    fn get_size(&self) -> (u32, u32) {
+        self.inner.dimensions()
+    }
+}
+
+impl TextureOp<()> for RenderBuffer {
+    type Error = Error;
+}
+
+impl CreateTexture<()> for RenderBuffer {
+    fn create<S: Into<[u32; 2]>>(
+        _factory: &mut (),
+        _format: Format,
+        memory: &[u8],
+        size: S,
+        settings: &TextureSettings,
+    ) -> Result<Self, Error> {
+        let mut buffer = RenderBuffer::from_texture_memory(memory, size.into())?;
+        buffer.set_min_filter(texture_filter(settings.get_min()));
+        buffer.set_mag_filter(texture_filter(settings.get_mag()));
+        buffer.set_wrap(
+            wrap_mode(settings.get_wrap_u()),
+            wrap_mode(settings.get_wrap_v()),
+        );
+        Ok(buffer)
+    }
+}
+
+impl UpdateTexture<()> for RenderBuffer {
+    fn update<O, S>(
+        &mut self,
+        _factory: &mut (),
+        _format: Format,
+        memory: &[u8],
+        offset: O,
+        size: S,
+    ) -> Result<(), Error>
+    where
+        O: Into<[u32; 2]>,
+        S: Into<[u32; 2]>,
+    {
+        self.update_texture_memory(memory, offset.into(), size.into())
+    }
+}
+
+impl<P: BufferPixel> Graphics for RenderBuffer<P> {
+    type Texture = RenderBuffer;
+    fn clear_color(&mut self, color: Color) {
+        self.clear_pixels(color);
+    }
+    fn clear_stencil(&mut self, value: u8) {
+        self.clear_stencil_plane(value);
+    }
+    fn tri_list<F>(&mut self, draw_state: &DrawState, color: &[f32; 4], f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]])),
+    {
+        self.draw_tris(draw_params(draw_state), color, f);
+    }
+    fn tri_list_uv<F>(
+        &mut self,
+        draw_state: &DrawState,
+        color: &[f32; 4],
+        texture: &Self::Texture,
+        f: F,
+    ) where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]])),
+    {
+        self.draw_tris_uv(draw_params(draw_state), color, texture, f);
+    }
+    fn tri_list_c<F>(&mut self, draw_state: &DrawState, f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 4]])),
+    {
+        self.draw_tris_c(draw_params(draw_state), f);
+    }
+    fn tri_list_uv_c<F>(&mut self, draw_state: &DrawState, texture: &Self::Texture, f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]], &[[f32; 4]])),
+    {
+        self.draw_tris_uv_c(draw_params(draw_state), texture, f);
+    }
+}
+
+impl Graphics for Viewport<'_> {
+    type Texture = RenderBuffer;
+    fn clear_color(&mut self, color: Color) {
+        self.clear_pixels(color);
+    }
+    fn clear_stencil(&mut self, value: u8) {
+        self.clear_stencil_plane(value);
+    }
+    fn tri_list<F>(&mut self, draw_state: &DrawState, color: &[f32; 4], f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]])),
+    {
+        self.draw_tris(draw_params(draw_state), color, f);
+    }
+    fn tri_list_uv<F>(
+        &mut self,
+        draw_state: &DrawState,
+        color: &[f32; 4],
+        texture: &Self::Texture,
+        f: F,
+    ) where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]])),
+    {
+        self.draw_tris_uv(draw_params(draw_state), color, texture, f);
+    }
+    fn tri_list_c<F>(&mut self, draw_state: &DrawState, f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 4]])),
+    {
+        self.draw_tris_c(draw_params(draw_state), f);
+    }
+    fn tri_list_uv_c<F>(&mut self, draw_state: &DrawState, texture: &Self::Texture, f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]], &[[f32; 4]])),
+    {
+        self.draw_tris_uv_c(draw_params(draw_state), texture, f);
+    }
+}
+
+impl<S: PixelStore> Graphics for RenderTarget<S> {
+    type Texture = RenderBuffer;
+    fn clear_color(&mut self, color: Color) {
+        self.canvas().clear(color);
+    }
+    fn clear_stencil(&mut self, value: u8) {
+        self.clear_stencil_plane(value);
+    }
+    fn tri_list<F>(&mut self, draw_state: &DrawState, color: &[f32; 4], f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]])),
+    {
+        let params = draw_params(draw_state);
+        self.draw_canvas(params).draw_tris(params, color, f);
+    }
+    fn tri_list_uv<F>(
+        &mut self,
+        draw_state: &DrawState,
+        color: &[f32; 4],
+        texture: &Self::Texture,
+        f: F,
+    ) where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]])),
+    {
+        let params = draw_params(draw_state);
+        self.draw_canvas(params)
+            .draw_tris_uv(params, color, texture, f);
+    }
+    fn tri_list_c<F>(&mut self, draw_state: &DrawState, f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 4]])),
+    {
+        let params = draw_params(draw_state);
+        self.draw_canvas(params).draw_tris_c(params, f);
+    }
+    fn tri_list_uv_c<F>(&mut self, draw_state: &DrawState, texture: &Self::Texture, f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]], &[[f32; 4]])),
+    {
+        let params = draw_params(draw_state);
+        self.draw_canvas(params).draw_tris_uv_c(params, texture, f);
+    }
+}
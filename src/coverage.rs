@@ -0,0 +1,89 @@
+use image::{GrayImage, Luma, Rgba, RgbaImage};
+
+use crate::{color_f32_rgba, BufferPixel, RenderBuffer};
+
+/// A single channel 8-bit image
+pub type GrayBuffer = GrayImage;
+
+impl<P: BufferPixel> RenderBuffer<P> {
+    /**
+    Enable or disable tracking of anti-aliased coverage.
+
+    While enabled, the coverage of every triangle drawn is accumulated into a
+    separate plane, which can be retrieved with `coverage_buffer`. Disabling
+    tracking discards the plane.
+    */
+    pub fn set_coverage_tracking(&mut self, enabled: bool) {
+        self.coverage = if enabled {
+            Some(GrayImage::new(self.inner.width(), self.inner.height()))
+        } else {
+            None
+        };
+        self.track_memory();
+    }
+    /// Check if coverage tracking is enabled.
+    pub fn coverage_tracking(&self) -> bool {
+        self.coverage.is_some()
+    }
+    /**
+    Get the accumulated coverage of everything drawn since tracking was enabled
+    or the buffer was last cleared.
+
+    A value of 255 means a pixel was fully covered, and 0 means it was not touched.
+    External compositors can use this as a mask to re-composite content over a
+    different background without halos. If tracking is disabled, the result is
+    all zeros.
+    */
+    pub fn coverage_buffer(&self) -> GrayBuffer {
+        self.coverage
+            .clone()
+            .unwrap_or_else(|| GrayImage::new(self.inner.width(), self.inner.height()))
+    }
+    /**
+    Create a buffer with the given RGB color everywhere and the accumulated
+    coverage in the alpha channel.
+
+    This is useful for baking monochrome glyphs and icons into assets that
+    engines tint at runtime. Use `[1.0, 1.0, 1.0]` for plain white. If
+    tracking is disabled, the result is fully transparent.
+    */
+    pub fn coverage_to_alpha(&self, color: [f32; 3]) -> RenderBuffer {
+        let rgb = color_f32_rgba(&[color[0], color[1], color[2], 1.0]);
+        let coverage = self.coverage_buffer();
+        RgbaImage::from_fn(coverage.width(), coverage.height(), |x, y| {
+            Rgba([rgb[0], rgb[1], rgb[2], coverage.get_pixel(x, y)[0]])
+        })
+        .into()
+    }
+    /// Reset the accumulated coverage to zero, if tracking is enabled.
+    pub fn reset_coverage(&mut self) {
+        if let Some(coverage) = &mut self.coverage {
+            for pixel in coverage.pixels_mut() {
+                *pixel = Luma([0]);
+            }
+        }
+    }
+}
+
+/**
+Draw something into a new buffer with coverage tracking and export its
+coverage as the alpha channel of a solid color.
+
+The colors used while drawing do not matter, only which pixels are covered and
+by how much. See `RenderBuffer::coverage_to_alpha`.
+*/
+pub fn bake_alpha_mask<F>(width: u32, height: u32, color: [f32; 3], draw: F) -> RenderBuffer
+where
+    F: FnOnce(&mut RenderBuffer),
+{
+    let mut buffer = RenderBuffer::new(width, height);
+    buffer.set_coverage_tracking(true);
+    draw(&mut buffer);
+    buffer.coverage_to_alpha(color)
+}
+
+/// Combine existing coverage with new coverage as the union of the two
+pub(crate) fn accumulate_coverage(existing: u8, coverage: f32) -> u8 {
+    let existing = f32::from(existing) / 255.0;
+    ((existing + coverage * (1.0 - existing)) * 255.0).round() as u8
+}
@@ -0,0 +1,195 @@
+use std::{fmt, path::Path};
+
+use image::{ImageBuffer, ImageResult, Luma, LumaA, Pixel, Primitive, Rgb, Rgba, RgbaImage};
+
+use crate::{luminance, RenderBuffer, Tonemap};
+
+/// An image with 16-bit RGBA pixels
+pub type Rgba16Image = ImageBuffer<Rgba<u16>, Vec<u16>>;
+
+/**
+A type that the channels of a pixel can be stored as.
+
+Colors are drawn and blended as `f32`s, where `1.0` is full intensity, and
+only converted to the channel type when stored. Integer channels are clamped
+to `[0, 1]`.
+*/
+pub trait Depth: Primitive + fmt::Debug + Send + Sync + 'static {
+    /// Whether values above full intensity can be stored.
+    ///
+    /// Colors drawn into such channels are not clipped to `[0, 1]` when blended.
+    const UNBOUNDED: bool = false;
+    /// Convert a channel value, where `1.0` is full intensity.
+    fn from_f32(value: f32) -> Self;
+    /// Convert to a channel value, where `1.0` is full intensity.
+    fn to_f32(self) -> f32;
+}
+
+impl Depth for u8 {
+    // Truncate rather than round, so 8-bit buffers keep the pixel values
+    // they had before other depths were added
+    fn from_f32(value: f32) -> Self {
+        (value.clamp(0.0, 1.0) * 255.0) as u8
+    }
+    fn to_f32(self) -> f32 {
+        f32::from(self) / 255.0
+    }
+}
+
+impl Depth for u16 {
+    fn from_f32(value: f32) -> Self {
+        (value.clamp(0.0, 1.0) * 65535.0).round() as u16
+    }
+    fn to_f32(self) -> f32 {
+        f32::from(self) / 65535.0
+    }
+}
+
+impl Depth for f32 {
+    const UNBOUNDED: bool = true;
+    fn from_f32(value: f32) -> Self {
+        value
+    }
+    fn to_f32(self) -> f32 {
+        self
+    }
+}
+
+/// Convert a color to a pixel of any depth
+pub(crate) fn color_to_pixel<T: Depth>(color: &[f32; 4]) -> Rgba<T> {
+    Rgba(color.map(T::from_f32))
+}
+
+/// Convert a pixel of any depth to a color
+pub(crate) fn pixel_to_color<T: Depth>(pixel: Rgba<T>) -> [f32; 4] {
+    pixel.0.map(Depth::to_f32)
+}
+
+/**
+A pixel layout that a `RenderBuffer` can store.
+
+This is implemented for `Rgba`, `Rgb`, `LumaA` and `Luma` pixels with any
+`Depth` of channel. Pixels without alpha are opaque, and grayscale pixels
+store the luminance of the colors drawn.
+*/
+pub trait BufferPixel: Pixel<Subpixel: Depth> + fmt::Debug + Send + Sync + 'static {
+    /// Convert a color to a pixel.
+    fn from_color(color: &[f32; 4]) -> Self;
+    /// Convert the pixel to a color.
+    fn to_color(self) -> [f32; 4];
+}
+
+impl<T: Depth> BufferPixel for Rgba<T> {
+    fn from_color(color: &[f32; 4]) -> Self {
+        color_to_pixel(color)
+    }
+    fn to_color(self) -> [f32; 4] {
+        pixel_to_color(self)
+    }
+}
+
+impl<T: Depth> BufferPixel for Rgb<T> {
+    fn from_color(color: &[f32; 4]) -> Self {
+        Rgb([color[0], color[1], color[2]].map(T::from_f32))
+    }
+    fn to_color(self) -> [f32; 4] {
+        let [r, g, b] = self.0.map(Depth::to_f32);
+        [r, g, b, 1.0]
+    }
+}
+
+impl<T: Depth> BufferPixel for LumaA<T> {
+    fn from_color(color: &[f32; 4]) -> Self {
+        LumaA([luminance(*color), color[3]].map(T::from_f32))
+    }
+    fn to_color(self) -> [f32; 4] {
+        let [l, a] = self.0.map(Depth::to_f32);
+        [l, l, l, a]
+    }
+}
+
+impl<T: Depth> BufferPixel for Luma<T> {
+    fn from_color(color: &[f32; 4]) -> Self {
+        Luma([T::from_f32(luminance(*color))])
+    }
+    fn to_color(self) -> [f32; 4] {
+        let l = Depth::to_f32(self.0[0]);
+        [l, l, l, 1.0]
+    }
+}
+
+/// An image with pixels of a `RenderBuffer`
+pub type PixelImage<P> = ImageBuffer<P, Vec<<P as Pixel>::Subpixel>>;
+
+/**
+A render target with pixels of any layout and depth.
+
+This is a `RenderBuffer` with a `BufferPixel` other than 8-bit RGBA, so
+grayscale, RGB and higher precision targets all share one rasterizer.
+*/
+pub type PixelBuffer<P> = RenderBuffer<P>;
+
+/**
+A render target with 16 bits per channel.
+
+This suits scientific and print rendering, and can be saved to 16-bit PNGs.
+*/
+pub type RenderBuffer16 = RenderBuffer<Rgba<u16>>;
+
+impl RenderBuffer16 {
+    /**
+    Save the buffer to a file, with the format deduced from the extension.
+
+    Formats that support 16 bits per channel, like PNG and TIFF, keep the
+    full precision of the pixels.
+    */
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        self.inner.save(path)
+    }
+}
+
+impl<P: BufferPixel> RenderBuffer<P> {
+    /// Take the pixels out of the buffer.
+    pub fn into_image(self) -> PixelImage<P> {
+        self.inner
+    }
+    /// Convert the buffer to 8 bits per channel, bringing colors into range with a tonemap.
+    pub fn tonemap(&self, tonemap: Tonemap) -> RenderBuffer {
+        let (width, height) = self.inner.dimensions();
+        RgbaImage::from_fn(width, height, |x, y| {
+            let [r, g, b, a] = self.pixel(x, y);
+            color_to_pixel(&[
+                tonemap.apply(r),
+                tonemap.apply(g),
+                tonemap.apply(b),
+                a.clamp(0.0, 1.0),
+            ])
+        })
+        .into()
+    }
+}
+
+impl<P: BufferPixel> From<&RenderBuffer> for RenderBuffer<P> {
+    fn from(buffer: &RenderBuffer) -> Self {
+        let (width, height) = buffer.dimensions();
+        PixelImage::<P>::from_fn(width, height, |x, y| P::from_color(&buffer.pixel(x, y))).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_conversions_are_pinned() {
+        let values = [-1.0, 0.0, 0.25, 0.5, 0.999, 1.0, 2.0];
+        assert_eq!(values.map(u8::from_f32), [0, 0, 63, 127, 254, 255, 255]);
+        assert_eq!(
+            values.map(u16::from_f32),
+            [0, 0, 16384, 32768, 65469, 65535, 65535]
+        );
+        assert_eq!(f32::from_f32(2.0), 2.0);
+        assert_eq!(Depth::to_f32(255u8), 1.0);
+        assert_eq!(Depth::to_f32(65535u16), 1.0);
+    }
+}
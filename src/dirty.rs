@@ -0,0 +1,26 @@
+use crate::RenderBuffer;
+
+impl RenderBuffer {
+    /**
+    Get the region changed since the dirty rectangle was last taken, without resetting it.
+
+    The region is given as `[x, y, width, height]` in the rows of the stored
+    image, so that it can be used directly to upload part of a texture. It is
+    the smallest rectangle containing every change, and may include some
+    pixels that did not actually change. `None` means nothing has changed.
+
+    A new buffer starts out entirely dirty.
+    */
+    pub fn dirty_rect(&self) -> Option<[u32; 4]> {
+        self.dirty
+    }
+    /**
+    Get the region changed since the dirty rectangle was last taken, and reset it.
+
+    This lets interactive tools upload or re-encode only the part of the
+    buffer that changed each frame. See `dirty_rect`.
+    */
+    pub fn take_dirty_rect(&mut self) -> Option<[u32; 4]> {
+        self.dirty.take()
+    }
+}
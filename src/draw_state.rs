@@ -0,0 +1,271 @@
+use graphics::draw_state::{Blend, DrawState, Stencil};
+use image::{GrayImage, Luma};
+
+use crate::{layer_color, layer_color_legacy, linear_to_srgb, srgb_to_linear};
+
+/**
+The parts of a `DrawState` that affect how triangles are drawn.
+
+This is independent of the version of Piston's graphics library that the
+`DrawState` came from.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct DrawParams {
+    pub(crate) blend: Option<Blend>,
+    pub(crate) stencil: Option<Stencil>,
+    /// `[x, y, width, height]` relative to the origin's corner
+    pub(crate) scissor: Option<[u32; 4]>,
+}
+
+impl From<&DrawState> for DrawParams {
+    fn from(draw_state: &DrawState) -> Self {
+        DrawParams {
+            blend: draw_state.blend,
+            stencil: draw_state.stencil,
+            scissor: draw_state.scissor,
+        }
+    }
+}
+
+/**
+Blend a color drawn with some coverage over an existing color.
+
+The blend modes match those of Piston's OpenGL backend. With no blend mode,
+the color replaces what is under it. Color channels are clamped to `[0, max]`
+and alpha to `[0, 1]`. With `legacy`, alpha blending uses the quadratic alpha
+weighting of older versions.
+*/
+fn blend_color(
+    blend: Option<Blend>,
+    legacy: bool,
+    src: &[f32; 4],
+    dst: &[f32; 4],
+    coverage: f32,
+    max: f32,
+) -> [f32; 4] {
+    let blended = match blend {
+        Some(Blend::Alpha) => {
+            let src = [src[0], src[1], src[2], src[3] * coverage];
+            return if legacy {
+                layer_color_legacy(&src, dst)
+            } else {
+                layer_color(&src, dst)
+            };
+        }
+        None => *src,
+        Some(Blend::Add) => channels(src, dst, |s, d| s + d),
+        Some(Blend::Multiply) => channels(src, dst, |s, d| s * d),
+        Some(Blend::Invert) => channels(src, dst, |s, d| s * (1.0 - d)),
+        // Only the color channels are added, as in OpenGL, which keeps the existing alpha
+        Some(Blend::Lighter) => {
+            let alpha = src[3];
+            let [r, g, b, _] = channels(src, dst, |s, d| d + s * alpha);
+            [r, g, b, dst[3]]
+        }
+    };
+    // Partially covered pixels fade between the existing and blended colors
+    bound(channels(dst, &blended, |d, b| d + (b - d) * coverage), max)
+}
+
+/**
+Blend a premultiplied color drawn with some coverage over an existing premultiplied color.
+
+Alpha blending is done directly in premultiplied space. The other blend modes,
+and legacy alpha blending, are defined on straight colors, so they convert back
+and forth.
+*/
+fn blend_premultiplied(
+    blend: Option<Blend>,
+    legacy: bool,
+    src: &[f32; 4],
+    dst: &[f32; 4],
+    coverage: f32,
+    max: f32,
+) -> [f32; 4] {
+    match blend {
+        Some(Blend::Alpha) if !legacy => {
+            let inverse = 1.0 - src[3] * coverage;
+            bound(channels(src, dst, |s, d| s * coverage + d * inverse), max)
+        }
+        None => bound(channels(dst, src, |d, s| d + (s - d) * coverage), max),
+        _ => premultiply(&blend_color(
+            blend,
+            legacy,
+            &unpremultiply(src),
+            &unpremultiply(dst),
+            coverage,
+            max,
+        )),
+    }
+}
+
+/// Clamp the color channels of a color to `[0, max]` and its alpha to `[0, 1]`
+fn bound(color: [f32; 4], max: f32) -> [f32; 4] {
+    [
+        color[0].clamp(0.0, max),
+        color[1].clamp(0.0, max),
+        color[2].clamp(0.0, max),
+        color[3].clamp(0.0, 1.0),
+    ]
+}
+
+/// How the colors of a buffer are stored and blended
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct ColorSpace {
+    /// Colors are stored premultiplied by alpha
+    pub(crate) premultiplied: bool,
+    /// Colors are blended in linear light rather than directly on sRGB values
+    pub(crate) linear: bool,
+    /// Colors brighter than white are kept rather than clipped, as in floating point buffers
+    pub(crate) unbounded: bool,
+    /// Alpha blending uses the quadratic alpha weighting of older versions
+    pub(crate) legacy: bool,
+}
+
+impl ColorSpace {
+    /// Blend a straight sRGB color drawn with some coverage over a stored color,
+    /// returning the new stored color
+    pub(crate) fn blend(
+        self,
+        blend: Option<Blend>,
+        color: &[f32; 4],
+        stored: &[f32; 4],
+        coverage: f32,
+    ) -> [f32; 4] {
+        let max = if self.unbounded { f32::INFINITY } else { 1.0 };
+        if !self.linear {
+            return if self.premultiplied {
+                blend_premultiplied(
+                    blend,
+                    self.legacy,
+                    &premultiply(color),
+                    stored,
+                    coverage,
+                    max,
+                )
+            } else {
+                blend_color(blend, self.legacy, color, stored, coverage, max)
+            };
+        }
+        let under = if self.premultiplied {
+            unpremultiply(stored)
+        } else {
+            *stored
+        };
+        let (color, under) = (to_linear(color), to_linear(&under));
+        let layered = if self.premultiplied {
+            unpremultiply(&blend_premultiplied(
+                blend,
+                self.legacy,
+                &premultiply(&color),
+                &premultiply(&under),
+                coverage,
+                max,
+            ))
+        } else {
+            blend_color(blend, self.legacy, &color, &under, coverage, max)
+        };
+        let layered = to_srgb(&layered);
+        if self.premultiplied {
+            premultiply(&layered)
+        } else {
+            layered
+        }
+    }
+}
+
+/// Convert the color channels of an sRGB color to linear light
+fn to_linear(color: &[f32; 4]) -> [f32; 4] {
+    [
+        srgb_to_linear(color[0]),
+        srgb_to_linear(color[1]),
+        srgb_to_linear(color[2]),
+        color[3],
+    ]
+}
+
+/// Undo `to_linear`
+fn to_srgb(color: &[f32; 4]) -> [f32; 4] {
+    [
+        linear_to_srgb(color[0]),
+        linear_to_srgb(color[1]),
+        linear_to_srgb(color[2]),
+        color[3],
+    ]
+}
+
+/// Multiply the color channels of a straight color by its alpha
+pub(crate) fn premultiply(color: &[f32; 4]) -> [f32; 4] {
+    let a = color[3];
+    [color[0] * a, color[1] * a, color[2] * a, a]
+}
+
+/// Undo `premultiply`
+pub(crate) fn unpremultiply(color: &[f32; 4]) -> [f32; 4] {
+    let a = color[3];
+    if a <= 0.0 {
+        return [0.0; 4];
+    }
+    [
+        (color[0] / a).min(1.0),
+        (color[1] / a).min(1.0),
+        (color[2] / a).min(1.0),
+        a,
+    ]
+}
+
+/**
+Apply a stencil operation to a pixel, returning whether its color should be drawn.
+
+The operations match those of Piston's OpenGL backend. `Clip` and `Increment`
+only write to the stencil plane. A pixel only counts as inside a clipping shape
+if at least half of it is covered. `value` is the pixel's value in the stencil
+plane. Without a plane, every stencil value is 0.
+*/
+pub(crate) fn apply_stencil(
+    stencil: Option<Stencil>,
+    value: Option<&mut u8>,
+    coverage: f32,
+) -> bool {
+    let stencil = match stencil {
+        Some(stencil) => stencil,
+        None => return true,
+    };
+    let current = value.as_deref().copied().unwrap_or(0);
+    let new = match stencil {
+        Stencil::Clip(new) => new,
+        Stencil::Increment => current.saturating_add(1),
+        Stencil::Inside(reference) => return current == reference,
+        Stencil::Outside(reference) => return current != reference,
+    };
+    if let (Some(value), true) = (value, coverage >= 0.5) {
+        *value = new;
+    }
+    false
+}
+
+/// Set every value of a stencil plane, allocating it at the given size if it is missing or the wrong size
+pub(crate) fn fill_stencil(plane: &mut Option<GrayImage>, (width, height): (u32, u32), value: u8) {
+    match plane {
+        Some(stencil) if stencil.dimensions() == (width, height) => {
+            for pixel in stencil.pixels_mut() {
+                *pixel = Luma([value]);
+            }
+        }
+        _ => *plane = Some(GrayImage::from_pixel(width, height, Luma([value]))),
+    }
+}
+
+/// Allocate a cleared stencil plane if a draw uses stencil operations and there is none of the given size
+pub(crate) fn prepare_stencil(plane: &mut Option<GrayImage>, size: (u32, u32), params: DrawParams) {
+    let fits = plane
+        .as_ref()
+        .is_some_and(|stencil| stencil.dimensions() == size);
+    if params.stencil.is_some() && !fits {
+        fill_stencil(plane, size, 0);
+    }
+}
+
+fn channels<F: Fn(f32, f32) -> f32>(a: &[f32; 4], b: &[f32; 4], f: F) -> [f32; 4] {
+    [f(a[0], b[0]), f(a[1], b[1]), f(a[2], b[2]), f(a[3], b[3])]
+}
@@ -0,0 +1,162 @@
+use std::{
+    fs,
+    io::{self, Write},
+    ops::{Bound, RangeBounds},
+    path::Path,
+};
+
+use image::{DynamicImage, ImageFormat, ImageOutputFormat, ImageResult};
+
+use crate::RenderBuffer;
+
+impl RenderBuffer {
+    /**
+    Encode the buffer as an image file in the given format.
+
+    `quality` ranges from 1 to 100 and is only used by lossy formats like JPEG.
+    */
+    #[cfg_attr(not(feature = "formats_extra"), allow(unused_variables))]
+    pub fn encode(&self, format: ImageFormat, quality: u8) -> ImageResult<Vec<u8>> {
+        let output_format: ImageOutputFormat = match format {
+            #[cfg(feature = "formats_extra")]
+            ImageFormat::Jpeg => ImageOutputFormat::Jpeg(quality.clamp(1, 100)),
+            format => format.into(),
+        };
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgba8(self.straight_pixels().into_owned())
+            .write_to(&mut bytes, output_format)?;
+        Ok(bytes)
+    }
+    /// Encode the buffer as a `data:` URI, such as `data:image/png;base64,...`,
+    /// for embedding directly in HTML or CSS.
+    pub fn to_data_uri(&self, format: ImageFormat) -> ImageResult<String> {
+        let bytes = self.encode(format, 90)?;
+        Ok(format!(
+            "data:{};base64,{}",
+            mime_type(format),
+            base64::encode(bytes)
+        ))
+    }
+    /// Encode the buffer as a PNG and then as base64, for pasting small renders into bug reports.
+    pub fn encode_base64_png(&self) -> ImageResult<String> {
+        self.encode(ImageFormat::Png, 100).map(base64::encode)
+    }
+    /**
+    Format the given rows of pixels as hexadecimal `RRGGBBAA` values.
+
+    Each row is written on its own line, prefixed with its index. Rows outside
+    of the buffer are skipped. This is useful for comparing pixel data in tests.
+    */
+    pub fn rows_hex_dump<R: RangeBounds<u32>>(&self, rows: R) -> String {
+        let start = match rows.start_bound() {
+            Bound::Included(&y) => y,
+            Bound::Excluded(&y) => y.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match rows.end_bound() {
+            Bound::Included(&y) => y.saturating_add(1),
+            Bound::Excluded(&y) => y,
+            Bound::Unbounded => self.height(),
+        }
+        .min(self.height());
+        let width = (end.max(1) - 1).to_string().len();
+        let pixels = self.straight_pixels();
+        let mut dump = String::new();
+        for y in start..end {
+            dump.push_str(&format!("{:>width$}:", y, width = width));
+            for x in 0..self.width() {
+                let [r, g, b, a] = pixels.get_pixel(x, y).0;
+                dump.push_str(&format!(" {:02x}{:02x}{:02x}{:02x}", r, g, b, a));
+            }
+            dump.push('\n');
+        }
+        dump
+    }
+    /// Encode the buffer as a binary PPM (`P6`) image. The alpha channel is dropped.
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut bytes = format!("P6\n{} {}\n255\n", self.width(), self.height()).into_bytes();
+        bytes.reserve((self.width() * self.height() * 3) as usize);
+        for pixel in self.straight_pixels().pixels() {
+            bytes.extend_from_slice(&pixel.0[..3]);
+        }
+        bytes
+    }
+    /// Encode the buffer as a PAM (`P7`) image with an `RGB_ALPHA` tuple type.
+    pub fn to_pam(&self) -> Vec<u8> {
+        let mut bytes = format!(
+            "P7\nWIDTH {}\nHEIGHT {}\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n",
+            self.width(),
+            self.height()
+        )
+        .into_bytes();
+        bytes.extend_from_slice(self.straight_pixels().as_raw());
+        bytes
+    }
+    /**
+    Write the raw RGBA bytes of the buffer, row by row with no header.
+
+    This matches `ffmpeg`'s `-f rawvideo -pixel_format rgba` input, so frames can be
+    piped straight into its standard input.
+    */
+    pub fn write_raw_rgba<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(self.straight_pixels().as_raw())
+    }
+    /// Get a JSON description of the layout of the raw RGBA bytes written by `write_raw_rgba`.
+    pub fn raw_rgba_json(&self) -> String {
+        format!(
+            "{{\"width\":{},\"height\":{},\"channels\":4,\"stride\":{},\"pixel_format\":\"rgba\"}}",
+            self.width(),
+            self.height(),
+            self.width() * 4
+        )
+    }
+    /**
+    Save the raw RGBA bytes of the buffer to a file, along with a sidecar JSON file
+    describing its dimensions.
+
+    The sidecar has the same path with a `.json` extension.
+    */
+    pub fn save_raw_rgba<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.straight_pixels().as_raw())?;
+        fs::write(path.with_extension("json"), self.raw_rgba_json())
+    }
+}
+
+/// Get the MIME type of an image format.
+pub fn mime_type(format: ImageFormat) -> &'static str {
+    match format {
+        ImageFormat::Png => "image/png",
+        ImageFormat::Jpeg => "image/jpeg",
+        ImageFormat::Gif => "image/gif",
+        ImageFormat::WebP => "image/webp",
+        ImageFormat::Pnm => "image/x-portable-anymap",
+        ImageFormat::Tiff => "image/tiff",
+        ImageFormat::Tga => "image/x-tga",
+        ImageFormat::Bmp => "image/bmp",
+        ImageFormat::Ico => "image/x-icon",
+        ImageFormat::Hdr => "image/vnd.radiance",
+        ImageFormat::Farbfeld => "image/x-farbfeld",
+        ImageFormat::Avif => "image/avif",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(feature = "axum_response")]
+impl RenderBuffer {
+    /**
+    Encode the buffer into an `axum` response with the matching `Content-Type` header.
+
+    If encoding fails, the response has a status of `500 Internal Server Error`.
+    */
+    pub fn into_response(&self, format: ImageFormat, quality: u8) -> axum::response::Response {
+        use axum::{
+            http::{header, StatusCode},
+            response::IntoResponse,
+        };
+        match self.encode(format, quality) {
+            Ok(bytes) => ([(header::CONTENT_TYPE, mime_type(format))], bytes).into_response(),
+            Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()).into_response(),
+        }
+    }
+}
@@ -0,0 +1,767 @@
+use graphics::math::Matrix2d;
+use image::{imageops, imageops::FilterType, Rgba, RgbaImage};
+
+use crate::{
+    color_f32_rgba, color_rgba_f32, draw_state::premultiply, linear_to_srgb, luminance,
+    srgb_to_linear, BlendMode, RenderBuffer,
+};
+
+/**
+Create a copy of a sprite surrounded by a colored outline.
+
+The outline follows the edges of the sprite's alpha channel and is `thickness`
+pixels wide. The returned buffer is enlarged by `thickness` pixels on every
+side so that the outline is never cut off.
+*/
+pub fn outline_sprite(sprite: &RenderBuffer, color: [f32; 4], thickness: u32) -> RenderBuffer {
+    let (width, height) = sprite.dimensions();
+    let t = thickness as i64;
+    let disk = disk_offsets(thickness);
+    let mut result = RgbaImage::new(width + 2 * thickness, height + 2 * thickness);
+    for (x, y, pixel) in result.enumerate_pixels_mut() {
+        let (sx, sy) = (x as i64 - t, y as i64 - t);
+        let coverage = disk
+            .iter()
+            .filter_map(|&(dx, dy)| sprite_alpha(sprite, sx + dx, sy + dy))
+            .fold(0.0f32, f32::max);
+        let outline = [color[0], color[1], color[2], color[3] * coverage];
+        let over = sprite_pixel(sprite, sx, sy).unwrap_or([0.0; 4]);
+        *pixel = color_f32_rgba(&BlendMode::Normal.composite(over, outline));
+    }
+    result.into()
+}
+
+/// The shape of the neighborhood used by morphological operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StructuringElement {
+    /// A square with the given radius, so a side length of `2 * radius + 1`
+    Square(u32),
+    /// A disk with the given radius
+    Disk(u32),
+}
+
+impl StructuringElement {
+    fn offsets(self) -> Vec<(i64, i64)> {
+        match self {
+            StructuringElement::Square(radius) => {
+                let r = radius as i64;
+                (-r..=r)
+                    .flat_map(|dy| (-r..=r).map(move |dx| (dx, dy)))
+                    .collect()
+            }
+            StructuringElement::Disk(radius) => disk_offsets(radius),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RangeKind {
+    Chroma { color: [f32; 4], tolerance: f32 },
+    Luma { min: f32, max: f32 },
+}
+
+/**
+A range of colors, used for things like background removal.
+
+Colors inside the range match fully. If the range is feathered, colors
+slightly outside of it match partially, fading out over the feather distance.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorRange {
+    kind: RangeKind,
+    feather: f32,
+}
+
+impl ColorRange {
+    /// Create a range of colors whose RGB distance to `color` is at most `tolerance`.
+    ///
+    /// Distance is normalized so that black and white are `1.0` apart.
+    pub fn chroma(color: [f32; 4], tolerance: f32) -> ColorRange {
+        ColorRange {
+            kind: RangeKind::Chroma { color, tolerance },
+            feather: 0.0,
+        }
+    }
+    /// Create a range of colors whose luminance is between `min` and `max`.
+    pub fn luma(min: f32, max: f32) -> ColorRange {
+        ColorRange {
+            kind: RangeKind::Luma { min, max },
+            feather: 0.0,
+        }
+    }
+    /// Set the distance outside of the range over which matching fades out.
+    pub fn feather(self, feather: f32) -> Self {
+        ColorRange {
+            feather: feather.max(0.0),
+            ..self
+        }
+    }
+    /// Get how strongly a color matches the range, from `0.0` to `1.0`.
+    pub fn matches(&self, color: [f32; 4]) -> f32 {
+        let distance = match self.kind {
+            RangeKind::Chroma {
+                color: key,
+                tolerance,
+            } => {
+                let sum: f32 = (0..3).map(|i| (color[i] - key[i]).powi(2)).sum();
+                (sum / 3.0).sqrt() - tolerance
+            }
+            RangeKind::Luma { min, max } => {
+                let luma = luminance(color);
+                (min - luma).max(luma - max)
+            }
+        };
+        if distance <= 0.0 {
+            1.0
+        } else if self.feather > 0.0 {
+            (1.0 - distance / self.feather).max(0.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// A type of color vision deficiency that can be simulated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorBlindness {
+    /// No functioning red cones
+    Protanopia,
+    /// No functioning green cones
+    Deuteranopia,
+    /// No functioning blue cones
+    Tritanopia,
+}
+
+impl ColorBlindness {
+    /// Simulation matrices from Machado, Oliveira and Fernandes (2009) at full severity,
+    /// applied to linear RGB
+    fn matrix(self) -> [[f32; 3]; 3] {
+        match self {
+            ColorBlindness::Protanopia => [
+                [0.152_286, 1.052_583, -0.204_868],
+                [0.114_503, 0.786_281, 0.099_216],
+                [-0.003_882, -0.048_116, 1.051_998],
+            ],
+            ColorBlindness::Deuteranopia => [
+                [0.367_322, 0.860_646, -0.227_968],
+                [0.280_085, 0.672_501, 0.047_413],
+                [-0.011_820, 0.042_940, 0.968_881],
+            ],
+            ColorBlindness::Tritanopia => [
+                [1.255_528, -0.076_749, -0.178_779],
+                [-0.078_411, 0.930_809, 0.147_602],
+                [0.004_733, 0.691_367, 0.303_900],
+            ],
+        }
+    }
+    /// Simulate how a color is seen with this deficiency.
+    pub fn simulate(self, color: [f32; 4]) -> [f32; 4] {
+        let m = self.matrix();
+        let linear = [
+            srgb_to_linear(color[0]),
+            srgb_to_linear(color[1]),
+            srgb_to_linear(color[2]),
+        ];
+        let channel = |row: [f32; 3]| {
+            linear_to_srgb(row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2])
+        };
+        [channel(m[0]), channel(m[1]), channel(m[2]), color[3]]
+    }
+}
+
+/// Settings for preparing text-heavy renders for optical character recognition.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OcrOptions {
+    /// The factor by which the content is enlarged
+    pub scale: u32,
+    /// The strength of the sharpening applied after conversion to grayscale
+    pub sharpen: f32,
+    /// The luminance threshold used for binarization, or `None` to choose one automatically
+    pub threshold: Option<f32>,
+}
+
+impl Default for OcrOptions {
+    fn default() -> Self {
+        OcrOptions {
+            scale: 2,
+            sharpen: 0.5,
+            threshold: None,
+        }
+    }
+}
+
+/**
+Render content for OCR at a higher resolution, then sharpen and binarize it.
+
+The buffer is cleared to white before `draw` is called. `draw` receives the
+buffer and a transform that scales logical coordinates up to the buffer's
+resolution, so content should be drawn as if the buffer were `width` by `height`.
+*/
+pub fn render_for_ocr<F>(width: u32, height: u32, options: OcrOptions, draw: F) -> RenderBuffer
+where
+    F: FnOnce(Matrix2d, &mut RenderBuffer),
+{
+    let scale = options.scale.max(1);
+    let mut buffer = RenderBuffer::new(width * scale, height * scale);
+    buffer.clear([1.0; 4]);
+    let s = f64::from(scale);
+    draw([[s, 0.0, 0.0], [0.0, s, 0.0]], &mut buffer);
+    buffer.grayscale();
+    buffer.sharpen(options.sharpen);
+    buffer.binarize(options.threshold);
+    buffer
+}
+
+impl RenderBuffer {
+    /// Create an enlarged, sharpened and binarized copy of the buffer for OCR.
+    pub fn to_ocr_ready(&self, options: OcrOptions) -> RenderBuffer {
+        let scale = options.scale.max(1);
+        let mut buffer = RenderBuffer::from(imageops::resize(
+            &self.inner,
+            self.width() * scale,
+            self.height() * scale,
+            FilterType::CatmullRom,
+        ));
+        buffer.grayscale();
+        buffer.sharpen(options.sharpen);
+        buffer.binarize(options.threshold);
+        buffer
+    }
+    /// Convert every pixel to its luminance, keeping alpha.
+    pub fn grayscale(&mut self) {
+        self.mark_all_dirty();
+        for pixel in self.inner.pixels_mut() {
+            let mut color = color_rgba_f32(*pixel);
+            let luma = luminance(color);
+            color[0] = luma;
+            color[1] = luma;
+            color[2] = luma;
+            *pixel = color_f32_rgba(&color);
+        }
+    }
+    /// Sharpen the color channels with a Laplacian kernel of the given strength.
+    pub fn sharpen(&mut self, amount: f32) {
+        self.mark_all_dirty();
+        let source = self.inner.clone();
+        let (width, height) = self.dimensions();
+        let at = |x: i64, y: i64, c: usize| {
+            let x = x.clamp(0, width as i64 - 1) as u32;
+            let y = y.clamp(0, height as i64 - 1) as u32;
+            f32::from(source.get_pixel(x, y)[c])
+        };
+        for (x, y, pixel) in self.inner.enumerate_pixels_mut() {
+            let (x, y) = (x as i64, y as i64);
+            for c in 0..3 {
+                let center = at(x, y, c);
+                let edges = 4.0 * center
+                    - at(x - 1, y, c)
+                    - at(x + 1, y, c)
+                    - at(x, y - 1, c)
+                    - at(x, y + 1, c);
+                pixel[c] = (center + amount * edges).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+    /**
+    Set every pixel to black or white depending on whether its luminance is below a threshold.
+
+    If `threshold` is `None`, one is chosen with Otsu's method.
+    */
+    pub fn binarize(&mut self, threshold: Option<f32>) {
+        self.mark_all_dirty();
+        let threshold = threshold.unwrap_or_else(|| self.otsu_threshold());
+        for pixel in self.inner.pixels_mut() {
+            let mut color = color_rgba_f32(*pixel);
+            let value = if luminance(color) < threshold {
+                0.0
+            } else {
+                1.0
+            };
+            color[0] = value;
+            color[1] = value;
+            color[2] = value;
+            *pixel = color_f32_rgba(&color);
+        }
+    }
+    /// Choose the luminance threshold that best separates the buffer into two classes
+    fn otsu_threshold(&self) -> f32 {
+        let mut histogram = [0u64; 256];
+        for pixel in self.inner.pixels() {
+            let luma = luminance(color_rgba_f32(*pixel));
+            histogram[(luma * 255.0).round() as usize] += 1;
+        }
+        let total: u64 = histogram.iter().sum();
+        let sum: f64 = histogram
+            .iter()
+            .enumerate()
+            .map(|(i, &n)| i as f64 * n as f64)
+            .sum();
+        let (mut background_sum, mut background_count) = (0.0, 0u64);
+        let (mut best, mut best_variance) = (128, 0.0);
+        for (i, &n) in histogram.iter().enumerate() {
+            background_count += n;
+            if background_count == 0 {
+                continue;
+            }
+            let foreground_count = total - background_count;
+            if foreground_count == 0 {
+                break;
+            }
+            background_sum += i as f64 * n as f64;
+            let background_mean = background_sum / background_count as f64;
+            let foreground_mean = (sum - background_sum) / foreground_count as f64;
+            let variance = background_count as f64
+                * foreground_count as f64
+                * (background_mean - foreground_mean).powi(2);
+            if variance > best_variance {
+                best = i + 1;
+                best_variance = variance;
+            }
+        }
+        best as f32 / 255.0
+    }
+    /**
+    Create a copy of the buffer surrounded by a border.
+
+    The returned buffer is enlarged by `width` pixels on every side. Its outer
+    corners are rounded with `corner_radius`, and the corners of the buffer
+    inside the border are rounded to match. Areas outside of the rounded
+    corners are transparent.
+    */
+    pub fn add_border(&self, width: u32, color: [f32; 4], corner_radius: f32) -> RenderBuffer {
+        let (inner_width, inner_height) = self.dimensions();
+        let mut result = RgbaImage::new(inner_width + 2 * width, inner_height + 2 * width);
+        let (t, radius) = (width as f32, corner_radius.max(0.0));
+        let outer = [0.0, 0.0, result.width() as f32, result.height() as f32];
+        let inner = [t, t, inner_width as f32, inner_height as f32];
+        for (x, y, pixel) in result.enumerate_pixels_mut() {
+            let point = [x as f32 + 0.5, y as f32 + 0.5];
+            let outer_coverage = rounded_rect_coverage(point, outer, radius);
+            if outer_coverage <= 0.0 {
+                continue;
+            }
+            let inner_coverage = rounded_rect_coverage(point, inner, (radius - t).max(0.0));
+            let mut image =
+                sprite_pixel(self, x as i64 - t as i64, y as i64 - t as i64).unwrap_or([0.0; 4]);
+            image[3] *= inner_coverage;
+            let mut border = color;
+            border[3] *= 1.0 - inner_coverage;
+            let mut composited = BlendMode::Normal.composite(image, border);
+            composited[3] *= outer_coverage;
+            if self.premultiplied {
+                composited = premultiply(&composited);
+            }
+            *pixel = color_f32_rgba(&composited);
+        }
+        RenderBuffer::with_settings_of(self, result)
+    }
+    /**
+    Darken the edges of the buffer by fading `color` in toward its corners.
+
+    `strength` ranges from `0.0`, which leaves the buffer unchanged, to `1.0`,
+    which covers the corners with `color` entirely. The center of the buffer is
+    never changed, and the alpha of every pixel is kept.
+    */
+    pub fn vignette(&mut self, strength: f32, color: [f32; 4]) {
+        let (width, height) = self.dimensions();
+        let strength = strength.clamp(0.0, 1.0) * color[3];
+        for y in 0..height {
+            for x in 0..width {
+                let dx = (x as f32 + 0.5) / width as f32 * 2.0 - 1.0;
+                let dy = (y as f32 + 0.5) / height as f32 * 2.0 - 1.0;
+                // Distance from the center, reaching 1 at the corners
+                let distance = ((dx * dx + dy * dy) / 2.0).sqrt();
+                let t = ((distance - 0.3) / 0.7).clamp(0.0, 1.0);
+                let amount = strength * t * t * (3.0 - 2.0 * t);
+                let mut pixel_color = self.pixel(x, y);
+                for (channel, target) in pixel_color.iter_mut().zip(&color).take(3) {
+                    *channel += (target - *channel) * amount;
+                }
+                self.set_pixel(x, y, pixel_color);
+            }
+        }
+    }
+    /**
+    Add monochrome film grain, brightening or darkening each pixel by a random amount.
+
+    `amount` is the largest change to a channel, from `0.0` to `1.0`. The grain
+    only depends on `seed` and the position of each pixel, so the same seed
+    always gives the same result.
+    */
+    pub fn add_grain(&mut self, amount: f32, seed: u64) {
+        self.grain(amount, seed, false);
+    }
+    /// Add chroma noise, changing each color channel of each pixel by a separate random amount.
+    ///
+    /// This is like `add_grain`, and is also deterministic given a seed.
+    pub fn add_chroma_grain(&mut self, amount: f32, seed: u64) {
+        self.grain(amount, seed, true);
+    }
+    fn grain(&mut self, amount: f32, seed: u64, chroma: bool) {
+        self.mark_all_dirty();
+        for (x, y, pixel) in self.inner.enumerate_pixels_mut() {
+            let mut color = color_rgba_f32(*pixel);
+            let shared = noise(seed, x, y, 0);
+            for (i, channel) in color.iter_mut().take(3).enumerate() {
+                let offset = if chroma {
+                    noise(seed, x, y, i as u64)
+                } else {
+                    shared
+                };
+                *channel = (*channel + offset * amount).clamp(0.0, 1.0);
+            }
+            *pixel = color_f32_rgba(&color);
+        }
+    }
+    /**
+    Redraw the buffer as black halftone dots on white, like a print.
+
+    The buffer is divided into a grid of `cell_size` pixel cells rotated by
+    `angle` radians, and each cell gets a dot whose area matches the darkness
+    of the buffer at the cell's center. Alpha is kept.
+    */
+    pub fn halftone(&mut self, cell_size: f32, angle: f32) {
+        self.mark_all_dirty();
+        let source = self.inner.clone();
+        let (width, height) = self.dimensions();
+        let cell = cell_size.max(1.0);
+        let (sin, cos) = angle.sin_cos();
+        for (x, y, pixel) in self.inner.enumerate_pixels_mut() {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            // Find the center of the cell in the rotated grid
+            let (u, v) = (px * cos + py * sin, py * cos - px * sin);
+            let (cu, cv) = (
+                ((u / cell).floor() + 0.5) * cell,
+                ((v / cell).floor() + 0.5) * cell,
+            );
+            let (cx, cy) = (cu * cos - cv * sin, cu * sin + cv * cos);
+            let sample = source.get_pixel(
+                (cx.max(0.0) as u32).min(width - 1),
+                (cy.max(0.0) as u32).min(height - 1),
+            );
+            let darkness = 1.0 - luminance(color_rgba_f32(*sample));
+            let radius = cell * (darkness / std::f32::consts::PI).sqrt();
+            let distance = ((u - cu).powi(2) + (v - cv).powi(2)).sqrt();
+            let ink = (radius - distance + 0.5).clamp(0.0, 1.0);
+            *pixel = ink_pixel(*pixel, ink);
+        }
+    }
+    /**
+    Redraw the buffer as black crosshatched lines on white, like a pen drawing.
+
+    Lines are `cell_size` pixels apart and run at `angle` radians. Darker areas
+    get more layers of lines in other directions. Alpha is kept.
+    */
+    pub fn crosshatch(&mut self, cell_size: f32, angle: f32) {
+        self.mark_all_dirty();
+        let cell = cell_size.max(2.0);
+        let layers = [
+            (0.2, 0.0),
+            (0.45, std::f32::consts::FRAC_PI_2),
+            (0.65, std::f32::consts::FRAC_PI_4),
+            (0.85, -std::f32::consts::FRAC_PI_4),
+        ];
+        for (x, y, pixel) in self.inner.enumerate_pixels_mut() {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let darkness = 1.0 - luminance(color_rgba_f32(*pixel));
+            let ink = layers
+                .iter()
+                .filter(|&&(threshold, _)| darkness > threshold)
+                .map(|&(_, offset)| {
+                    let (sin, cos) = (angle + offset).sin_cos();
+                    // Distance to the nearest line of this layer
+                    let across = (py * cos - px * sin) / cell;
+                    let distance = (across - across.round()).abs() * cell;
+                    (1.0 - distance).clamp(0.0, 1.0)
+                })
+                .fold(0.0f32, f32::max);
+            *pixel = ink_pixel(*pixel, ink);
+        }
+    }
+    /**
+    Replace every pixel with the strength of the edges at it, found with a Sobel filter.
+
+    Edges are found in the luminance, and are white on black. Alpha is kept.
+    */
+    pub fn detect_edges(&mut self) {
+        self.mark_all_dirty();
+        let magnitudes = self.edge_magnitudes();
+        for (pixel, magnitude) in self.inner.pixels_mut().zip(magnitudes) {
+            let value = (magnitude.min(1.0) * 255.0).round() as u8;
+            *pixel = Rgba([value, value, value, pixel[3]]);
+        }
+    }
+    /// Reduce each color channel to `levels` evenly spaced values.
+    ///
+    /// Fewer than 2 levels are treated as 2.
+    pub fn posterize(&mut self, levels: u32) {
+        self.mark_all_dirty();
+        let steps = (levels.max(2) - 1) as f32;
+        for pixel in self.inner.pixels_mut() {
+            for channel in pixel.0.iter_mut().take(3) {
+                let value = f32::from(*channel) / 255.0;
+                *channel = ((value * steps).round() / steps * 255.0).round() as u8;
+            }
+        }
+    }
+    /**
+    Give the buffer a cartoon look with flat colors and dark outlines.
+
+    Colors are posterized to `levels` values per channel, and pixels where the
+    Sobel edge strength is above `edge_threshold` are drawn black.
+    */
+    pub fn toon(&mut self, levels: u32, edge_threshold: f32) {
+        self.mark_all_dirty();
+        let magnitudes = self.edge_magnitudes();
+        self.posterize(levels);
+        for (pixel, magnitude) in self.inner.pixels_mut().zip(magnitudes) {
+            if magnitude > edge_threshold {
+                *pixel = Rgba([0, 0, 0, pixel[3]]);
+            }
+        }
+    }
+    /// The Sobel gradient magnitude of the luminance at every pixel, in row order
+    fn edge_magnitudes(&self) -> Vec<f32> {
+        let (width, height) = self.dimensions();
+        let luma: Vec<f32> = self
+            .inner
+            .pixels()
+            .map(|pixel| luminance(color_rgba_f32(*pixel)))
+            .collect();
+        let at = |x: i64, y: i64| {
+            let x = x.clamp(0, width as i64 - 1);
+            let y = y.clamp(0, height as i64 - 1);
+            luma[(y * width as i64 + x) as usize]
+        };
+        (0..height as i64)
+            .flat_map(|y| (0..width as i64).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let gx = at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1)
+                    - at(x - 1, y - 1)
+                    - 2.0 * at(x - 1, y)
+                    - at(x - 1, y + 1);
+                let gy = at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1)
+                    - at(x - 1, y - 1)
+                    - 2.0 * at(x, y - 1)
+                    - at(x + 1, y - 1);
+                (gx * gx + gy * gy).sqrt()
+            })
+            .collect()
+    }
+    /// Simulate how the buffer looks to someone with the given color vision deficiency.
+    pub fn simulate_color_blindness(&mut self, kind: ColorBlindness) {
+        self.mark_all_dirty();
+        for pixel in self.inner.pixels_mut() {
+            *pixel = color_f32_rgba(&kind.simulate(color_rgba_f32(*pixel)));
+        }
+    }
+    /**
+    Replace the color of every pixel with a color from a gradient, picked by its luminance.
+
+    `stops` are pairs of a luminance in `[0, 1]` and the color it maps to.
+    Luminances between stops blend between their colors, and luminances past
+    the first or last stop use its color. The alpha of each stop is multiplied
+    with the alpha of the pixel. Stops do not need to be sorted. Does nothing
+    if there are no stops.
+    */
+    pub fn gradient_map(&mut self, stops: &[(f32, [f32; 4])]) {
+        self.mark_all_dirty();
+        let mut stops = stops.to_vec();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let (first, last) = match (stops.first(), stops.last()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => return,
+        };
+        let sample = |luma: f32| {
+            if luma <= first.0 {
+                return first.1;
+            }
+            stops
+                .windows(2)
+                .find(|pair| luma <= pair[1].0)
+                .map_or(last.1, |pair| {
+                    let ((start, from), (end, to)) = (pair[0], pair[1]);
+                    let t = (luma - start) / (end - start);
+                    [0, 1, 2, 3].map(|i| from[i] + (to[i] - from[i]) * t)
+                })
+        };
+        for pixel in self.inner.pixels_mut() {
+            let color = color_rgba_f32(*pixel);
+            let mapped = sample(luminance(color));
+            *pixel = color_f32_rgba(&[mapped[0], mapped[1], mapped[2], mapped[3] * color[3]]);
+        }
+    }
+    /// Map dark pixels to `dark` and light pixels to `light`, blending between them by luminance.
+    ///
+    /// This is a `gradient_map` with two stops.
+    pub fn duotone(&mut self, dark: [f32; 4], light: [f32; 4]) {
+        self.gradient_map(&[(0.0, dark), (1.0, light)]);
+    }
+    /// Make pixels that match the color range transparent.
+    ///
+    /// Partially matching pixels in a feathered range become partially transparent.
+    pub fn remove_background(&mut self, range: ColorRange) {
+        self.mark_all_dirty();
+        for pixel in self.inner.pixels_mut() {
+            let mut color = color_rgba_f32(*pixel);
+            color[3] *= 1.0 - range.matches(color);
+            *pixel = color_f32_rgba(&color);
+        }
+    }
+    /// Grow opaque areas of the alpha channel by taking the maximum alpha in each pixel's neighborhood.
+    pub fn dilate_alpha(&mut self, element: StructuringElement) {
+        self.morph_alpha(element, u8::max, 0);
+    }
+    /// Shrink opaque areas of the alpha channel by taking the minimum alpha in each pixel's neighborhood.
+    pub fn erode_alpha(&mut self, element: StructuringElement) {
+        self.morph_alpha(element, u8::min, 255);
+    }
+    /// Erode then dilate the alpha channel, removing small specks while keeping the size of larger shapes.
+    pub fn open_alpha(&mut self, element: StructuringElement) {
+        self.erode_alpha(element);
+        self.dilate_alpha(element);
+    }
+    /// Dilate then erode the alpha channel, filling small holes while keeping the size of larger shapes.
+    pub fn close_alpha(&mut self, element: StructuringElement) {
+        self.dilate_alpha(element);
+        self.erode_alpha(element);
+    }
+    /// Make the alpha channel binary, setting alpha to fully opaque
+    /// where it is at least `threshold` and fully transparent elsewhere.
+    pub fn threshold_alpha(&mut self, threshold: f32) {
+        self.mark_all_dirty();
+        let threshold = (threshold * 255.0).round();
+        for pixel in self.inner.pixels_mut() {
+            pixel[3] = if f32::from(pixel[3]) >= threshold {
+                255
+            } else {
+                0
+            };
+        }
+    }
+    /**
+    Crop the buffer to the given size, keeping the most detailed region.
+
+    Every possible window is scored by the total gradient magnitude of its
+    luminance, and the window with the highest score is kept. When several
+    windows score equally, the one closest to the center wins.
+    */
+    pub fn smart_crop(&self, target_width: u32, target_height: u32) -> RenderBuffer {
+        let (width, height) = self.dimensions();
+        let (tw, th) = (target_width.min(width), target_height.min(height));
+        let integral = self.energy_integral();
+        let stride = width as usize + 1;
+        let window_energy = |x: u32, y: u32| {
+            let (x0, y0) = (x as usize, y as usize);
+            let (x1, y1) = (x0 + tw as usize, y0 + th as usize);
+            integral[y1 * stride + x1] + integral[y0 * stride + x0]
+                - integral[y0 * stride + x1]
+                - integral[y1 * stride + x0]
+        };
+        let mut best = ((width - tw) / 2, (height - th) / 2);
+        let mut best_energy = window_energy(best.0, best.1);
+        for y in 0..=height - th {
+            for x in 0..=width - tw {
+                let energy = window_energy(x, y);
+                if energy > best_energy {
+                    best = (x, y);
+                    best_energy = energy;
+                }
+            }
+        }
+        self.crop(best.0, best.1, tw, th)
+    }
+    /// Summed-area table of the gradient magnitude of the luminance,
+    /// with an extra leading row and column of zeros
+    fn energy_integral(&self) -> Vec<f64> {
+        let (width, height) = (self.width() as usize, self.height() as usize);
+        let luma: Vec<f64> = self
+            .inner
+            .pixels()
+            .map(|p| f64::from(luminance(p.0.map(f32::from))))
+            .collect();
+        let at = |x: usize, y: usize| luma[y * width + x];
+        let stride = width + 1;
+        let mut integral = vec![0.0; stride * (height + 1)];
+        for y in 0..height {
+            let mut row_sum = 0.0;
+            for x in 0..width {
+                let dx = at((x + 1).min(width - 1), y) - at(x.saturating_sub(1), y);
+                let dy = at(x, (y + 1).min(height - 1)) - at(x, y.saturating_sub(1));
+                row_sum += dx.abs() + dy.abs();
+                integral[(y + 1) * stride + x + 1] = integral[y * stride + x + 1] + row_sum;
+            }
+        }
+        integral
+    }
+    fn morph_alpha(&mut self, element: StructuringElement, op: fn(u8, u8) -> u8, init: u8) {
+        self.mark_all_dirty();
+        let offsets = element.offsets();
+        let (width, height) = (self.width() as i64, self.height() as i64);
+        let alpha: Vec<u8> = self.inner.pixels().map(|pixel| pixel[3]).collect();
+        for (x, y, pixel) in self.inner.enumerate_pixels_mut() {
+            pixel[3] = offsets
+                .iter()
+                .map(|&(dx, dy)| (x as i64 + dx, y as i64 + dy))
+                .filter(|&(x, y)| x >= 0 && y >= 0 && x < width && y < height)
+                .map(|(x, y)| alpha[(y * width + x) as usize])
+                .fold(init, op);
+        }
+    }
+}
+
+fn sprite_pixel(sprite: &RenderBuffer, x: i64, y: i64) -> Option<[f32; 4]> {
+    if x < 0 || y < 0 || x >= sprite.width() as i64 || y >= sprite.height() as i64 {
+        None
+    } else {
+        Some(sprite.pixel(x as u32, y as u32))
+    }
+}
+
+/// Black ink over white paper, keeping the alpha of a pixel
+fn ink_pixel(pixel: Rgba<u8>, ink: f32) -> Rgba<u8> {
+    let paper = ((1.0 - ink) * 255.0).round() as u8;
+    Rgba([paper, paper, paper, pixel[3]])
+}
+
+/// Deterministic noise in `[-1, 1]` for a channel of a pixel, weighted toward 0
+fn noise(seed: u64, x: u32, y: u32, channel: u64) -> f32 {
+    // SplitMix64, which scrambles nearby inputs into unrelated outputs
+    let mix = |mut z: u64| {
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    let position = ((u64::from(y) << 32) | u64::from(x)).wrapping_add(channel << 62);
+    let hash = mix(mix(seed.wrapping_add(0x9e37_79b9_7f4a_7c15)) ^ position);
+    // Two uniform values summed give a triangular distribution
+    let a = (hash & 0xffff_ffff) as f32 / u32::MAX as f32;
+    let b = (hash >> 32) as f32 / u32::MAX as f32;
+    a + b - 1.0
+}
+
+/// The coverage of a pixel centered at a point by a rectangle with rounded corners
+fn rounded_rect_coverage(point: [f32; 2], rect: [f32; 4], radius: f32) -> f32 {
+    let radius = radius.min(rect[2] / 2.0).min(rect[3] / 2.0);
+    let half = [rect[2] / 2.0 - radius, rect[3] / 2.0 - radius];
+    let q = [
+        (point[0] - rect[0] - rect[2] / 2.0).abs() - half[0],
+        (point[1] - rect[1] - rect[3] / 2.0).abs() - half[1],
+    ];
+    let outside = (q[0].max(0.0).powi(2) + q[1].max(0.0).powi(2)).sqrt();
+    let distance = outside + q[0].max(q[1]).min(0.0) - radius;
+    (0.5 - distance).clamp(0.0, 1.0)
+}
+
+fn sprite_alpha(sprite: &RenderBuffer, x: i64, y: i64) -> Option<f32> {
+    sprite_pixel(sprite, x, y).map(|color| color[3])
+}
+
+/// Offsets of all pixels within `radius` of the origin
+fn disk_offsets(radius: u32) -> Vec<(i64, i64)> {
+    let r = radius as i64;
+    (-r..=r)
+        .flat_map(|dy| (-r..=r).map(move |dx| (dx, dy)))
+        .filter(|&(dx, dy)| dx * dx + dy * dy <= r * r)
+        .collect()
+}
@@ -0,0 +1,219 @@
+use graphics::{draw_state::DrawState, types::Color, Graphics};
+use image::{GrayImage, RgbaImage};
+
+use crate::{
+    color_f32_rgba, color_rgba_f32,
+    draw_state::{self, ColorSpace, DrawParams},
+    Canvas, Origin, OverdrawMask, PixelStore, Rasterizer, RenderBuffer, ScanlineRasterizer,
+};
+
+/// `W` by `H` RGBA8 pixels stored inline
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct FixedPixels<const W: usize, const H: usize>([[[u8; 4]; W]; H]);
+
+impl<const W: usize, const H: usize> PixelStore for FixedPixels<W, H> {
+    fn dimensions(&self) -> (u32, u32) {
+        (W as u32, H as u32)
+    }
+    fn bytes(&self) -> &[u8] {
+        // Nested arrays are laid out contiguously
+        unsafe { std::slice::from_raw_parts(self.0.as_ptr() as *const u8, W * H * 4) }
+    }
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.0.as_mut_ptr() as *mut u8, W * H * 4) }
+    }
+}
+
+/**
+A `W` by `H` render target whose pixels are stored inline rather than on the heap.
+
+This is meant for icon-sized renders in hot paths. A `FixedRenderBuffer` can
+live on the stack or, because `new` is a `const fn`, in a static. Large sizes
+should use a `RenderBuffer` instead, as they may overflow the stack.
+*/
+#[derive(Debug, Clone)]
+pub struct FixedRenderBuffer<const W: usize, const H: usize> {
+    pixels: FixedPixels<W, H>,
+    used: OverdrawMask,
+    rasterizer: &'static dyn Rasterizer,
+    origin: Origin,
+    prevent_overdraw: bool,
+    /// Only allocated once stencil operations are used
+    stencil: Option<GrayImage>,
+    pixels_written: usize,
+    dirty: Option<[u32; 4]>,
+}
+
+impl<const W: usize, const H: usize> Default for FixedRenderBuffer<W, H> {
+    fn default() -> Self {
+        FixedRenderBuffer::new()
+    }
+}
+
+impl<const W: usize, const H: usize> FixedRenderBuffer<W, H> {
+    /// The width of the buffer.
+    pub const WIDTH: u32 = W as u32;
+    /// The height of the buffer.
+    pub const HEIGHT: u32 = H as u32;
+    /// Create a new `FixedRenderBuffer` with all pixels transparent black.
+    pub const fn new() -> Self {
+        FixedRenderBuffer {
+            pixels: FixedPixels([[[0; 4]; W]; H]),
+            used: OverdrawMask::empty(),
+            rasterizer: &ScanlineRasterizer,
+            origin: Origin::TopLeft,
+            prevent_overdraw: true,
+            stencil: None,
+            pixels_written: 0,
+            dirty: if W == 0 || H == 0 {
+                None
+            } else {
+                Some([0, 0, W as u32, H as u32])
+            },
+        }
+    }
+    /// Clear the buffer with a color.
+    pub fn clear(&mut self, color: [f32; 4]) {
+        self.clear_color(color);
+    }
+    /// Returns the color of the pixel at the given coordinates.
+    pub fn pixel(&self, x: u32, y: u32) -> [f32; 4] {
+        color_rgba_f32(image::Rgba(self.pixels.0[y as usize][x as usize]))
+    }
+    /// Sets the color of the pixel at the given coordinates.
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: [f32; 4]) {
+        self.pixels.0[y as usize][x as usize] = color_f32_rgba(&color).0;
+    }
+    /// Get the raw RGBA8 pixel data, row by row.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.pixels.bytes()
+    }
+    /// Set the rasterizer used to draw triangles.
+    ///
+    /// The default is `ScanlineRasterizer`.
+    pub fn set_rasterizer(&mut self, rasterizer: &'static dyn Rasterizer) {
+        self.rasterizer = rasterizer;
+    }
+    /// Set the rasterizer used to draw triangles.
+    pub fn with_rasterizer(mut self, rasterizer: &'static dyn Rasterizer) -> Self {
+        self.set_rasterizer(rasterizer);
+        self
+    }
+    /// Set the origin convention used for drawing.
+    ///
+    /// The default is `Origin::TopLeft`.
+    pub fn set_origin(&mut self, origin: Origin) {
+        self.origin = origin;
+    }
+    /// Set the origin convention used for drawing.
+    pub fn with_origin(mut self, origin: Origin) -> Self {
+        self.set_origin(origin);
+        self
+    }
+    /// Set whether pixels are kept from being drawn more than once per draw call.
+    ///
+    /// The default is `true`. See `RenderBuffer::set_overdraw_prevention`.
+    pub fn set_overdraw_prevention(&mut self, prevent: bool) {
+        self.prevent_overdraw = prevent;
+    }
+    /// Set whether pixels are kept from being drawn more than once per draw call.
+    pub fn with_overdraw_prevention(mut self, prevent: bool) -> Self {
+        self.set_overdraw_prevention(prevent);
+        self
+    }
+    /// Get the number of pixels written by draw calls.
+    pub fn pixels_written(&self) -> usize {
+        self.pixels_written
+    }
+    /// Get the region changed since the dirty rectangle was last taken, and reset it.
+    ///
+    /// See `RenderBuffer::take_dirty_rect`.
+    pub fn take_dirty_rect(&mut self) -> Option<[u32; 4]> {
+        self.dirty.take()
+    }
+    /// Copy the pixels into a new heap-allocated `RenderBuffer`.
+    pub fn to_render_buffer(&self) -> RenderBuffer {
+        RgbaImage::from_raw(W as u32, H as u32, self.as_bytes().to_vec())
+            .expect("Fixed buffer has the wrong number of bytes")
+            .into()
+    }
+    fn clear_stencil_plane(&mut self, value: u8) {
+        draw_state::fill_stencil(&mut self.stencil, (W as u32, H as u32), value);
+    }
+    /// Get the canvas for a draw, allocating the stencil plane if the draw uses it
+    fn draw_canvas(&mut self, params: DrawParams) -> Canvas<'_> {
+        draw_state::prepare_stencil(&mut self.stencil, (W as u32, H as u32), params);
+        self.canvas()
+    }
+    fn canvas(&mut self) -> Canvas<'_> {
+        Canvas {
+            pixels: &mut self.pixels,
+            used: &mut self.used,
+            coverage: None,
+            stencil: self.stencil.as_mut(),
+            color_space: ColorSpace::default(),
+            ids: None,
+            current_id: 0,
+            rasterizer: self.rasterizer,
+            origin: self.origin,
+            prevent_overdraw: self.prevent_overdraw,
+            pixels_written: &mut self.pixels_written,
+            dirty: &mut self.dirty,
+            journal: None,
+            #[cfg(feature = "parallel")]
+            thread_pool: None,
+        }
+    }
+}
+
+impl<const W: usize, const H: usize> PartialEq for FixedRenderBuffer<W, H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.pixels == other.pixels
+    }
+}
+
+impl<const W: usize, const H: usize> Eq for FixedRenderBuffer<W, H> {}
+
+impl<const W: usize, const H: usize> Graphics for FixedRenderBuffer<W, H> {
+    type Texture = RenderBuffer;
+    fn clear_color(&mut self, color: Color) {
+        self.canvas().clear(color);
+    }
+    fn clear_stencil(&mut self, value: u8) {
+        self.clear_stencil_plane(value);
+    }
+    fn tri_list<F>(&mut self, draw_state: &DrawState, color: &[f32; 4], f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]])),
+    {
+        let params = draw_state.into();
+        self.draw_canvas(params).draw_tris(params, color, f);
+    }
+    fn tri_list_uv<F>(
+        &mut self,
+        draw_state: &DrawState,
+        color: &[f32; 4],
+        texture: &Self::Texture,
+        f: F,
+    ) where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]])),
+    {
+        let params = draw_state.into();
+        self.draw_canvas(params)
+            .draw_tris_uv(params, color, texture, f);
+    }
+    fn tri_list_c<F>(&mut self, draw_state: &DrawState, f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 4]])),
+    {
+        let params = draw_state.into();
+        self.draw_canvas(params).draw_tris_c(params, f);
+    }
+    fn tri_list_uv_c<F>(&mut self, draw_state: &DrawState, texture: &Self::Texture, f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]], &[[f32; 4]])),
+    {
+        let params = draw_state.into();
+        self.draw_canvas(params).draw_tris_uv_c(params, texture, f);
+    }
+}
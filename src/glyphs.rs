@@ -1,6 +1,7 @@
-use std::{io, path::Path};
+use std::{error, fmt, io, path::Path};
 
-use graphics::glyph_cache::rusttype;
+use graphics::glyph_cache::rusttype::GlyphCache;
+use rusttype::Font;
 use texture::TextureSettings;
 
 use crate::RenderBuffer;
@@ -9,12 +10,47 @@ use crate::RenderBuffer;
 ///
 /// If the link to the `GlyphCache` type is not working,
 /// try generating the docs yourself.
-pub type BufferGlyphs<'a> = rusttype::GlyphCache<'a, (), RenderBuffer>;
+pub type BufferGlyphs<'a> = GlyphCache<'a, (), RenderBuffer>;
+
+/// An error that can occur when creating a `BufferGlyphs` from font data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FontError {
+    /// The font data could not be parsed
+    InvalidData,
+    /// The font data is in a known format that is not supported, like WOFF
+    UnsupportedFormat,
+    /// The font does not contain any glyphs
+    NoGlyphs,
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontError::InvalidData => write!(f, "Font data could not be parsed"),
+            FontError::UnsupportedFormat => write!(
+                f,
+                "Font format is not supported. \
+                 Only TrueType and OpenType fonts can be loaded"
+            ),
+            FontError::NoGlyphs => write!(f, "Font does not contain any glyphs"),
+        }
+    }
+}
+
+impl error::Error for FontError {}
 
 /// Create a `BufferGlyphs` from some font data
-#[allow(clippy::result_unit_err)]
-pub fn buffer_glyphs_from_bytes(font_data: &[u8]) -> Result<BufferGlyphs, ()> {
-    BufferGlyphs::from_bytes(font_data, (), TextureSettings::new())
+pub fn buffer_glyphs_from_bytes(font_data: &[u8]) -> Result<BufferGlyphs<'_>, FontError> {
+    match font_data.get(..4) {
+        Some(b"wOFF") | Some(b"wOF2") => return Err(FontError::UnsupportedFormat),
+        Some([0, 1, 0, 0]) | Some(b"true") | Some(b"OTTO") | Some(b"ttcf") => {}
+        _ => return Err(FontError::InvalidData),
+    }
+    let font = Font::try_from_bytes(font_data).ok_or(FontError::InvalidData)?;
+    if font.glyph_count() == 0 {
+        return Err(FontError::NoGlyphs);
+    }
+    Ok(BufferGlyphs::from_font(font, (), TextureSettings::new()))
 }
 
 /// Create a `BufferGlyphs` from a path to some font
@@ -0,0 +1,319 @@
+use std::{error, fmt, sync::mpsc};
+
+use wgpu::util::DeviceExt;
+
+use crate::{premultiply_rgba8, RenderBuffer};
+
+const SHADER: &str = include_str!("gpu_post.wgsl");
+
+/// An error that can occur when running post-processing on the GPU.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GpuError {
+    /// No suitable GPU adapter was found
+    NoAdapter,
+    /// The GPU device could not be created
+    Device(String),
+    /// The result could not be read back from the GPU
+    Readback,
+    /// Two buffers that must be the same size are not
+    DimensionMismatch((u32, u32), (u32, u32)),
+    /// The GPU rejected a shader, pipeline or command as invalid
+    Validation(String),
+}
+
+impl fmt::Display for GpuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GpuError::NoAdapter => write!(f, "No suitable GPU adapter was found"),
+            GpuError::Device(message) => write!(f, "Unable to create GPU device: {}", message),
+            GpuError::Readback => write!(f, "Unable to read back results from the GPU"),
+            GpuError::DimensionMismatch(a, b) => write!(
+                f,
+                "Buffers have different dimensions: {}x{} and {}x{}",
+                a.0, a.1, b.0, b.1
+            ),
+            GpuError::Validation(message) => write!(f, "GPU validation failed: {}", message),
+        }
+    }
+}
+
+impl error::Error for GpuError {}
+
+/**
+Runs expensive post-processing filters on the GPU with compute shaders.
+
+Drawing still happens on the CPU. Each operation uploads the buffer, runs a
+compute shader, and reads the result back into a `RenderBuffer`. Creating a
+`GpuPost` sets up a device and compiles the shaders, so it should be reused
+for many operations.
+*/
+#[derive(Debug)]
+pub struct GpuPost {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    blur: wgpu::ComputePipeline,
+    resize: wgpu::ComputePipeline,
+    ssim: wgpu::ComputePipeline,
+}
+
+impl GpuPost {
+    /// Create a new `GpuPost` on the default GPU adapter.
+    pub fn new() -> Result<Self, GpuError> {
+        pollster::block_on(GpuPost::new_async())
+    }
+    async fn new_async() -> Result<Self, GpuError> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions::default())
+            .await
+            .ok_or(GpuError::NoAdapter)?;
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor::default(), None)
+            .await
+            .map_err(|e| GpuError::Device(e.to_string()))?;
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("graphics_buffer post-processing"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: None,
+                module: &module,
+                entry_point,
+            })
+        };
+        let blur = pipeline("blur");
+        let resize = pipeline("resize");
+        let ssim = pipeline("ssim");
+        if let Some(error) = device.pop_error_scope().await {
+            return Err(GpuError::Validation(error.to_string()));
+        }
+        Ok(GpuPost {
+            device,
+            queue,
+            blur,
+            resize,
+            ssim,
+        })
+    }
+    /// Blur a buffer in place with a Gaussian kernel with the given standard deviation in pixels.
+    pub fn gaussian_blur(&self, buffer: &mut RenderBuffer, sigma: f32) -> Result<(), GpuError> {
+        let size = buffer.dimensions();
+        if size.0 == 0 || size.1 == 0 || sigma <= 0.0 {
+            return Ok(());
+        }
+        let bytes = self.validated(|| {
+            let source = self.storage(buffer.straight_pixels().as_raw());
+            let temp = self.output(pixel_bytes(size));
+            let result = self.output(pixel_bytes(size));
+            let horizontal = self.params(size, size, [1, 0], sigma);
+            let vertical = self.params(size, size, [0, 1], sigma);
+            let mut encoder = self.device.create_command_encoder(&Default::default());
+            self.dispatch(
+                &mut encoder,
+                &self.blur,
+                &[(0, &horizontal), (1, &source), (2, &temp)],
+                size,
+            );
+            self.dispatch(
+                &mut encoder,
+                &self.blur,
+                &[(0, &vertical), (1, &temp), (2, &result)],
+                size,
+            );
+            self.read(encoder, &result, pixel_bytes(size))
+        })?;
+        buffer.mark_all_dirty();
+        let premultiplied = buffer.premultiplied;
+        let pixels: &mut [u8] = &mut buffer.inner;
+        pixels.copy_from_slice(&bytes);
+        // The shaders work on straight colors
+        if premultiplied {
+            premultiply_rgba8(pixels);
+        }
+        Ok(())
+    }
+    /// Resize a buffer with bilinear filtering into a new `RenderBuffer`.
+    pub fn resize(
+        &self,
+        buffer: &RenderBuffer,
+        width: u32,
+        height: u32,
+    ) -> Result<RenderBuffer, GpuError> {
+        let src_size = buffer.dimensions();
+        let dst_size = (width, height);
+        if src_size.0 == 0 || src_size.1 == 0 || width == 0 || height == 0 {
+            return Ok(RenderBuffer::new(width, height));
+        }
+        let bytes = self.validated(|| {
+            let source = self.storage(buffer.straight_pixels().as_raw());
+            let result = self.output(pixel_bytes(dst_size));
+            let params = self.params(src_size, dst_size, [0, 0], 0.0);
+            let mut encoder = self.device.create_command_encoder(&Default::default());
+            self.dispatch(
+                &mut encoder,
+                &self.resize,
+                &[(0, &params), (1, &source), (2, &result)],
+                dst_size,
+            );
+            self.read(encoder, &result, pixel_bytes(dst_size))
+        })?;
+        let mut resized = RenderBuffer::new(width, height);
+        let pixels: &mut [u8] = &mut resized.inner;
+        pixels.copy_from_slice(&bytes);
+        Ok(resized)
+    }
+    /**
+    Compute the mean structural similarity (SSIM) of the luminance of two buffers.
+
+    The result is 1.0 for identical buffers and decreases as they differ.
+    */
+    pub fn ssim(&self, a: &RenderBuffer, b: &RenderBuffer) -> Result<f32, GpuError> {
+        let size = a.dimensions();
+        if size != b.dimensions() {
+            return Err(GpuError::DimensionMismatch(size, b.dimensions()));
+        }
+        if size.0 == 0 || size.1 == 0 {
+            return Ok(1.0);
+        }
+        let bytes = self.validated(|| {
+            let source = self.storage(a.straight_pixels().as_raw());
+            let other = self.storage(b.straight_pixels().as_raw());
+            let scores = self.output(pixel_bytes(size));
+            let params = self.params(size, size, [0, 0], 0.0);
+            let mut encoder = self.device.create_command_encoder(&Default::default());
+            self.dispatch(
+                &mut encoder,
+                &self.ssim,
+                &[(0, &params), (1, &source), (3, &other), (4, &scores)],
+                size,
+            );
+            self.read(encoder, &scores, pixel_bytes(size))
+        })?;
+        let sum: f64 = bytes
+            .chunks_exact(4)
+            .map(|b| f64::from(f32::from_le_bytes([b[0], b[1], b[2], b[3]])))
+            .sum();
+        Ok((sum / (size.0 as f64 * size.1 as f64)) as f32)
+    }
+    /**
+    Run some GPU work, returning validation errors instead of panicking.
+
+    wgpu reports validation errors that are not caught by an error scope
+    to a handler that panics, so every operation runs inside one.
+    */
+    fn validated<T, F>(&self, f: F) -> Result<T, GpuError>
+    where
+        F: FnOnce() -> Result<T, GpuError>,
+    {
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let result = f();
+        match pollster::block_on(self.device.pop_error_scope()) {
+            Some(error) => Err(GpuError::Validation(error.to_string())),
+            None => result,
+        }
+    }
+    /// Upload bytes to a storage buffer
+    fn storage(&self, bytes: &[u8]) -> wgpu::Buffer {
+        self.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytes,
+                usage: wgpu::BufferUsages::STORAGE,
+            })
+    }
+    /// Create a storage buffer that a shader writes to and that can be read back
+    fn output(&self, size: u64) -> wgpu::Buffer {
+        self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        })
+    }
+    /// Upload the shader parameters, laid out like `Params` in the shader
+    fn params(
+        &self,
+        src_size: (u32, u32),
+        dst_size: (u32, u32),
+        direction: [i32; 2],
+        sigma: f32,
+    ) -> wgpu::Buffer {
+        let mut bytes = Vec::with_capacity(32);
+        for value in &[src_size.0, src_size.1, dst_size.0, dst_size.1] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in &direction {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(&sigma.to_le_bytes());
+        bytes.extend_from_slice(&0f32.to_le_bytes());
+        self.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: &bytes,
+                usage: wgpu::BufferUsages::UNIFORM,
+            })
+    }
+    /// Record a compute pass that runs a pipeline once for each pixel of an image of the given size
+    fn dispatch(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::ComputePipeline,
+        bindings: &[(u32, &wgpu::Buffer)],
+        (width, height): (u32, u32),
+    ) {
+        let entries: Vec<wgpu::BindGroupEntry> = bindings
+            .iter()
+            .map(|&(binding, buffer)| wgpu::BindGroupEntry {
+                binding,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect();
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &entries,
+        });
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(width.div_ceil(8), height.div_ceil(8), 1);
+    }
+    /// Submit the encoded work and read back the contents of a buffer
+    fn read(
+        &self,
+        mut encoder: wgpu::CommandEncoder,
+        buffer: &wgpu::Buffer,
+        size: u64,
+    ) -> Result<Vec<u8>, GpuError> {
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        encoder.copy_buffer_to_buffer(buffer, 0, &staging, 0, size);
+        self.queue.submit(Some(encoder.finish()));
+        let slice = staging.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        match receiver.recv() {
+            Ok(Ok(())) => {}
+            _ => return Err(GpuError::Readback),
+        }
+        let bytes = slice.get_mapped_range().to_vec();
+        staging.unmap();
+        Ok(bytes)
+    }
+}
+
+/// The number of bytes of RGBA8 pixel data in an image of the given size
+fn pixel_bytes((width, height): (u32, u32)) -> u64 {
+    u64::from(width) * u64::from(height) * 4
+}
@@ -0,0 +1,117 @@
+// Post-processing kernels for `GpuPost`.
+//
+// Pixels are stored as packed RGBA8 values. Filtering is done on
+// premultiplied colors so that transparent pixels do not bleed into their
+// neighbors.
+
+struct Params {
+    src_size: vec2<u32>,
+    dst_size: vec2<u32>,
+    direction: vec2<i32>,
+    sigma: f32,
+    _padding: f32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> src: array<u32>;
+@group(0) @binding(2) var<storage, read_write> dst: array<u32>;
+@group(0) @binding(3) var<storage, read> other: array<u32>;
+@group(0) @binding(4) var<storage, read_write> scores: array<f32>;
+
+fn premultiply(color: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(color.rgb * color.a, color.a);
+}
+
+fn clamp_to_src(p: vec2<i32>) -> u32 {
+    let c = clamp(p, vec2<i32>(0), vec2<i32>(params.src_size) - vec2<i32>(1));
+    return u32(c.y) * params.src_size.x + u32(c.x);
+}
+
+fn load(p: vec2<i32>) -> vec4<f32> {
+    return premultiply(unpack4x8unorm(src[clamp_to_src(p)]));
+}
+
+fn load_other(p: vec2<i32>) -> vec4<f32> {
+    return premultiply(unpack4x8unorm(other[clamp_to_src(p)]));
+}
+
+fn store(p: vec2<u32>, color: vec4<f32>) {
+    var rgb = vec3<f32>(0.0);
+    if color.a > 0.0 {
+        rgb = color.rgb / color.a;
+    }
+    dst[p.y * params.dst_size.x + p.x] = pack4x8unorm(vec4<f32>(rgb, color.a));
+}
+
+fn luma(color: vec4<f32>) -> f32 {
+    return dot(color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+// One pass of a separable Gaussian blur along `params.direction`
+@compute @workgroup_size(8, 8)
+fn blur(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.dst_size.x || id.y >= params.dst_size.y {
+        return;
+    }
+    let radius = i32(ceil(params.sigma * 3.0));
+    var sum = vec4<f32>(0.0);
+    var total = 0.0;
+    for (var i = -radius; i <= radius; i = i + 1) {
+        let weight = exp(-f32(i * i) / (2.0 * params.sigma * params.sigma));
+        sum = sum + weight * load(vec2<i32>(id.xy) + params.direction * i);
+        total = total + weight;
+    }
+    store(id.xy, sum / total);
+}
+
+// Bilinear resampling from `src_size` to `dst_size`
+@compute @workgroup_size(8, 8)
+fn resize(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.dst_size.x || id.y >= params.dst_size.y {
+        return;
+    }
+    let scale = vec2<f32>(params.src_size) / vec2<f32>(params.dst_size);
+    let pos = (vec2<f32>(id.xy) + 0.5) * scale - 0.5;
+    let base = floor(pos);
+    let t = pos - base;
+    let p = vec2<i32>(base);
+    let top = mix(load(p), load(p + vec2<i32>(1, 0)), t.x);
+    let bottom = mix(load(p + vec2<i32>(0, 1)), load(p + vec2<i32>(1, 1)), t.x);
+    store(id.xy, mix(top, bottom, t.y));
+}
+
+// The structural similarity of the luminance of `src` and `other` in a 7x7
+// window around each pixel
+@compute @workgroup_size(8, 8)
+fn ssim(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.src_size.x || id.y >= params.src_size.y {
+        return;
+    }
+    var sum_a = 0.0;
+    var sum_b = 0.0;
+    var sum_aa = 0.0;
+    var sum_bb = 0.0;
+    var sum_ab = 0.0;
+    for (var dy = -3; dy <= 3; dy = dy + 1) {
+        for (var dx = -3; dx <= 3; dx = dx + 1) {
+            let p = vec2<i32>(id.xy) + vec2<i32>(dx, dy);
+            let a = luma(load(p));
+            let b = luma(load_other(p));
+            sum_a = sum_a + a;
+            sum_b = sum_b + b;
+            sum_aa = sum_aa + a * a;
+            sum_bb = sum_bb + b * b;
+            sum_ab = sum_ab + a * b;
+        }
+    }
+    let n = 49.0;
+    let mean_a = sum_a / n;
+    let mean_b = sum_b / n;
+    let var_a = sum_aa / n - mean_a * mean_a;
+    let var_b = sum_bb / n - mean_b * mean_b;
+    let covariance = sum_ab / n - mean_a * mean_b;
+    let c1 = 0.0001;
+    let c2 = 0.0009;
+    scores[id.y * params.src_size.x + id.x] = ((2.0 * mean_a * mean_b + c1) * (2.0 * covariance + c2))
+        / ((mean_a * mean_a + mean_b * mean_b + c1) * (var_a + var_b + c2));
+}
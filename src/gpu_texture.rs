@@ -0,0 +1,382 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use image::imageops::FilterType;
+
+use crate::RenderBuffer;
+
+/// The pixel format used when writing GPU texture containers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextureFormat {
+    /// Uncompressed 8-bit RGBA with linear color
+    Rgba8,
+    /// Uncompressed 8-bit RGBA with sRGB color
+    Rgba8Srgb,
+    /// BC1 (DXT1) block compression with 1-bit alpha and linear color
+    #[cfg(feature = "bc_compression")]
+    Bc1,
+    /// BC1 (DXT1) block compression with 1-bit alpha and sRGB color
+    #[cfg(feature = "bc_compression")]
+    Bc1Srgb,
+    /// BC3 (DXT5) block compression with linear color
+    #[cfg(feature = "bc_compression")]
+    Bc3,
+    /// BC3 (DXT5) block compression with sRGB color
+    #[cfg(feature = "bc_compression")]
+    Bc3Srgb,
+    /// High quality BC7 block compression with linear color
+    #[cfg(feature = "bc_compression")]
+    Bc7,
+    /// High quality BC7 block compression with sRGB color
+    #[cfg(feature = "bc_compression")]
+    Bc7Srgb,
+}
+
+impl TextureFormat {
+    fn is_srgb(self) -> bool {
+        match self {
+            TextureFormat::Rgba8 => false,
+            TextureFormat::Rgba8Srgb => true,
+            #[cfg(feature = "bc_compression")]
+            TextureFormat::Bc1 | TextureFormat::Bc3 | TextureFormat::Bc7 => false,
+            #[cfg(feature = "bc_compression")]
+            TextureFormat::Bc1Srgb | TextureFormat::Bc3Srgb | TextureFormat::Bc7Srgb => true,
+        }
+    }
+    /// Check if the format is block compressed.
+    pub fn is_compressed(self) -> bool {
+        !matches!(self, TextureFormat::Rgba8 | TextureFormat::Rgba8Srgb)
+    }
+    /// The number of bytes in each block of texels
+    fn block_bytes(self) -> usize {
+        match self {
+            TextureFormat::Rgba8 | TextureFormat::Rgba8Srgb => 4,
+            #[cfg(feature = "bc_compression")]
+            TextureFormat::Bc1 | TextureFormat::Bc1Srgb => 8,
+            #[cfg(feature = "bc_compression")]
+            _ => 16,
+        }
+    }
+    fn vk_format(self) -> u32 {
+        match self {
+            TextureFormat::Rgba8 => 37,
+            TextureFormat::Rgba8Srgb => 43,
+            #[cfg(feature = "bc_compression")]
+            TextureFormat::Bc1 => 133,
+            #[cfg(feature = "bc_compression")]
+            TextureFormat::Bc1Srgb => 134,
+            #[cfg(feature = "bc_compression")]
+            TextureFormat::Bc3 => 137,
+            #[cfg(feature = "bc_compression")]
+            TextureFormat::Bc3Srgb => 138,
+            #[cfg(feature = "bc_compression")]
+            TextureFormat::Bc7 => 145,
+            #[cfg(feature = "bc_compression")]
+            TextureFormat::Bc7Srgb => 146,
+        }
+    }
+    fn dxgi_format(self) -> u32 {
+        match self {
+            TextureFormat::Rgba8 => 28,
+            TextureFormat::Rgba8Srgb => 29,
+            #[cfg(feature = "bc_compression")]
+            TextureFormat::Bc1 => 71,
+            #[cfg(feature = "bc_compression")]
+            TextureFormat::Bc1Srgb => 72,
+            #[cfg(feature = "bc_compression")]
+            TextureFormat::Bc3 => 77,
+            #[cfg(feature = "bc_compression")]
+            TextureFormat::Bc3Srgb => 78,
+            #[cfg(feature = "bc_compression")]
+            TextureFormat::Bc7 => 98,
+            #[cfg(feature = "bc_compression")]
+            TextureFormat::Bc7Srgb => 99,
+        }
+    }
+    /// The legacy DDS four-character code for this format, if it has one
+    fn dds_four_cc(self) -> Option<&'static [u8; 4]> {
+        match self {
+            #[cfg(feature = "bc_compression")]
+            TextureFormat::Bc1 => Some(b"DXT1"),
+            #[cfg(feature = "bc_compression")]
+            TextureFormat::Bc3 => Some(b"DXT5"),
+            _ => None,
+        }
+    }
+    /// Encode a single mip level in this format
+    fn level_data(self, level: &RenderBuffer) -> Vec<u8> {
+        if !self.is_compressed() {
+            return level.straight_pixels().into_owned().into_raw();
+        }
+        #[cfg(feature = "bc_compression")]
+        {
+            compress_level(self, level)
+        }
+        #[cfg(not(feature = "bc_compression"))]
+        unreachable!()
+    }
+    /// The Khronos data format descriptor block for this format, without the total size
+    fn dfd_block(self) -> Vec<u32> {
+        let transfer = if self.is_srgb() { 2 } else { 1 };
+        if self.is_compressed() {
+            return self.compressed_dfd_block(transfer);
+        }
+        let mut block = vec![
+            0,
+            2 | (88 << 16),
+            // RGBSDA color model, BT.709 primaries, straight alpha
+            1 | (1 << 8) | (transfer << 16),
+            0,
+            4,
+            0,
+        ];
+        for (i, channel) in [0u32, 1, 2, 15].iter().enumerate() {
+            let linear = if *channel == 15 && self.is_srgb() {
+                0x10
+            } else {
+                0
+            };
+            block.extend_from_slice(&[
+                (i as u32 * 8) | (7 << 16) | ((channel | linear) << 24),
+                0,
+                0,
+                255,
+            ]);
+        }
+        block
+    }
+}
+
+impl TextureFormat {
+    #[cfg(not(feature = "bc_compression"))]
+    fn compressed_dfd_block(self, _transfer: u32) -> Vec<u32> {
+        unreachable!()
+    }
+    #[cfg(feature = "bc_compression")]
+    fn compressed_dfd_block(self, transfer: u32) -> Vec<u32> {
+        // Color model and the channel ID, bit offset and bit length of each sample
+        let (model, samples): (u32, &[(u32, u32, u32)]) = match self {
+            TextureFormat::Bc1 | TextureFormat::Bc1Srgb => (128, &[(1, 0, 64)]),
+            TextureFormat::Bc3 | TextureFormat::Bc3Srgb => (130, &[(15, 0, 64), (0, 64, 64)]),
+            _ => (134, &[(0, 0, 128)]),
+        };
+        let mut block = vec![
+            0,
+            2 | ((24 + 16 * samples.len() as u32) << 16),
+            model | (1 << 8) | (transfer << 16),
+            // 4x4 texel blocks
+            3 | (3 << 8),
+            self.block_bytes() as u32,
+            0,
+        ];
+        for &(channel, offset, length) in samples {
+            let linear = if channel == 15 && self.is_srgb() {
+                0x10
+            } else {
+                0
+            };
+            block.extend_from_slice(&[
+                offset | ((length - 1) << 16) | ((channel | linear) << 24),
+                0,
+                0,
+                u32::MAX,
+            ]);
+        }
+        block
+    }
+}
+
+/// Compress a mip level, padding it to a multiple of 4 texels in each dimension
+#[cfg(feature = "bc_compression")]
+fn compress_level(format: TextureFormat, level: &RenderBuffer) -> Vec<u8> {
+    use intel_tex_2::{bc1, bc3, bc7, RgbaSurface};
+    let pixels = level.straight_pixels();
+    let (width, height) = pixels.dimensions();
+    let (padded_width, padded_height) = (width.div_ceil(4) * 4, height.div_ceil(4) * 4);
+    let padded = image::RgbaImage::from_fn(padded_width, padded_height, |x, y| {
+        *pixels.get_pixel(x.min(width - 1), y.min(height - 1))
+    });
+    let surface = RgbaSurface {
+        data: padded.as_raw(),
+        width: padded_width,
+        height: padded_height,
+        stride: padded_width * 4,
+    };
+    match format {
+        TextureFormat::Bc1 | TextureFormat::Bc1Srgb => bc1::compress_blocks(&surface),
+        TextureFormat::Bc3 | TextureFormat::Bc3Srgb => bc3::compress_blocks(&surface),
+        _ => bc7::compress_blocks(&bc7::alpha_basic_settings(), &surface),
+    }
+}
+
+/**
+Write a mip chain to a KTX2 container.
+
+`levels` should start with the full size image, and each following level
+should be half the size of the one before it, as produced by `build_pyramid`.
+*/
+pub fn write_ktx2<W: Write>(
+    mut writer: W,
+    levels: &[RenderBuffer],
+    format: TextureFormat,
+) -> io::Result<()> {
+    let base = levels
+        .first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no mip levels"))?;
+    let dfd = format.dfd_block();
+    let dfd_len = 4 + dfd.len() * 4;
+    let level_count = levels.len();
+    let header_len = 80 + level_count * 24;
+    let dfd_offset = header_len;
+    let data: Vec<Vec<u8>> = levels.iter().map(|l| format.level_data(l)).collect();
+    // Levels are stored smallest first, each aligned to the block size
+    let align = format.block_bytes().max(4);
+    let mut offsets = vec![0; level_count];
+    let mut cursor = dfd_offset + dfd_len;
+    for i in (0..level_count).rev() {
+        cursor = cursor.div_ceil(align) * align;
+        offsets[i] = cursor;
+        cursor += data[i].len();
+    }
+    let mut out = Vec::with_capacity(cursor);
+    out.extend_from_slice(&[
+        0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+    ]);
+    for value in &[
+        format.vk_format(),
+        1,
+        base.width(),
+        base.height(),
+        0,
+        0,
+        1,
+        level_count as u32,
+        0,
+        dfd_offset as u32,
+        dfd_len as u32,
+        0,
+        0,
+    ] {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    // Supercompression global data
+    out.extend_from_slice(&0u64.to_le_bytes());
+    out.extend_from_slice(&0u64.to_le_bytes());
+    for (offset, level) in offsets.iter().zip(&data) {
+        out.extend_from_slice(&(*offset as u64).to_le_bytes());
+        out.extend_from_slice(&(level.len() as u64).to_le_bytes());
+        out.extend_from_slice(&(level.len() as u64).to_le_bytes());
+    }
+    out.extend_from_slice(&(dfd_len as u32).to_le_bytes());
+    for word in dfd {
+        out.extend_from_slice(&word.to_le_bytes());
+    }
+    for i in (0..level_count).rev() {
+        out.resize(offsets[i], 0);
+        out.extend_from_slice(&data[i]);
+    }
+    writer.write_all(&out)
+}
+
+/**
+Write a mip chain to a DDS container.
+
+`levels` should start with the full size image, and each following level
+should be half the size of the one before it, as produced by `build_pyramid`.
+*/
+pub fn write_dds<W: Write>(
+    mut writer: W,
+    levels: &[RenderBuffer],
+    format: TextureFormat,
+) -> io::Result<()> {
+    let base = levels
+        .first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no mip levels"))?;
+    let mipmapped = levels.len() > 1;
+    let four_cc = format.dds_four_cc();
+    let dx10 = format.is_srgb() || (format.is_compressed() && four_cc.is_none());
+    let data: Vec<Vec<u8>> = levels.iter().map(|l| format.level_data(l)).collect();
+    // Caps, height, width, pixel format, and pitch or linear size flags
+    let mut flags = 0x1 | 0x2 | 0x4 | 0x1000;
+    flags |= if format.is_compressed() {
+        0x8_0000
+    } else {
+        0x8
+    };
+    if mipmapped {
+        flags |= 0x2_0000;
+    }
+    let pitch_or_linear_size = if format.is_compressed() {
+        data[0].len() as u32
+    } else {
+        base.width() * 4
+    };
+    let caps = 0x1000 | if mipmapped { 0x8 | 0x40_0000 } else { 0 };
+    let mut header = vec![
+        124,
+        flags,
+        base.height(),
+        base.width(),
+        pitch_or_linear_size,
+        0,
+        levels.len() as u32,
+    ];
+    header.extend_from_slice(&[0; 11]);
+    if dx10 {
+        // Pixel format with a "DX10" four-character code
+        header.extend_from_slice(&[32, 0x4, u32::from_le_bytes(*b"DX10"), 0, 0, 0, 0, 0]);
+    } else if let Some(four_cc) = four_cc {
+        header.extend_from_slice(&[32, 0x4, u32::from_le_bytes(*four_cc), 0, 0, 0, 0, 0]);
+    } else {
+        // Pixel format with RGB and alpha bit masks
+        header.extend_from_slice(&[
+            32,
+            0x40 | 0x1,
+            0,
+            32,
+            0x0000_00ff,
+            0x0000_ff00,
+            0x00ff_0000,
+            0xff00_0000,
+        ]);
+    }
+    header.extend_from_slice(&[caps, 0, 0, 0, 0]);
+    if dx10 {
+        // DXGI format, 2D texture dimension, no flags, one array element
+        header.extend_from_slice(&[format.dxgi_format(), 3, 0, 1, 0]);
+    }
+    let mut out = Vec::from(&b"DDS "[..]);
+    for word in header {
+        out.extend_from_slice(&word.to_le_bytes());
+    }
+    for level in data {
+        out.extend_from_slice(&level);
+    }
+    writer.write_all(&out)
+}
+
+impl RenderBuffer {
+    /// Save the buffer and `mip_levels - 1` generated mip levels to a KTX2 file.
+    pub fn save_ktx2<P: AsRef<Path>>(
+        &self,
+        path: P,
+        format: TextureFormat,
+        mip_levels: usize,
+    ) -> io::Result<()> {
+        let levels = self.build_pyramid(mip_levels.max(1), FilterType::Triangle);
+        write_ktx2(BufWriter::new(File::create(path)?), &levels, format)
+    }
+    /// Save the buffer and `mip_levels - 1` generated mip levels to a DDS file.
+    pub fn save_dds<P: AsRef<Path>>(
+        &self,
+        path: P,
+        format: TextureFormat,
+        mip_levels: usize,
+    ) -> io::Result<()> {
+        let levels = self.build_pyramid(mip_levels.max(1), FilterType::Triangle);
+        write_dds(BufWriter::new(File::create(path)?), &levels, format)
+    }
+}
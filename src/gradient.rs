@@ -0,0 +1,84 @@
+use graphics::{
+    draw_state::DrawState,
+    math::{self, Matrix2d},
+};
+
+use crate::RenderBuffer;
+
+impl RenderBuffer {
+    /**
+    Draw triangles with colors interpolated between their vertices.
+
+    Every 3 vertices form a triangle, and each vertex has the color at the same
+    index in `colors`. Vertices are in pixel coordinates. This is the same as
+    calling `Graphics::tri_list_c` with a single batch.
+
+    # Panics
+
+    Panics if `vertices` and `colors` have different lengths.
+    */
+    pub fn tri_list_colored(
+        &mut self,
+        draw_state: &DrawState,
+        vertices: &[[f32; 2]],
+        colors: &[[f32; 4]],
+    ) {
+        assert_eq!(
+            vertices.len(),
+            colors.len(),
+            "Each vertex must have a color"
+        );
+        self.draw_tris_c(draw_state.into(), |f| f(vertices, colors));
+    }
+    /**
+    Fill a rectangle with colors interpolated between its corners.
+
+    The rectangle is given as `[x, y, width, height]`. The corner colors are in
+    the order top left, top right, bottom right, bottom left. Two corners with the
+    same color on each side make a linear gradient.
+    */
+    pub fn fill_gradient_rect(
+        &mut self,
+        rect: [f64; 4],
+        colors: [[f32; 4]; 4],
+        draw_state: &DrawState,
+        transform: Matrix2d,
+    ) {
+        let [x, y, w, h] = rect;
+        self.fill_gradient_polygon(
+            &[[x, y], [x + w, y], [x + w, y + h], [x, y + h]],
+            &colors,
+            draw_state,
+            transform,
+        );
+    }
+    /**
+    Fill a convex polygon with colors interpolated between its vertices.
+
+    Each vertex has the color at the same index in `colors`. The polygon is
+    split into a fan of triangles around its first vertex, so concave polygons
+    are not filled correctly.
+
+    # Panics
+
+    Panics if `polygon` and `colors` have different lengths.
+    */
+    pub fn fill_gradient_polygon(
+        &mut self,
+        polygon: &[[f64; 2]],
+        colors: &[[f32; 4]],
+        draw_state: &DrawState,
+        transform: Matrix2d,
+    ) {
+        assert_eq!(polygon.len(), colors.len(), "Each vertex must have a color");
+        let point = |point| {
+            let [x, y] = math::transform_pos(transform, point);
+            [x as f32, y as f32]
+        };
+        let (vertices, vertex_colors): (Vec<_>, Vec<_>) = (1..polygon.len().saturating_sub(1))
+            .flat_map(|i| [0, i, i + 1])
+            .map(|i| (point(polygon[i]), colors[i]))
+            .unzip();
+        self.tri_list_colored(draw_state, &vertices, &vertex_colors);
+    }
+}
@@ -0,0 +1,79 @@
+use std::path::Path;
+
+use image::{ImageBuffer, ImageResult, Rgba};
+
+use crate::RenderBuffer;
+
+/// An image with 32-bit floating point RGBA pixels
+pub type Rgba32FImage = ImageBuffer<Rgba<f32>, Vec<f32>>;
+
+/// How colors outside of `[0, 1]` are brought into range when converting to 8 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tonemap {
+    /// Clip each channel to `[0, 1]`
+    Clamp,
+    /// Compress each channel with `c / (1 + c)`, which keeps detail in bright areas
+    Reinhard,
+}
+
+impl Tonemap {
+    pub(crate) fn apply(self, c: f32) -> f32 {
+        match self {
+            Tonemap::Clamp => c.clamp(0.0, 1.0),
+            Tonemap::Reinhard => {
+                let c = c.max(0.0);
+                c / (1.0 + c)
+            }
+        }
+    }
+}
+
+/**
+A render target with 32-bit floating point pixels.
+
+Colors are stored and blended without being rounded to 8 bits or clipped to
+`[0, 1]`, so many translucent layers or glows drawn over each other do not
+band or saturate. Alpha is always kept in `[0, 1]`. With the `openexr`
+feature, the pixels can be saved to an OpenEXR file directly. Use `tonemap`
+to convert to an 8-bit `RenderBuffer`.
+*/
+pub type RenderBufferF32 = RenderBuffer<Rgba<f32>>;
+
+impl RenderBufferF32 {
+    /**
+    Save the buffer to a file, with the format deduced from the extension.
+
+    Files with the `.exr` extension keep the full range of the pixels if the
+    `openexr` feature is enabled. Other formats are clamped to 8 bits.
+    */
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        #[cfg(feature = "openexr")]
+        {
+            let is_exr = path
+                .as_ref()
+                .extension()
+                .map_or(false, |ext| ext.eq_ignore_ascii_case("exr"));
+            if is_exr {
+                return self.save_exr(path);
+            }
+        }
+        self.tonemap(Tonemap::Clamp).save(path)
+    }
+    /// Save the pixels to an OpenEXR file as they are.
+    #[cfg(feature = "openexr")]
+    pub fn save_exr<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        use image::error::{EncodingError, ImageError, ImageFormatHint};
+
+        let (width, height) = self.dimensions();
+        exr::prelude::write_rgba_file(path, width as usize, height as usize, |x, y| {
+            let [r, g, b, a] = self.pixel(x as u32, y as u32);
+            (r, g, b, a)
+        })
+        .map_err(|e| {
+            ImageError::Encoding(EncodingError::new(
+                ImageFormatHint::Name("OpenEXR".into()),
+                e.to_string(),
+            ))
+        })
+    }
+}
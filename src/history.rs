@@ -0,0 +1,181 @@
+use std::sync::Arc;
+
+use image::RgbaImage;
+
+use crate::RenderBuffer;
+
+/// The number of zero bytes in a row that ends a literal run
+const MIN_ZERO_RUN: usize = 8;
+
+/**
+A losslessly compressed copy of a `RenderBuffer`'s pixels.
+
+A keyframe stores each pixel as its difference from the pixel before it, and
+a delta frame stores each pixel as its difference from the same pixel in a
+keyframe. Runs of unchanged bytes are then stored as a count, so flat areas
+and parts of a recording that do not change between frames take almost no
+memory. This makes long recordings and undo histories practical to keep in memory.
+
+Delta frames share their keyframe through an `Arc`, and can be decompressed
+on their own. Rendering settings are not kept, and pixels are stored with
+straight alpha.
+*/
+#[derive(Debug, Clone)]
+pub struct CompressedFrame {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+    keyframe: Option<Arc<CompressedFrame>>,
+}
+
+impl CompressedFrame {
+    /// Compress a buffer as a keyframe, which does not depend on any other frame.
+    pub fn keyframe(buffer: &RenderBuffer) -> CompressedFrame {
+        let pixels = buffer.straight_pixels();
+        let bytes: &[u8] = &pixels;
+        let residuals: Vec<u8> = bytes
+            .iter()
+            .enumerate()
+            .map(|(i, &byte)| if i < 4 { byte } else { byte ^ bytes[i - 4] })
+            .collect();
+        CompressedFrame {
+            width: buffer.width(),
+            height: buffer.height(),
+            data: encode_runs(&residuals),
+            keyframe: None,
+        }
+    }
+    /**
+    Compress a buffer as its difference from a keyframe.
+
+    If `keyframe` is itself a delta frame, its own keyframe is used instead.
+    If the buffer and the keyframe have different dimensions, the buffer is
+    compressed as a new keyframe.
+    */
+    pub fn delta(buffer: &RenderBuffer, keyframe: &Arc<CompressedFrame>) -> CompressedFrame {
+        let keyframe = keyframe.keyframe.as_ref().unwrap_or(keyframe);
+        if keyframe.dimensions() != buffer.dimensions() {
+            return CompressedFrame::keyframe(buffer);
+        }
+        let base = keyframe.decode();
+        let pixels = buffer.straight_pixels();
+        let residuals: Vec<u8> = pixels.iter().zip(base.iter()).map(|(a, b)| a ^ b).collect();
+        CompressedFrame {
+            width: buffer.width(),
+            height: buffer.height(),
+            data: encode_runs(&residuals),
+            keyframe: Some(Arc::clone(keyframe)),
+        }
+    }
+    /// Check if the frame is a keyframe.
+    pub fn is_keyframe(&self) -> bool {
+        self.keyframe.is_none()
+    }
+    /// Get the keyframe of a delta frame.
+    pub fn base(&self) -> Option<&Arc<CompressedFrame>> {
+        self.keyframe.as_ref()
+    }
+    /// Get the width and height of the frame.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+    /// Get the number of bytes of compressed data, not including the keyframe of a delta frame.
+    pub fn compressed_size(&self) -> usize {
+        self.data.len()
+    }
+    /// Decompress the frame into a new `RenderBuffer`.
+    pub fn decompress(&self) -> RenderBuffer {
+        self.decode().into()
+    }
+    fn decode(&self) -> RgbaImage {
+        let len = self.width as usize * self.height as usize * 4;
+        let mut bytes = decode_runs(&self.data, len);
+        match &self.keyframe {
+            Some(keyframe) => {
+                for (byte, base) in bytes.iter_mut().zip(keyframe.decode().iter()) {
+                    *byte ^= base;
+                }
+            }
+            None => {
+                let mut previous = [0; 4];
+                for pixel in bytes.chunks_exact_mut(4) {
+                    for (byte, previous) in pixel.iter_mut().zip(&mut previous) {
+                        *byte ^= *previous;
+                        *previous = *byte;
+                    }
+                }
+            }
+        }
+        RgbaImage::from_raw(self.width, self.height, bytes)
+            .expect("Compressed frame has the wrong number of bytes")
+    }
+}
+
+impl From<&CompressedFrame> for RenderBuffer {
+    fn from(frame: &CompressedFrame) -> Self {
+        frame.decompress()
+    }
+}
+
+/// Append a number as a variable length integer, 7 bits at a time
+fn write_varint(mut value: usize, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Read a variable length integer, advancing the position past it
+fn read_varint(data: &[u8], pos: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    while let Some(&byte) = data.get(*pos) {
+        *pos += 1;
+        value |= usize::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    value
+}
+
+/// Encode bytes as pairs of a run of zeros followed by a run of literal bytes
+fn encode_runs(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let zeros = bytes[i..].iter().take_while(|&&byte| byte == 0).count();
+        i += zeros;
+        // A literal run continues until enough zeros in a row are worth a run of their own
+        let start = i;
+        let mut zero_run = 0;
+        while i < bytes.len() && zero_run < MIN_ZERO_RUN {
+            zero_run = if bytes[i] == 0 { zero_run + 1 } else { 0 };
+            i += 1;
+        }
+        if zero_run >= MIN_ZERO_RUN {
+            i -= zero_run;
+        }
+        write_varint(zeros, &mut out);
+        write_varint(i - start, &mut out);
+        out.extend_from_slice(&bytes[start..i]);
+    }
+    out
+}
+
+/// Decode bytes encoded by `encode_runs`
+fn decode_runs(data: &[u8], len: usize) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(len);
+    let mut pos = 0;
+    while pos < data.len() {
+        let zeros = read_varint(data, &mut pos);
+        bytes.resize(bytes.len() + zeros, 0);
+        let literal = read_varint(data, &mut pos);
+        bytes.extend_from_slice(&data[pos..pos + literal]);
+        pos += literal;
+    }
+    bytes.resize(len, 0);
+    bytes
+}
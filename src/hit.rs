@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use graphics::math::Vec2d;
+
+use crate::{FillRule, Path};
+
+/// A named path registered in `HitRegions`
+#[derive(Debug, Clone, PartialEq)]
+struct HitRegion {
+    id: u32,
+    name: String,
+    path: Path,
+    fill_rule: FillRule,
+}
+
+/**
+A registry of named paths that maps points back to the elements drawn there.
+
+Each region is given a unique ID when it is added. Paths are in the same
+coordinates as the points tested against them, which are usually the pixels
+of the buffer the paths were drawn into. When regions overlap, the one added
+last is considered to be on top, matching the order they would be drawn in.
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HitRegions {
+    regions: Vec<HitRegion>,
+    ids: HashMap<String, u32>,
+    next_id: u32,
+}
+
+impl HitRegions {
+    /// Create a new empty `HitRegions`.
+    pub fn new() -> Self {
+        HitRegions::default()
+    }
+    /**
+    Add a region, returning its ID.
+
+    A region previously registered with the same name is replaced.
+    */
+    pub fn insert<S: Into<String>>(&mut self, name: S, path: Path, fill_rule: FillRule) -> u32 {
+        let name = name.into();
+        self.remove(&name);
+        let id = self.next_id;
+        self.next_id += 1;
+        self.ids.insert(name.clone(), id);
+        self.regions.push(HitRegion {
+            id,
+            name,
+            path,
+            fill_rule,
+        });
+        id
+    }
+    /// Add a region.
+    pub fn with<S: Into<String>>(mut self, name: S, path: Path, fill_rule: FillRule) -> Self {
+        self.insert(name, path, fill_rule);
+        self
+    }
+    /// Remove a region by name, returning its path.
+    pub fn remove(&mut self, name: &str) -> Option<Path> {
+        let id = self.ids.remove(name)?;
+        let index = self.regions.iter().position(|region| region.id == id)?;
+        Some(self.regions.remove(index).path)
+    }
+    /// Get the ID of a region.
+    pub fn id(&self, name: &str) -> Option<u32> {
+        self.ids.get(name).copied()
+    }
+    /// Get the name of the region with an ID.
+    pub fn name(&self, id: u32) -> Option<&str> {
+        self.region(id).map(|region| region.name.as_str())
+    }
+    /// Get the path of the region with an ID.
+    pub fn path(&self, id: u32) -> Option<&Path> {
+        self.region(id).map(|region| &region.path)
+    }
+    /// Get the ID of the topmost region that contains a point.
+    pub fn hit(&self, point: Vec2d) -> Option<u32> {
+        self.hits(point).next()
+    }
+    /// Get the IDs of all regions that contain a point, from top to bottom.
+    pub fn hits(&self, point: Vec2d) -> impl Iterator<Item = u32> + '_ {
+        self.regions
+            .iter()
+            .rev()
+            .filter(move |region| region.path.contains_with_rule(point, region.fill_rule))
+            .map(|region| region.id)
+    }
+    /// Get the number of regions.
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+    /// Check if there are no regions.
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+    fn region(&self, id: u32) -> Option<&HitRegion> {
+        self.regions.iter().find(|region| region.id == id)
+    }
+}
@@ -0,0 +1,184 @@
+#[cfg(feature = "tiny_skia_interop")]
+use image::RgbaImage;
+#[cfg(feature = "tiny_skia_interop")]
+use tiny_skia::{IntSize, Pixmap, PixmapMut, PixmapRef};
+
+#[cfg(feature = "tiny_skia_interop")]
+use crate::{premultiply_rgba8, unpremultiply_rgba8, RenderBuffer};
+
+#[cfg(feature = "tiny_skia_interop")]
+impl RenderBuffer {
+    /**
+    Borrow the buffer's pixels as a `tiny_skia::PixmapMut` for the duration of a closure.
+
+    `tiny-skia` works with premultiplied alpha. If the buffer is premultiplied,
+    its pixels are borrowed directly. Otherwise `f` draws into a premultiplied
+    copy, and only the pixels it changes are converted back, so translucent
+    pixels that are not drawn over keep their exact values. Returns `None` if
+    the buffer has a width or height of zero.
+    */
+    pub fn with_pixmap_mut<F, R>(&mut self, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut PixmapMut) -> R,
+    {
+        let (width, height) = self.inner.dimensions();
+        self.mark_all_dirty();
+        if self.premultiplied {
+            return PixmapMut::from_bytes(&mut self.inner, width, height)
+                .map(|mut pixmap| f(&mut pixmap));
+        }
+        let mut data = self.inner.as_raw().clone();
+        premultiply_rgba8(&mut data);
+        let result =
+            PixmapMut::from_bytes(&mut data, width, height).map(|mut pixmap| f(&mut pixmap))?;
+        for (dst, src) in self.inner.chunks_exact_mut(4).zip(data.chunks_exact_mut(4)) {
+            let mut before = [dst[0], dst[1], dst[2], dst[3]];
+            premultiply_rgba8(&mut before);
+            if before[..] != src[..] {
+                unpremultiply_rgba8(src);
+                dst.copy_from_slice(src);
+            }
+        }
+        Some(result)
+    }
+    /// Copy the buffer into a new `tiny_skia::Pixmap`.
+    ///
+    /// Returns `None` if the buffer has a width or height of zero.
+    pub fn to_pixmap(&self) -> Option<Pixmap> {
+        let (width, height) = self.inner.dimensions();
+        let mut data = self.inner.as_raw().clone();
+        if !self.premultiplied {
+            premultiply_rgba8(&mut data);
+        }
+        Pixmap::from_vec(data, IntSize::from_wh(width, height)?)
+    }
+    /// Copy a `tiny_skia` pixmap into a new `RenderBuffer`.
+    pub fn from_pixmap(pixmap: PixmapRef) -> RenderBuffer {
+        let mut data = pixmap.data().to_vec();
+        unpremultiply_rgba8(&mut data);
+        RgbaImage::from_raw(pixmap.width(), pixmap.height(), data)
+            .expect("pixmap data matches its dimensions")
+            .into()
+    }
+}
+
+#[cfg(feature = "tiny_skia_interop")]
+impl From<Pixmap> for RenderBuffer {
+    fn from(pixmap: Pixmap) -> Self {
+        RenderBuffer::from_pixmap(pixmap.as_ref())
+    }
+}
+
+#[cfg(feature = "raqote_interop")]
+impl RenderBuffer {
+    /// Copy the buffer into a new `raqote::DrawTarget`.
+    pub fn to_draw_target(&self) -> raqote::DrawTarget {
+        let (width, height) = self.inner.dimensions();
+        let mut target = raqote::DrawTarget::new(width as i32, height as i32);
+        for (dst, src) in target
+            .get_data_mut()
+            .iter_mut()
+            .zip(self.straight_pixels().pixels())
+        {
+            *dst = pack_premultiplied_argb(src.0);
+        }
+        target
+    }
+    /// Copy a `raqote::DrawTarget` into a new `RenderBuffer`.
+    pub fn from_draw_target(target: &raqote::DrawTarget) -> RenderBuffer {
+        let (width, height) = (target.width() as u32, target.height() as u32);
+        let mut image = image::RgbaImage::new(width, height);
+        for (dst, &src) in image.pixels_mut().zip(target.get_data()) {
+            dst.0 = unpack_premultiplied_argb(src);
+        }
+        image.into()
+    }
+}
+
+#[cfg(feature = "raqote_interop")]
+impl From<raqote::DrawTarget> for RenderBuffer {
+    fn from(target: raqote::DrawTarget) -> Self {
+        RenderBuffer::from_draw_target(&target)
+    }
+}
+
+#[cfg(feature = "cairo_interop")]
+impl RenderBuffer {
+    /// Copy the buffer into a new `cairo::ImageSurface` with the `ARgb32` format.
+    pub fn to_cairo_surface(&self) -> Result<cairo::ImageSurface, Box<dyn std::error::Error>> {
+        let (width, height) = self.inner.dimensions();
+        let mut surface =
+            cairo::ImageSurface::create(cairo::Format::ARgb32, width as i32, height as i32)?;
+        let stride = surface.stride() as usize;
+        {
+            let mut data = surface.data()?;
+            for (y, row) in self.straight_pixels().rows().enumerate() {
+                for (x, pixel) in row.enumerate() {
+                    let i = y * stride + x * 4;
+                    data[i..i + 4].copy_from_slice(&pack_premultiplied_argb(pixel.0).to_ne_bytes());
+                }
+            }
+        }
+        Ok(surface)
+    }
+    /// Copy a `cairo::ImageSurface` with the `ARgb32` format into a new `RenderBuffer`.
+    pub fn from_cairo_surface(
+        surface: &cairo::ImageSurface,
+    ) -> Result<RenderBuffer, Box<dyn std::error::Error>> {
+        let (width, height) = (surface.width() as u32, surface.height() as u32);
+        let stride = surface.stride() as usize;
+        let mut image = image::RgbaImage::new(width, height);
+        surface.flush();
+        surface.with_data(|data| {
+            for (x, y, pixel) in image.enumerate_pixels_mut() {
+                let i = y as usize * stride + x as usize * 4;
+                let packed = u32::from_ne_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+                pixel.0 = unpack_premultiplied_argb(packed);
+            }
+        })?;
+        Ok(image.into())
+    }
+}
+
+/// Pack a straight RGBA8 color into a premultiplied `0xAARRGGBB` word
+#[cfg(any(feature = "raqote_interop", feature = "cairo_interop"))]
+fn pack_premultiplied_argb([r, g, b, a]: [u8; 4]) -> u32 {
+    let premultiply = |c: u8| (u32::from(c) * u32::from(a) + 127) / 255;
+    (u32::from(a) << 24) | (premultiply(r) << 16) | (premultiply(g) << 8) | premultiply(b)
+}
+
+/// Unpack a premultiplied `0xAARRGGBB` word into a straight RGBA8 color
+#[cfg(any(feature = "raqote_interop", feature = "cairo_interop"))]
+fn unpack_premultiplied_argb(packed: u32) -> [u8; 4] {
+    let a = packed >> 24;
+    let unpremultiply = |c: u32| (c * 255 + a / 2).checked_div(a).unwrap_or(0).min(255) as u8;
+    [
+        unpremultiply((packed >> 16) & 0xff),
+        unpremultiply((packed >> 8) & 0xff),
+        unpremultiply(packed & 0xff),
+        a as u8,
+    ]
+}
+
+#[cfg(all(test, feature = "tiny_skia_interop"))]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use crate::RenderBuffer;
+
+    #[test]
+    fn pixmap_mut_keeps_pixels_that_are_not_drawn() {
+        // Premultiplying this pixel at 8 bits and converting it back loses its color
+        let translucent = Rgba([201, 77, 3, 10]);
+        let mut buffer = RenderBuffer::from(RgbaImage::from_pixel(2, 1, translucent));
+        buffer.with_pixmap_mut(|_| {});
+        assert_eq!(buffer.get_pixel(0, 0), &translucent);
+        assert_eq!(buffer.get_pixel(1, 0), &translucent);
+        buffer.with_pixmap_mut(|pixmap| {
+            pixmap.pixels_mut()[1] =
+                tiny_skia::PremultipliedColorU8::from_rgba(0, 0, 0, 0).unwrap();
+        });
+        assert_eq!(buffer.get_pixel(0, 0), &translucent);
+        assert_eq!(buffer.get_pixel(1, 0), &Rgba([0, 0, 0, 0]));
+    }
+}
@@ -0,0 +1,249 @@
+use image::imageops;
+
+use crate::{Regions, RenderBuffer};
+
+/// The axis along which a `Layout` places its items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Items are placed left to right
+    Row,
+    /// Items are placed top to bottom
+    Column,
+}
+
+/// How items are positioned across the axis of a `Layout`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Align {
+    /// Items are placed at the top or left
+    Start,
+    /// Items are centered
+    Center,
+    /// Items are placed at the bottom or right
+    End,
+    /// Items fill the available space
+    #[default]
+    Stretch,
+}
+
+/// The size of an item along the axis of a `Layout`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Size {
+    /// A fixed number of pixels
+    Fixed(u32),
+    /// A share of the space left over after fixed items, proportional to the weight
+    Weight(f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LayoutItem {
+    size: Size,
+    cross: Option<u32>,
+}
+
+/**
+A row or column of items with padding, gaps, weights and alignment.
+
+A layout computes rectangles, given as `[x, y, width, height]` in pixels,
+that rendered elements can be placed into with `RenderBuffer::place` or
+drawn into with `Regions::draw_into`.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    direction: Direction,
+    padding: u32,
+    gap: u32,
+    align: Align,
+    items: Vec<LayoutItem>,
+}
+
+impl Layout {
+    /// Create a new empty `Layout` in the given direction.
+    pub fn new(direction: Direction) -> Self {
+        Layout {
+            direction,
+            padding: 0,
+            gap: 0,
+            align: Align::default(),
+            items: Vec::new(),
+        }
+    }
+    /// Create a new empty `Layout` that places items left to right.
+    pub fn row() -> Self {
+        Layout::new(Direction::Row)
+    }
+    /// Create a new empty `Layout` that places items top to bottom.
+    pub fn column() -> Self {
+        Layout::new(Direction::Column)
+    }
+    /// Set the space between the edge of the layout and its items.
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+    /// Set the space between adjacent items.
+    pub fn gap(mut self, gap: u32) -> Self {
+        self.gap = gap;
+        self
+    }
+    /// Set how items with a cross size are positioned across the axis.
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+    /// Add an item with a fixed size that fills the cross axis.
+    pub fn fixed(self, size: u32) -> Self {
+        self.item(Size::Fixed(size), None)
+    }
+    /// Add an item with a weighted size that fills the cross axis.
+    pub fn weighted(self, weight: f32) -> Self {
+        self.item(Size::Weight(weight), None)
+    }
+    /**
+    Add an item.
+
+    If `cross` is given, the item has that size across the axis and is
+    positioned according to the alignment. Otherwise, it fills the cross axis.
+    */
+    pub fn item(mut self, size: Size, cross: Option<u32>) -> Self {
+        self.items.push(LayoutItem { size, cross });
+        self
+    }
+    /// Get the number of items.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+    /// Check if there are no items.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+    /**
+    Compute the rectangles of the items within the given rectangle.
+
+    Space left over after padding, gaps and fixed items is shared between
+    weighted items. If there is not enough space, items are shrunk from the end.
+    */
+    pub fn compute(&self, rect: [u32; 4]) -> Vec<[u32; 4]> {
+        let [x, y, width, height] = rect;
+        let (main_start, main_len, cross_start, cross_len) = match self.direction {
+            Direction::Row => (x, width, y, height),
+            Direction::Column => (y, height, x, width),
+        };
+        let main_start = main_start + self.padding;
+        let main_len = main_len.saturating_sub(2 * self.padding);
+        let cross_start = cross_start + self.padding;
+        let cross_len = cross_len.saturating_sub(2 * self.padding);
+        let gaps = self.gap * (self.items.len().max(1) as u32 - 1);
+        let fixed: u32 = self
+            .items
+            .iter()
+            .map(|item| match item.size {
+                Size::Fixed(size) => size,
+                Size::Weight(_) => 0,
+            })
+            .sum();
+        let total_weight: f32 = self
+            .items
+            .iter()
+            .map(|item| match item.size {
+                Size::Fixed(_) => 0.0,
+                Size::Weight(weight) => weight.max(0.0),
+            })
+            .sum();
+        let free = main_len.saturating_sub(gaps + fixed) as f32;
+        let main_end = main_start + main_len;
+        let mut weight_so_far = 0.0;
+        let mut rounded_so_far = 0;
+        let mut cursor = main_start;
+        let mut rects = Vec::with_capacity(self.items.len());
+        for item in &self.items {
+            let size = match item.size {
+                Size::Fixed(size) => size,
+                Size::Weight(weight) if total_weight > 0.0 => {
+                    // Round cumulative positions so weighted items exactly fill the space
+                    weight_so_far += weight.max(0.0);
+                    let end = (free * weight_so_far / total_weight).round() as u32;
+                    let size = end - rounded_so_far;
+                    rounded_so_far = end;
+                    size
+                }
+                Size::Weight(_) => 0,
+            };
+            let start = cursor.min(main_end);
+            let size = size.min(main_end - start);
+            let (item_cross_start, item_cross_len) = match (item.cross, self.align) {
+                (Some(cross), Align::Start) => (cross_start, cross.min(cross_len)),
+                (Some(cross), Align::Center) => {
+                    let cross = cross.min(cross_len);
+                    (cross_start + (cross_len - cross) / 2, cross)
+                }
+                (Some(cross), Align::End) => {
+                    let cross = cross.min(cross_len);
+                    (cross_start + cross_len - cross, cross)
+                }
+                _ => (cross_start, cross_len),
+            };
+            rects.push(match self.direction {
+                Direction::Row => [start, item_cross_start, size, item_cross_len],
+                Direction::Column => [item_cross_start, start, item_cross_len, size],
+            });
+            cursor = start + size + self.gap;
+        }
+        rects
+    }
+    /// Compute the rectangles of the items and register them as named regions.
+    ///
+    /// Names are matched with items in order. Extra names or items are ignored.
+    pub fn regions<I, S>(&self, rect: [u32; 4], names: I) -> Regions
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut regions = Regions::new();
+        for (name, rect) in names.into_iter().zip(self.compute(rect)) {
+            regions.insert(name, rect);
+        }
+        regions
+    }
+}
+
+/**
+Compute the rectangles of an evenly sized grid of cells within a rectangle.
+
+Cells are returned in row-major order. Leftover pixels are shared so that
+the cells exactly fill the space.
+*/
+pub fn grid(rect: [u32; 4], columns: u32, rows: u32, padding: u32, gap: u32) -> Vec<[u32; 4]> {
+    let mut row_layout = Layout::column().padding(padding).gap(gap);
+    for _ in 0..rows {
+        row_layout = row_layout.weighted(1.0);
+    }
+    let mut column_layout = Layout::row().gap(gap);
+    for _ in 0..columns {
+        column_layout = column_layout.weighted(1.0);
+    }
+    let mut cells = Vec::with_capacity((columns * rows) as usize);
+    for row in row_layout.compute(rect) {
+        cells.extend(column_layout.compute(row));
+    }
+    cells
+}
+
+impl RenderBuffer {
+    /**
+    Draw another buffer over this one at the top-left corner of a rectangle.
+
+    Anything outside of the rectangle or this buffer is clipped.
+    */
+    pub fn place(&mut self, element: &RenderBuffer, rect: [u32; 4]) {
+        let [x, y, width, height] = rect;
+        let clipped = imageops::crop_imm(&element.inner, 0, 0, width, height).to_image();
+        let (buffer_width, buffer_height) = self.dimensions();
+        self.mark_dirty([
+            x.min(buffer_width),
+            y.min(buffer_height),
+            clipped.width().min(buffer_width.saturating_sub(x)),
+            clipped.height().min(buffer_height.saturating_sub(y)),
+        ]);
+        imageops::overlay(&mut self.inner, &clipped, x, y);
+    }
+}
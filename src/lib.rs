@@ -12,22 +12,150 @@ converted into a `G2dTexture` so that it can be rendered with
 enable this, add `features = ["piston_window_texture"]` to the `graphics_buffer`
 dependency in your `cargo.toml`. More about this feature can be found in
 the [`RenderBuffer` documentation](struct.RenderBuffer.html).
+
+Some of the crate's dependencies can be turned off by disabling default features:
+
+* `parallel` renders tiles of the buffer in parallel with `rayon`, on the
+  global pool or one set with `RenderBuffer::set_thread_pool`
+* `parallel_jpeg` decodes JPEGs in parallel
+* `text` enables `BufferGlyphs` for drawing text with `rusttype`
+* `png_decode` opens PNG files with a fast direct decoder
+* `formats_extra` adds support for image formats other than PNG
+
+With none of them, shapes can still be rendered and saved as PNGs or raw bytes.
+Without `parallel` and `parallel_jpeg`, the crate does not depend on `rayon`
+and renders on the calling thread, which suits embedded and wasm targets.
+
+Colors drawn with alpha blending are composited with standard Porter-Duff
+source-over, matching GPU backends and image editors.
+`RenderBuffer::set_legacy_blending` restores the quadratic alpha weighting of
+older versions.
+
+Rendering is deterministic. Each tile of a buffer is drawn by a single thread,
+which draws the triangles of a call in order, so the same draw calls always
+produce the same pixels regardless of the number of threads or whether
+`parallel` is enabled. Golden-image tests can compare output byte for byte.
+
+With the `http_assets` feature, images can be downloaded from URLs with
+`RenderBuffer::fetch`, and scene images can be URLs, which are cached and
+size-limited by the scene's `AssetCache` and only downloaded from the hosts it
+allows.
+
+`RichText` flows colored text and small inline images together with any
+`CharacterCache`, and expands `:smile:`-style shortcodes with `Shortcodes`,
+for rendering chat transcripts and badges.
+
+Downstreams on a newer Piston stack can enable the `piston_graphics_0_44` feature,
+which also implements `Graphics` and the texture traits for `piston2d-graphics`
+0.44 and `piston-texture` 0.9.
 */
 
+mod accessibility;
+mod animation;
+#[cfg(all(feature = "scene", feature = "parallel"))]
+mod batch;
+#[cfg(feature = "screen_capture")]
+mod capture;
+mod checkpoint;
+#[cfg(feature = "piston_graphics_0_44")]
+mod compat;
+mod composite;
+mod coverage;
+mod depth;
+mod dirty;
+mod draw_state;
+mod encode;
+mod filters;
+mod fixed;
+#[cfg(feature = "text")]
 mod glyphs;
+#[cfg(feature = "gpu_post")]
+mod gpu_post;
+mod gpu_texture;
+mod gradient;
+mod hdr;
+mod history;
+mod hit;
+mod interop;
+mod layout;
+mod limits;
+mod load;
+mod locale;
+mod luma;
+mod memory;
+mod mockup;
+mod origin;
+mod palette;
+mod path;
+mod perceptual;
+mod picking;
+mod placeholder;
+mod planar;
+mod pool;
+mod premultiplied;
+mod progress;
+mod pyramid;
+mod query;
+mod raster;
+mod regions;
+#[cfg(feature = "http_assets")]
+mod remote;
+mod rich_text;
+mod sampling;
+mod scale;
+#[cfg(feature = "scene")]
+mod scene;
+mod scene_graph;
+mod sequence;
+mod sprite;
+mod stitch;
+mod store;
+mod supersample;
+mod theme;
+#[cfg(feature = "parallel")]
+mod threading;
+mod timeline;
+mod viewport;
+mod warp;
+#[cfg(all(feature = "scene", feature = "parallel"))]
+pub use crate::batch::*;
+#[cfg(feature = "text")]
 pub use crate::glyphs::*;
+#[cfg(feature = "gpu_post")]
+pub use crate::gpu_post::*;
+#[cfg(feature = "http_assets")]
+pub use crate::remote::*;
+#[cfg(feature = "scene")]
+pub use crate::scene::*;
+pub use crate::{
+    accessibility::*, animation::*, checkpoint::*, composite::*, coverage::*, depth::*, encode::*,
+    filters::*, fixed::*, gpu_texture::*, hdr::*, history::*, hit::*, layout::*, limits::*,
+    locale::*, luma::*, memory::*, mockup::*, origin::*, path::*, perceptual::*, picking::*,
+    planar::*, pool::*, progress::*, pyramid::*, raster::*, regions::*, rich_text::*, sampling::*,
+    scale::*, scene_graph::*, sequence::*, sprite::*, stitch::*, store::*, supersample::*,
+    theme::*, timeline::*, viewport::*,
+};
 
-use std::{error, fmt, fs::File, ops, path::Path};
+use std::{
+    collections::BTreeMap,
+    error, fmt,
+    hash::{Hash, Hasher},
+    ops,
+    sync::Arc,
+};
 
 use bit_vec::BitVec;
 use graphics::{draw_state::DrawState, math::Matrix2d, types::Color, Graphics, ImageSize};
-use image::{DynamicImage, GenericImageView, ImageResult, Rgba, RgbaImage};
+use image::{DynamicImage, GrayImage, Rgba, RgbaImage};
 #[cfg(feature = "piston_window_texture")]
 use piston_window::{G2dTexture, G2dTextureContext};
-use png::{Decoder as PngDecoder, Limits};
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
 use texture::{CreateTexture, Format, TextureOp, TextureSettings, UpdateTexture};
 
+use crate::draw_state::{apply_stencil, premultiply, unpremultiply, ColorSpace, DrawParams};
+use crate::{checkpoint::PixelJournal, picking::write_id};
+
 /// The identity matrix: `[[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]`.
 pub const IDENTITY: Matrix2d = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
 
@@ -36,6 +164,21 @@ pub const IDENTITY: Matrix2d = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
 pub enum Error {
     /// Pixels/bytes mismatch when creating texture
     SizeMismatch(usize, usize),
+    /// Font data could not be loaded
+    #[cfg(feature = "text")]
+    Font(FontError),
+    /// A screen or window to capture could not be found
+    CaptureTarget(String),
+    /// Requested bytes do not fit in the memory budget, which has some bytes available
+    OverBudget(usize, usize),
+    /// A limit from `RenderLimits` was exceeded
+    LimitExceeded(Limit),
+    /// A row stride in bytes is shorter than a row of pixels, which has some bytes
+    InvalidStride(usize, usize),
+    /// A frame rate is not a positive, finite number of frames per second
+    InvalidFrameRate(f64),
+    /// A duration is not a non-negative, finite number of seconds, or has too many frames
+    InvalidDuration(f64),
 }
 
 impl fmt::Display for Error {
@@ -50,60 +193,113 @@ impl fmt::Display for Error {
                 len / 4,
                 area
             ),
+            #[cfg(feature = "text")]
+            Error::Font(error) => write!(f, "{}", error),
+            Error::CaptureTarget(message) => write!(f, "Unable to capture: {}", message),
+            Error::OverBudget(requested, available) => write!(
+                f,
+                "Allocation of {} bytes exceeds the memory budget. \
+                 Only {} bytes are available",
+                requested, available
+            ),
+            Error::LimitExceeded(limit) => write!(f, "Render limit exceeded: {}", limit),
+            Error::InvalidStride(stride, row) => write!(
+                f,
+                "Stride of {} bytes is shorter than a row of {} bytes",
+                stride, row
+            ),
+            Error::InvalidFrameRate(fps) => write!(f, "Invalid frame rate: {} fps", fps),
+            Error::InvalidDuration(duration) => write!(f, "Invalid duration: {} s", duration),
         }
     }
 }
 
 impl error::Error for Error {}
 
+#[cfg(feature = "text")]
+impl From<FontError> for Error {
+    fn from(error: FontError) -> Self {
+        Error::Font(error)
+    }
+}
+
 /**
 A buffer that can be rendered to with Piston's graphics library.
+
+Pixels are 8-bit RGBA by default. Buffers with other `BufferPixel` layouts and
+depths, like `RenderBuffer16`, `RenderBufferF32` and `LumaBuffer`, are drawn
+with the same rasterizers and blend modes. Features built around 8-bit pixels,
+like filters, encoding and use as a texture, are only available for the default.
+
+Two buffers are equal if their pixels are equal, regardless of rendering settings.
+
+`RenderBuffer` is `Send + Sync`. It has no interior mutability, so drawing
+always requires `&mut self`, and a buffer used as a texture is only ever read
+through a shared reference while another buffer is drawn to. This means the
+same texture can be used by many threads rendering into their own buffers at once.
 */
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct RenderBuffer {
-    inner: RgbaImage,
-    used: Vec<BitVec>,
+#[derive(Debug, Clone)]
+pub struct RenderBuffer<P: BufferPixel = Rgba<u8>> {
+    inner: PixelImage<P>,
+    used: OverdrawMask,
+    rasterizer: Arc<dyn Rasterizer>,
+    origin: Origin,
+    coverage: Option<GrayImage>,
+    /// Only allocated once stencil operations are used
+    stencil: Option<GrayImage>,
+    /// Whether `inner` holds premultiplied colors
+    premultiplied: bool,
+    linear_blending: bool,
+    legacy_blending: bool,
+    prevent_overdraw: bool,
+    /// Object IDs, row by row, if ID tracking is enabled
+    ids: Option<Vec<u32>>,
+    current_id: u32,
+    tags: BTreeMap<u32, String>,
+    min_filter: TextureFilter,
+    mag_filter: TextureFilter,
+    wrap_u: WrapMode,
+    wrap_v: WrapMode,
+    pixels_written: usize,
+    /// The region changed since the dirty rectangle was last taken
+    dirty: Option<[u32; 4]>,
+    /// The original pixels changed during a transaction
+    journal: Option<PixelJournal<P>>,
+    #[cfg(feature = "parallel")]
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+    /// Counts the pixel data and planes against the `MemoryBudget` while the buffer lives
+    _allocation: Allocation,
 }
 
 impl RenderBuffer {
     /// Create a new `RenderBuffer` with the given witdth or height.
     pub fn new(width: u32, height: u32) -> RenderBuffer {
-        RenderBuffer {
-            inner: RgbaImage::new(width, height),
-            used: vec![BitVec::from_elem(height as usize, false); width as usize],
-        }
+        RenderBuffer::blank(width, height)
     }
-    /// Creates a new `RenderBuffer` by opening it from a file.
-    pub fn open<P: AsRef<Path>>(path: P) -> Result<RenderBuffer, Box<dyn error::Error>> {
-        if path
-            .as_ref()
-            .extension()
-            .map(|ext| ext == "png")
-            .unwrap_or(false)
-        {
-            let (info, mut reader) = PngDecoder::new_with_limits(
-                File::open(&path)?,
-                Limits {
-                    bytes: std::usize::MAX,
-                },
-            )
-            .read_info()?;
-            let mut buf = vec![0; info.buffer_size()];
-            reader.next_frame(&mut buf)?;
-            Ok(
-                if let Some(image) = image::RgbaImage::from_raw(info.width, info.height, buf) {
-                    image.into()
-                } else {
-                    image::open(path)?.into()
-                },
-            )
-        } else {
-            Ok(image::open(path)?.into())
-        }
+    /// Creates a `G2dTexture` from the `RenderBuffer` for drawing to a `PistonWindow`.
+    #[cfg(feature = "piston_window_texture")]
+    pub fn to_g2d_texture(
+        &self,
+        context: &mut G2dTextureContext,
+        settings: &TextureSettings,
+    ) -> Result<G2dTexture, Box<dyn error::Error>> {
+        Ok(G2dTexture::from_image(
+            context,
+            &self.straight_pixels(),
+            settings,
+        )?)
     }
-    /// Creates a new `RenderBuffer` by decoding image data.
-    pub fn decode_from_bytes(bytes: &[u8]) -> ImageResult<RenderBuffer> {
-        image::load_from_memory(bytes).map(RenderBuffer::from)
+}
+
+impl<P: BufferPixel> RenderBuffer<P> {
+    /**
+    Create a new buffer of any pixel layout with the given width and height,
+    with every channel set to zero.
+
+    `new` only creates 8-bit RGBA buffers, so that their type can always be inferred.
+    */
+    pub fn blank(width: u32, height: u32) -> Self {
+        PixelImage::<P>::new(width, height).into()
     }
     /// Clear the buffer with a color.
     pub fn clear(&mut self, color: [f32; 4]) {
@@ -111,24 +307,256 @@ impl RenderBuffer {
     }
     /// Returns the color of the pixel at the given coordinates.
     pub fn pixel(&self, x: u32, y: u32) -> [f32; 4] {
-        color_rgba_f32(*self.inner.get_pixel(x, y))
+        let color = self.inner.get_pixel(x, y).to_color();
+        if self.premultiplied {
+            unpremultiply(&color)
+        } else {
+            color
+        }
     }
     /// Sets the color of the pixel at the given coordinates.
     pub fn set_pixel(&mut self, x: u32, y: u32, color: [f32; 4]) {
-        self.inner.put_pixel(x, y, color_f32_rgba(&color));
+        let color = if self.premultiplied {
+            premultiply(&color)
+        } else {
+            color
+        };
+        self.mark_dirty([x, y, 1, 1]);
+        self.inner.put_pixel(x, y, P::from_color(&color));
     }
-    fn reset_used(&mut self) {
-        let (width, height) = self.inner.dimensions();
-        self.used = vec![BitVec::from_elem(height as usize, false); width as usize];
+    /// Get the rasterizer used to draw triangles.
+    pub fn rasterizer(&self) -> &dyn Rasterizer {
+        &*self.rasterizer
     }
-    /// Creates a `G2dTexture` from the `RenderBuffer` for drawing to a `PistonWindow`.
-    #[cfg(feature = "piston_window_texture")]
-    pub fn to_g2d_texture(
-        &self,
-        context: &mut G2dTextureContext,
-        settings: &TextureSettings,
-    ) -> Result<G2dTexture, Box<dyn error::Error>> {
-        Ok(G2dTexture::from_image(context, &self.inner, settings)?)
+    /// Set the rasterizer used to draw triangles.
+    ///
+    /// The default is `ScanlineRasterizer`. Use `AnalyticRasterizer` for smooth edges.
+    pub fn set_rasterizer<R: Rasterizer + 'static>(&mut self, rasterizer: R) {
+        self.rasterizer = Arc::new(rasterizer);
+    }
+    /// Set the rasterizer used to draw triangles.
+    pub fn with_rasterizer<R: Rasterizer + 'static>(mut self, rasterizer: R) -> Self {
+        self.set_rasterizer(rasterizer);
+        self
+    }
+    /// Check if colors are blended in linear light.
+    pub fn linear_blending(&self) -> bool {
+        self.linear_blending
+    }
+    /**
+    Set whether colors are blended in linear light.
+
+    Pixels are always stored sRGB-encoded. Blending directly on sRGB values
+    darkens gradients and anti-aliased edges, so with linear blending enabled,
+    colors are converted to linear light before blending and back to sRGB when
+    stored. This is slower, but matches GPU pipelines with sRGB render targets.
+
+    The default is `false`.
+    */
+    pub fn set_linear_blending(&mut self, linear: bool) {
+        self.linear_blending = linear;
+    }
+    /// Set whether colors are blended in linear light.
+    pub fn with_linear_blending(mut self, linear: bool) -> Self {
+        self.set_linear_blending(linear);
+        self
+    }
+    /// Check if alpha blending uses the quadratic alpha weighting of older versions.
+    pub fn legacy_blending(&self) -> bool {
+        self.legacy_blending
+    }
+    /**
+    Set whether alpha blending uses the quadratic alpha weighting of older versions.
+
+    By default, colors are composited with Porter-Duff source-over. Older
+    versions weighted the drawn color by `1 - (1 - alpha)²` and combined alphas
+    as a vector length, which makes translucent shapes look more opaque. Enable
+    this to reproduce images rendered by those versions.
+
+    The default is `false`.
+    */
+    pub fn set_legacy_blending(&mut self, legacy: bool) {
+        self.legacy_blending = legacy;
+    }
+    /// Set whether alpha blending uses the quadratic alpha weighting of older versions.
+    pub fn with_legacy_blending(mut self, legacy: bool) -> Self {
+        self.set_legacy_blending(legacy);
+        self
+    }
+    /// Check if pixels are kept from being drawn more than once per draw call.
+    pub fn overdraw_prevention(&self) -> bool {
+        self.prevent_overdraw
+    }
+    /**
+    Set whether pixels are kept from being drawn more than once per draw call.
+
+    Piston tessellates shapes into triangles that may overlap, so by default a
+    pixel fully covered by one triangle is skipped by the rest of the same
+    call. This keeps translucent shapes from showing darker seams. Disable it
+    for self-overlapping paths whose overlaps should accumulate alpha, then
+    enable it again for the following draws. Textured draws always cover every pixel.
+
+    The default is `true`.
+    */
+    pub fn set_overdraw_prevention(&mut self, prevent: bool) {
+        self.prevent_overdraw = prevent;
+    }
+    /// Set whether pixels are kept from being drawn more than once per draw call.
+    pub fn with_overdraw_prevention(mut self, prevent: bool) -> Self {
+        self.set_overdraw_prevention(prevent);
+        self
+    }
+}
+
+// Compile-time check that buffers and the handles shared between render threads are `Send + Sync`
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<RenderBuffer>();
+    assert_send_sync::<RenderBufferF32>();
+    assert_send_sync::<Arc<dyn Rasterizer>>();
+    assert_send_sync::<BufferPool>();
+    assert_send_sync::<Texels<'_>>();
+};
+
+/// A shared, read-only handle to the pixels of a texture
+#[derive(Debug, Clone, Copy)]
+struct Texels<'a> {
+    image: &'a RgbaImage,
+    premultiplied: bool,
+    min_filter: TextureFilter,
+    mag_filter: TextureFilter,
+    wrap: [WrapMode; 2],
+}
+
+impl<'a> Texels<'a> {
+    fn new(texture: &'a RenderBuffer) -> Self {
+        Texels {
+            image: &texture.inner,
+            premultiplied: texture.premultiplied,
+            min_filter: texture.min_filter,
+            mag_filter: texture.mag_filter,
+            wrap: [texture.wrap_u, texture.wrap_v],
+        }
+    }
+    /// Pick the filter for a triangle in pixel coordinates mapped to one in texture pixel coordinates
+    fn filter_for(&self, tri: &[[f32; 2]], tex_tri: &[[f32; 2]]) -> TextureFilter {
+        let area = |t: &[[f32; 2]]| {
+            ((t[1][0] - t[0][0]) * (t[2][1] - t[0][1]) - (t[2][0] - t[0][0]) * (t[1][1] - t[0][1]))
+                .abs()
+        };
+        // The texture is minified if more than one texel maps to each pixel
+        if area(tex_tri) > area(tri) {
+            self.min_filter
+        } else {
+            self.mag_filter
+        }
+    }
+    /// Get the straight color of a texture at a point in texture pixel coordinates
+    fn sample(&self, point: [f32; 2], filter: TextureFilter) -> [f32; 4] {
+        match filter {
+            TextureFilter::Nearest => {
+                let (width, height) = self.image.dimensions();
+                // Texel `i` covers `[i, i + 1)`, so rounding would pick the next texel
+                // over the right half of each texel and wrap past the last one
+                let color = color_rgba_f32(*self.image.get_pixel(
+                    self.wrap[0].apply(point[0].floor() as i64, width),
+                    self.wrap[1].apply(point[1].floor() as i64, height),
+                ));
+                if self.premultiplied {
+                    unpremultiply(&color)
+                } else {
+                    color
+                }
+            }
+            TextureFilter::Linear => self.sample_linear(point),
+        }
+    }
+    /// Interpolate between the four texels around a point, weighting colors by
+    /// alpha so that transparent texels do not darken edges
+    fn sample_linear(&self, point: [f32; 2]) -> [f32; 4] {
+        let (width, height) = self.image.dimensions();
+        let (fx, fy) = (point[0].floor(), point[1].floor());
+        let (tx, ty) = (point[0] - fx, point[1] - fy);
+        let (fx, fy) = (fx as i64, fy as i64);
+        let (x0, x1) = (
+            self.wrap[0].apply(fx, width),
+            self.wrap[0].apply(fx + 1, width),
+        );
+        let (y0, y1) = (
+            self.wrap[1].apply(fy, height),
+            self.wrap[1].apply(fy + 1, height),
+        );
+        let (a, b, c, d) = (
+            self.texel(x0, y0),
+            self.texel(x1, y0),
+            self.texel(x0, y1),
+            self.texel(x1, y1),
+        );
+        let lerp = |i: usize| {
+            let top = a[i] + (b[i] - a[i]) * tx;
+            let bottom = c[i] + (d[i] - c[i]) * tx;
+            top + (bottom - top) * ty
+        };
+        unpremultiply(&[lerp(0), lerp(1), lerp(2), lerp(3)])
+    }
+    /// Get the premultiplied color of a texel
+    fn texel(&self, x: u32, y: u32) -> [f32; 4] {
+        let color = color_rgba_f32(*self.image.get_pixel(x, y));
+        if self.premultiplied {
+            color
+        } else {
+            premultiply(&color)
+        }
+    }
+}
+
+impl RenderBuffer {
+    /**
+    Create a buffer with the rendering settings of `source`, from pixels stored
+    the way `source` stores them.
+
+    Only settings are copied, not coverage, stencil or ID tracking.
+    */
+    pub(crate) fn with_settings_of(source: &RenderBuffer, image: RgbaImage) -> RenderBuffer {
+        let mut buffer = RenderBuffer::from(image);
+        buffer.rasterizer = Arc::clone(&source.rasterizer);
+        buffer.origin = source.origin;
+        buffer.premultiplied = source.premultiplied;
+        buffer.linear_blending = source.linear_blending;
+        buffer.legacy_blending = source.legacy_blending;
+        buffer.prevent_overdraw = source.prevent_overdraw;
+        buffer.min_filter = source.min_filter;
+        buffer.mag_filter = source.mag_filter;
+        buffer.wrap_u = source.wrap_u;
+        buffer.wrap_v = source.wrap_v;
+        #[cfg(feature = "parallel")]
+        {
+            buffer.thread_pool = source.thread_pool.clone();
+        }
+        buffer
+    }
+    /// Create a buffer from RGBA8 texture data
+    pub(crate) fn from_texture_memory(memory: &[u8], size: [u32; 2]) -> Result<Self, Error> {
+        Ok(RenderBuffer::from(
+            RgbaImage::from_raw(size[0], size[1], memory.to_vec())
+                .ok_or_else(|| Error::SizeMismatch(memory.len(), (size[0] * size[1]) as usize))?,
+        ))
+    }
+    /// Overwrite a region of the buffer with RGBA8 texture data
+    pub(crate) fn update_texture_memory(
+        &mut self,
+        memory: &[u8],
+        offset: [u32; 2],
+        size: [u32; 2],
+    ) -> Result<(), Error> {
+        let new_image = RenderBuffer::from_texture_memory(memory, size)?;
+        for i in 0..size[0] {
+            for j in 0..size[1] {
+                let color = new_image.pixel(i, j);
+                self.set_pixel(i + offset[0], j + offset[1], color);
+            }
+        }
+        Ok(())
     }
 }
 
@@ -142,13 +570,13 @@ impl CreateTexture<()> for RenderBuffer {
         _format: Format,
         memory: &[u8],
         size: S,
-        _settings: &TextureSettings,
+        settings: &TextureSettings,
     ) -> Result<Self, Error> {
-        let size = size.into();
-        Ok(RenderBuffer::from(
-            RgbaImage::from_raw(size[0], size[1], memory.to_vec())
-                .ok_or_else(|| Error::SizeMismatch(memory.len(), (size[0] * size[1]) as usize))?,
-        ))
+        let mut buffer = RenderBuffer::from_texture_memory(memory, size.into())?;
+        buffer.set_min_filter(settings.get_min().into());
+        buffer.set_mag_filter(settings.get_mag().into());
+        buffer.set_wrap(settings.get_wrap_u().into(), settings.get_wrap_v().into());
+        Ok(buffer)
     }
 }
 
@@ -165,230 +593,741 @@ impl UpdateTexture<()> for RenderBuffer {
         O: Into<[u32; 2]>,
         S: Into<[u32; 2]>,
     {
-        let offset = offset.into();
-        let size = size.into();
-        let new_image = RenderBuffer::from(
-            RgbaImage::from_raw(size[0], size[1], memory.to_vec())
-                .ok_or_else(|| Error::SizeMismatch(memory.len(), (size[0] * size[1]) as usize))?,
-        );
-        for i in 0..size[0] {
-            for j in 0..size[1] {
-                let color = new_image.pixel(i, j);
-                self.set_pixel(i + offset[0], j + offset[1], color);
-            }
-        }
-        Ok(())
+        self.update_texture_memory(memory, offset.into(), size.into())
     }
 }
 
-impl From<RgbaImage> for RenderBuffer {
-    fn from(image: RgbaImage) -> Self {
+impl<P: BufferPixel> PartialEq for RenderBuffer<P>
+where
+    PixelImage<P>: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<P: BufferPixel> Eq for RenderBuffer<P> where PixelImage<P>: Eq {}
+
+impl<P: BufferPixel> Hash for RenderBuffer<P>
+where
+    PixelImage<P>: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.hash(state);
+    }
+}
+
+impl<P: BufferPixel> From<PixelImage<P>> for RenderBuffer<P> {
+    fn from(image: PixelImage<P>) -> Self {
         let (width, height) = image.dimensions();
+        let allocation = Allocation::new(std::mem::size_of_val(image.as_raw().as_slice()));
         RenderBuffer {
             inner: image,
-            used: vec![BitVec::from_elem(height as usize, false); width as usize],
+            used: OverdrawMask::new(width, height),
+            rasterizer: Arc::new(ScanlineRasterizer),
+            origin: Origin::TopLeft,
+            coverage: None,
+            stencil: None,
+            premultiplied: false,
+            linear_blending: false,
+            legacy_blending: false,
+            prevent_overdraw: true,
+            ids: None,
+            current_id: 0,
+            tags: BTreeMap::new(),
+            min_filter: TextureFilter::Nearest,
+            mag_filter: TextureFilter::Nearest,
+            wrap_u: WrapMode::ClampToEdge,
+            wrap_v: WrapMode::ClampToEdge,
+            pixels_written: 0,
+            dirty: union_rect(None, [0, 0, width, height]),
+            journal: None,
+            #[cfg(feature = "parallel")]
+            thread_pool: None,
+            _allocation: allocation,
         }
     }
 }
 
 impl From<DynamicImage> for RenderBuffer {
     fn from(image: DynamicImage) -> Self {
-        let (width, height) = image.dimensions();
-        RenderBuffer {
-            inner: image.to_rgba8(),
-            used: vec![BitVec::from_elem(height as usize, false); width as usize],
-        }
+        image.to_rgba8().into()
     }
 }
 
-impl ops::Deref for RenderBuffer {
-    type Target = RgbaImage;
+impl<P: BufferPixel> ops::Deref for RenderBuffer<P> {
+    type Target = PixelImage<P>;
     fn deref(&self) -> &Self::Target {
         &self.inner
     }
 }
 
-impl ImageSize for RenderBuffer {
+impl<P: BufferPixel> ImageSize for RenderBuffer<P> {
     fn get_size(&self) -> (u32, u32) {
         self.inner.dimensions()
     }
 }
 
-impl Graphics for RenderBuffer {
-    type Texture = RenderBuffer;
-    fn clear_color(&mut self, color: Color) {
-        for (_, _, pixel) in self.inner.enumerate_pixels_mut() {
-            *pixel = color_f32_rgba(&color);
+/**
+The drawing operations behind the `Graphics` impls.
+
+These only take plain types, so that they can be shared by the impls for each
+supported version of Piston's graphics library.
+*/
+impl<P: BufferPixel> RenderBuffer<P> {
+    /// Get how the buffer's colors are stored and blended
+    pub(crate) fn color_space(&self) -> ColorSpace {
+        ColorSpace {
+            premultiplied: self.premultiplied,
+            linear: self.linear_blending,
+            unbounded: <P::Subpixel as Depth>::UNBOUNDED,
+            legacy: self.legacy_blending,
+        }
+    }
+    /// Borrow the parts of the buffer that triangles are drawn into
+    fn canvas(&mut self) -> Canvas<'_, P> {
+        Canvas {
+            color_space: self.color_space(),
+            pixels: &mut self.inner,
+            used: &mut self.used,
+            coverage: self.coverage.as_mut(),
+            stencil: self.stencil.as_mut(),
+            ids: self.ids.as_mut(),
+            current_id: self.current_id,
+            rasterizer: &*self.rasterizer,
+            origin: self.origin,
+            prevent_overdraw: self.prevent_overdraw,
+            pixels_written: &mut self.pixels_written,
+            dirty: &mut self.dirty,
+            journal: self.journal.as_mut(),
+            #[cfg(feature = "parallel")]
+            thread_pool: self.thread_pool.as_deref(),
+        }
+    }
+    /// Add a region to the dirty rectangle
+    ///
+    /// This must be called before the pixels in the region are changed.
+    pub(crate) fn mark_dirty(&mut self, rect: [u32; 4]) {
+        if let Some(journal) = &mut self.journal {
+            journal.save(&self.inner, rect);
         }
+        self.dirty = union_rect(self.dirty, rect);
     }
-    fn clear_stencil(&mut self, _value: u8) {}
-    fn tri_list<F>(&mut self, _draw_state: &DrawState, color: &[f32; 4], mut f: F)
+    /// Mark the whole buffer as dirty
+    pub(crate) fn mark_all_dirty(&mut self) {
+        let (width, height) = self.dimensions();
+        self.mark_dirty([0, 0, width, height]);
+    }
+    pub(crate) fn clear_pixels(&mut self, color: Color) {
+        self.canvas().clear(color);
+        self.reset_coverage();
+        self.reset_ids();
+    }
+    pub(crate) fn clear_stencil_plane(&mut self, value: u8) {
+        let allocated = self.stencil.is_some();
+        draw_state::fill_stencil(&mut self.stencil, self.inner.dimensions(), value);
+        if !allocated {
+            self.track_memory();
+        }
+    }
+    /// Allocate the stencil plane if a draw uses it
+    fn prepare_stencil(&mut self, params: DrawParams) {
+        let allocated = self.stencil.is_some();
+        draw_state::prepare_stencil(&mut self.stencil, self.inner.dimensions(), params);
+        if !allocated && self.stencil.is_some() {
+            self.track_memory();
+        }
+    }
+    pub(crate) fn draw_tris<F>(&mut self, params: DrawParams, color: &[f32; 4], f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]])),
+    {
+        self.prepare_stencil(params);
+        self.canvas().draw_tris(params, color, f);
+    }
+    pub(crate) fn draw_tris_uv<F>(
+        &mut self,
+        params: DrawParams,
+        color: &[f32; 4],
+        texture: &RenderBuffer,
+        f: F,
+    ) where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]])),
+    {
+        self.prepare_stencil(params);
+        self.canvas().draw_tris_uv(params, color, texture, f);
+    }
+    pub(crate) fn draw_tris_c<F>(&mut self, params: DrawParams, f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 4]])),
+    {
+        self.prepare_stencil(params);
+        self.canvas().draw_tris_c(params, f);
+    }
+    pub(crate) fn draw_tris_uv_c<F>(&mut self, params: DrawParams, texture: &RenderBuffer, f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]], &[[f32; 4]])),
+    {
+        self.prepare_stencil(params);
+        self.canvas().draw_tris_uv_c(params, texture, f);
+    }
+}
+
+/// The parts of a render target that triangles are drawn into
+pub(crate) struct Canvas<'a, P: BufferPixel = Rgba<u8>> {
+    pub(crate) pixels: &'a mut dyn PixelStore<P>,
+    pub(crate) used: &'a mut OverdrawMask,
+    pub(crate) coverage: Option<&'a mut GrayImage>,
+    pub(crate) stencil: Option<&'a mut GrayImage>,
+    pub(crate) color_space: ColorSpace,
+    pub(crate) ids: Option<&'a mut Vec<u32>>,
+    pub(crate) current_id: u32,
+    pub(crate) rasterizer: &'a dyn Rasterizer,
+    pub(crate) origin: Origin,
+    /// Whether pixels fully covered earlier in a draw call are skipped
+    pub(crate) prevent_overdraw: bool,
+    pub(crate) pixels_written: &'a mut usize,
+    /// The region changed since the dirty rectangle was last taken
+    pub(crate) dirty: &'a mut Option<[u32; 4]>,
+    /// The original pixels changed during a transaction
+    pub(crate) journal: Option<&'a mut PixelJournal<P>>,
+    /// The pool to draw tiles on instead of the global one
+    #[cfg(feature = "parallel")]
+    pub(crate) thread_pool: Option<&'a rayon::ThreadPool>,
+}
+
+impl<P: BufferPixel> Canvas<'_, P> {
+    /// Add a region to the dirty rectangle, before its pixels are changed
+    fn mark_dirty(&mut self, rect: [u32; 4]) {
+        if let Some(journal) = &mut self.journal {
+            journal.save(&*self.pixels, rect);
+        }
+        *self.dirty = union_rect(*self.dirty, rect);
+    }
+    fn reset_used(&mut self) {
+        let (width, height) = self.pixels.dimensions();
+        self.used.reset(width, height);
+    }
+    pub(crate) fn clear(&mut self, color: Color) {
+        let (width, height) = self.pixels.dimensions();
+        self.clear_rect(color, [0, 0, width, height]);
+    }
+    /// Set every pixel in a rectangle in top-left coordinates to a color
+    pub(crate) fn clear_rect(&mut self, color: Color, rect: [u32; 4]) {
+        let (width, height) = self.pixels.dimensions();
+        let [x, y, width, height] = intersect_bounds([0, 0, width, height], rect);
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.mark_dirty([x, y, width, height]);
+        let stride = self.pixels.stride();
+        let color = if self.color_space.premultiplied {
+            premultiply(&color)
+        } else {
+            color
+        };
+        let color = P::from_color(&color);
+        let channels = usize::from(P::CHANNEL_COUNT);
+        let (start, end) = (x as usize * channels, (x + width) as usize * channels);
+        for row in self
+            .pixels
+            .bytes_mut()
+            .chunks_mut(stride)
+            .skip(y as usize)
+            .take(height as usize)
+        {
+            for pixel in row[start..end].chunks_exact_mut(channels) {
+                pixel.copy_from_slice(color.channels());
+            }
+        }
+    }
+    pub(crate) fn draw_tris<F>(&mut self, params: DrawParams, color: &[f32; 4], mut f: F)
     where
         F: FnMut(&mut dyn FnMut(&[[f32; 2]])),
     {
         self.reset_used();
+        let origin = self.origin;
+        let skip_used = self.prevent_overdraw;
         // Render Triangles
         f(&mut |vertices| {
-            for tri in vertices.chunks(3) {
-                // Get tri bounds for efficiency
-                let mut tl = [0f32, 0f32];
-                let mut br = [0f32, 0f32];
-                for v in tri {
-                    tl[0] = tl[0].min(v[0]);
-                    tl[1] = tl[1].min(v[1]);
-                    br[0] = br[0].max(v[0]);
-                    br[1] = br[1].max(v[1]);
-                }
-                let tl = [tl[0].floor().max(0.0) as i32, tl[1].floor().max(0.0) as i32];
-                let br = [
-                    br[0].ceil().min(self.width() as f32) as i32,
-                    br[1].ceil().min(self.height() as f32) as i32,
-                ];
-                // Render
-                let inner = &self.inner;
-                let used = &self.used;
-                (tl[0]..br[0]).into_par_iter().for_each(|x| {
-                    let mut entered = false;
-                    for y in tl[1]..br[1] {
-                        if triangle_contains(tri, [x as f32, y as f32]) {
-                            entered = true;
-                            if !used[x as usize].get(y as usize).unwrap_or(true) {
-                                let under_color =
-                                    color_rgba_f32(*inner.get_pixel(x as u32, y as u32));
-                                let layered_color = layer_color(&color, &under_color);
-                                unsafe {
-                                    (inner as *const RgbaImage as *mut RgbaImage)
-                                        .as_mut()
-                                        .unwrap()
-                                        .put_pixel(
-                                            x as u32,
-                                            y as u32,
-                                            color_f32_rgba(&layered_color),
-                                        );
-                                    (used as *const Vec<BitVec> as *mut Vec<BitVec>)
-                                        .as_mut()
-                                        .unwrap()[x as usize]
-                                        .set(y as usize, true);
-                                }
-                            }
-                        } else if entered {
-                            break;
-                        }
-                    }
-                });
-            }
+            let (_, height) = self.pixels.dimensions();
+            let tris: Vec<[[f32; 2]; 3]> = vertices
+                .chunks_exact(3)
+                .map(|tri| origin.apply(tri, height))
+                .collect();
+            self.fill_tris(params, &tris, skip_used, |_, _, _| *color);
         });
     }
-    fn tri_list_uv<F>(
+    pub(crate) fn draw_tris_uv<F>(
         &mut self,
-        _draw_state: &DrawState,
+        params: DrawParams,
         color: &[f32; 4],
-        texture: &Self::Texture,
+        texture: &RenderBuffer,
         mut f: F,
     ) where
         F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]])),
     {
         self.reset_used();
+        let origin = self.origin;
+        let texels = Texels::new(texture);
         // Render Triangles
         f(&mut |vertices, tex_vertices| {
-            for (tri, tex_tri) in vertices.chunks(3).zip(tex_vertices.chunks(3)) {
-                // Get tri bounds for efficiency
-                let mut tl = [0f32, 0f32];
-                let mut br = [0f32, 0f32];
-                for v in tri {
-                    tl[0] = tl[0].min(v[0]);
-                    tl[1] = tl[1].min(v[1]);
-                    br[0] = br[0].max(v[0]);
-                    br[1] = br[1].max(v[1]);
+            let (_, height) = self.pixels.dimensions();
+            let (tris, mappings): (Vec<_>, Vec<_>) = vertices
+                .chunks_exact(3)
+                .zip(tex_vertices.chunks_exact(3))
+                .map(|(tri, tex_tri)| {
+                    let tri = origin.apply(tri, height);
+                    let scaled_tex_tri = tri_image_scale(tex_tri, texture.get_size());
+                    let filter = texels.filter_for(&tri, &scaled_tex_tri);
+                    (tri, (triangle_mapping(&tri, &scaled_tex_tri), filter))
+                })
+                .unzip();
+            self.fill_tris(params, &tris, false, |i, x, y| {
+                let (mapping, filter) = &mappings[i];
+                let mapped_point = map_point(mapping, [x as f32, y as f32]);
+                color_mul(color, &texels.sample(mapped_point, *filter))
+            });
+        });
+    }
+    pub(crate) fn draw_tris_c<F>(&mut self, params: DrawParams, mut f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 4]])),
+    {
+        self.reset_used();
+        let origin = self.origin;
+        let skip_used = self.prevent_overdraw;
+        // Render Triangles
+        f(&mut |vertices, colors| {
+            let (_, height) = self.pixels.dimensions();
+            let (tris, gradients): (Vec<_>, Vec<_>) = vertices
+                .chunks_exact(3)
+                .zip(colors.chunks_exact(3))
+                .map(|(tri, colors)| {
+                    let tri = origin.apply(tri, height);
+                    let gradient = (barycentric_mapping(&tri), [colors[0], colors[1], colors[2]]);
+                    (tri, gradient)
+                })
+                .unzip();
+            self.fill_tris(params, &tris, skip_used, |i, x, y| {
+                let (mapping, colors) = &gradients[i];
+                interpolate_color(mapping, colors, [x as f32, y as f32])
+            });
+        });
+    }
+    pub(crate) fn draw_tris_uv_c<F>(&mut self, params: DrawParams, texture: &RenderBuffer, mut f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]], &[[f32; 4]])),
+    {
+        self.reset_used();
+        let origin = self.origin;
+        let texels = Texels::new(texture);
+        // Render Triangles
+        f(&mut |vertices, tex_vertices, colors| {
+            let (_, height) = self.pixels.dimensions();
+            let (tris, mappings): (Vec<_>, Vec<_>) = vertices
+                .chunks_exact(3)
+                .zip(tex_vertices.chunks_exact(3))
+                .zip(colors.chunks_exact(3))
+                .map(|((tri, tex_tri), colors)| {
+                    let tri = origin.apply(tri, height);
+                    let scaled_tex_tri = tri_image_scale(tex_tri, texture.get_size());
+                    let filter = texels.filter_for(&tri, &scaled_tex_tri);
+                    let mapping = triangle_mapping(&tri, &scaled_tex_tri);
+                    let gradient = (barycentric_mapping(&tri), [colors[0], colors[1], colors[2]]);
+                    (tri, (mapping, filter, gradient))
+                })
+                .unzip();
+            self.fill_tris(params, &tris, false, |i, x, y| {
+                let (mapping, filter, (weights, colors)) = &mappings[i];
+                let point = [x as f32, y as f32];
+                let texel = texels.sample(map_point(mapping, point), *filter);
+                color_mul(&interpolate_color(weights, colors, point), &texel)
+            });
+        });
+    }
+    /**
+    Rasterize triangles in pixel coordinates, blending the color `shade` gives
+    each covered pixel of each triangle.
+
+    The triangles are binned into the tiles their bounds overlap, and tiles are
+    drawn in parallel. Each tile draws its triangles in order, so overlapping
+    triangles blend the same as if they were drawn one at a time.
+
+    If `skip_used` is set, pixels already fully covered since the last reset of
+    the overdraw mask are left alone.
+    */
+    fn fill_tris<S>(
+        &mut self,
+        params: DrawParams,
+        tris: &[[[f32; 2]; 3]],
+        skip_used: bool,
+        shade: S,
+    ) where
+        S: Fn(usize, u32, u32) -> [f32; 4] + Sync,
+    {
+        let (width, height) = self.pixels.dimensions();
+        let scissor = params
+            .scissor
+            .map(|scissor| self.origin.apply_rect(scissor, height));
+        let tiles_across = width.div_ceil(TILE_SIZE);
+        let mut bins = vec![Vec::new(); self.used.tiles.len()];
+        for (i, tri) in tris.iter().enumerate() {
+            if raster::orientation(tri).is_none() {
+                continue;
+            }
+            let mut bounds = tri_bounds(tri, (width, height));
+            if let Some(scissor) = scissor {
+                bounds = intersect_bounds(bounds, scissor);
+            }
+            if bounds[2] == 0 || bounds[3] == 0 {
+                continue;
+            }
+            self.mark_dirty(bounds);
+            let right = (bounds[0] + bounds[2] - 1) / TILE_SIZE;
+            let bottom = (bounds[1] + bounds[3] - 1) / TILE_SIZE;
+            for ty in bounds[1] / TILE_SIZE..=bottom {
+                for tx in bounds[0] / TILE_SIZE..=right {
+                    bins[(ty * tiles_across + tx) as usize].push((i, bounds));
                 }
-                let tl = [tl[0].floor().max(0.0) as i32, tl[1].floor().max(0.0) as i32];
-                let br = [
-                    br[0].ceil().min((self.width() - 1) as f32) as i32,
-                    br[1].ceil().min((self.height() - 1) as f32) as i32,
-                ];
-                let avg_y = ((tri[0][1] + tri[1][1] + tri[2][1]) / 3.0) as i32;
-                let vert_center = (br[1] - tl[1]) / 2;
-                let vertical_balance_top = avg_y < vert_center;
-                // Render
-                let scaled_tex_tri = tri_image_scale(tex_tri, texture.get_size());
-                let inner = &self.inner;
-                let used = &self.used;
-                (tl[0]..br[0]).into_par_iter().for_each(|x| {
-                    let mut entered = false;
-                    let range: Box<dyn Iterator<Item = i32>> = if vertical_balance_top {
-                        Box::new(tl[1]..br[1])
-                    } else {
-                        Box::new((tl[1]..br[1]).rev())
-                    };
-                    for y in range {
-                        if triangle_contains(tri, [x as f32, y as f32]) {
-                            entered = true;
-                            let mapped_point =
-                                map_to_triangle([x as f32, y as f32], tri, &scaled_tex_tri);
-                            let texel = color_rgba_f32(*texture.get_pixel(
-                                (mapped_point[0].round() as u32).min(texture.width() - 1),
-                                (mapped_point[1].round() as u32).min(texture.height() - 1),
-                            ));
-                            let over_color = color_mul(color, &texel);
-                            let under_color = color_rgba_f32(*inner.get_pixel(x as u32, y as u32));
-                            let layered_color = layer_color(&over_color, &under_color);
-                            unsafe {
-                                (inner as *const RgbaImage as *mut RgbaImage)
-                                    .as_mut()
-                                    .unwrap()
-                                    .put_pixel(x as u32, y as u32, color_f32_rgba(&layered_color));
-                                (used as *const Vec<BitVec> as *mut Vec<BitVec>)
-                                    .as_mut()
-                                    .unwrap()[x as usize]
-                                    .set(y as usize, true);
-                            }
-                        } else if entered {
-                            break;
-                        }
+            }
+        }
+        if bins.iter().all(Vec::is_empty) {
+            return;
+        }
+        let rasterizer = self.rasterizer;
+        let color_space = self.color_space;
+        let current_id = self.current_id;
+        #[cfg(feature = "parallel")]
+        let thread_pool = self.thread_pool;
+        let channels = usize::from(P::CHANNEL_COUNT);
+        let tiles = self.tiles(bins);
+        let draw_tile = |mut tile: Tile<'_, P::Subpixel>| {
+            let mut written = 0;
+            let rect = [tile.x, tile.y, tile.width, tile.height];
+            for (i, bounds) in std::mem::take(&mut tile.tris) {
+                let clip = intersect_bounds(bounds, rect);
+                rasterizer.rasterize(&tris[i], clip, &mut |x, y, coverage| {
+                    if x < clip[0]
+                        || y < clip[1]
+                        || x >= clip[0] + clip[2]
+                        || y >= clip[1] + clip[3]
+                    {
+                        return;
                     }
+                    let (row, col) = ((y - tile.y) as usize, (x - tile.x) as usize);
+                    let local = row * TILE_SIZE as usize + col;
+                    if skip_used && tile.used[local] {
+                        return;
+                    }
+                    // Stencil operations that only write to the stencil plane
+                    // leave the pixel's color alone
+                    let stencil_value = tile.stencil.get_mut(row).map(|plane| &mut plane[col]);
+                    if !apply_stencil(params.stencil, stencil_value, coverage) {
+                        return;
+                    }
+                    let pixel = &mut tile.pixels[row][col * channels..(col + 1) * channels];
+                    let under_color = P::from_slice(pixel).to_color();
+                    let layered_color =
+                        color_space.blend(params.blend, &shade(i, x, y), &under_color, coverage);
+                    pixel.copy_from_slice(P::from_color(&layered_color).channels());
+                    // Partially covered edge pixels are left open
+                    // so that adjacent triangles can fill them in
+                    if coverage >= 1.0 {
+                        tile.used.set(local, true);
+                    }
+                    if let Some(plane) = tile.coverage.get_mut(row) {
+                        plane[col] = accumulate_coverage(plane[col], coverage);
+                    }
+                    if let Some(ids) = tile.ids.get_mut(row) {
+                        write_id(&mut ids[col], current_id, coverage);
+                    }
+                    written += 1;
                 });
             }
-        });
+            written
+        };
+        // Render each tile in parallel. Tiles share no pixels or overdraw
+        // state, so the result does not depend on how they are scheduled
+        #[cfg(feature = "parallel")]
+        let written: usize = match thread_pool {
+            Some(pool) => pool.install(|| tiles.into_par_iter().map(draw_tile).sum()),
+            None => tiles.into_par_iter().map(draw_tile).sum(),
+        };
+        #[cfg(not(feature = "parallel"))]
+        let written: usize = tiles.into_iter().map(draw_tile).sum();
+        *self.pixels_written += written;
+    }
+    /// Split the canvas into disjoint tiles that can be drawn in parallel,
+    /// keeping only those with triangles binned into them
+    fn tiles(&mut self, bins: Vec<Vec<(usize, [u32; 4])>>) -> Vec<Tile<'_, P::Subpixel>> {
+        let (width, height) = self.pixels.dimensions();
+        let stride = self.pixels.stride();
+        let channels = usize::from(P::CHANNEL_COUNT);
+        let bytes = self.pixels.bytes_mut();
+        assert!(
+            stride >= width as usize * channels
+                && bytes.len() >= store::required_store_len(width, height, stride, channels),
+            "Pixel store is too small for its dimensions"
+        );
+        let tiles_across = width.div_ceil(TILE_SIZE) as usize;
+        let dirty = &mut self.used.dirty;
+        let mut tiles: Vec<Option<Tile<_>>> = bins
+            .into_iter()
+            .zip(self.used.tiles.iter_mut())
+            .enumerate()
+            .map(|(i, (tris, used))| {
+                if tris.is_empty() {
+                    return None;
+                }
+                dirty.push(i);
+                let (x, y) = ((i % tiles_across) as u32, (i / tiles_across) as u32);
+                let (x, y) = (x * TILE_SIZE, y * TILE_SIZE);
+                Some(Tile {
+                    x,
+                    y,
+                    width: (width - x).min(TILE_SIZE),
+                    height: (height - y).min(TILE_SIZE),
+                    pixels: Vec::new(),
+                    used,
+                    coverage: Vec::new(),
+                    stencil: Vec::new(),
+                    ids: Vec::new(),
+                    tris,
+                })
+            })
+            .collect();
+        // Hand the part of each row within a tile to that tile
+        let (width, tile_size) = (width as usize, TILE_SIZE as usize);
+        let coverage = chunks_or_none(
+            self.coverage.as_deref_mut().map(|plane| &mut **plane),
+            width,
+        );
+        let stencil = chunks_or_none(self.stencil.as_deref_mut().map(|plane| &mut **plane), width);
+        let ids = chunks_or_none(self.ids.as_deref_mut().map(Vec::as_mut_slice), width);
+        let rows = bytes.chunks_mut(stride).zip(coverage).zip(stencil).zip(ids);
+        for (y, (((pixels, coverage), stencil), ids)) in rows.take(height as usize).enumerate() {
+            let band = &mut tiles[y / tile_size * tiles_across..][..tiles_across];
+            if band.iter().all(Option::is_none) {
+                continue;
+            }
+            let parts = pixels[..width * channels]
+                .chunks_mut(tile_size * channels)
+                .zip(chunks_or_none(coverage, tile_size))
+                .zip(chunks_or_none(stencil, tile_size))
+                .zip(chunks_or_none(ids, tile_size));
+            for (tile, (((pixels, coverage), stencil), ids)) in band.iter_mut().zip(parts) {
+                if let Some(tile) = tile {
+                    tile.pixels.push(pixels);
+                    tile.coverage.extend(coverage);
+                    tile.stencil.extend(stencil);
+                    tile.ids.extend(ids);
+                }
+            }
+        }
+        tiles.into_iter().flatten().collect()
+    }
+}
+
+/// The width and height of the tiles that triangles are binned into for drawing
+const TILE_SIZE: u32 = 64;
+
+/**
+Which pixels have been fully covered during a draw call, stored per tile.
+
+The mask is kept between draw calls, and resetting it only clears the tiles
+that were drawn into, so many small draw calls do not each pay for the size
+of the whole image.
+*/
+#[derive(Debug, Clone, Default)]
+pub(crate) struct OverdrawMask {
+    tiles: Vec<BitVec>,
+    /// The tiles that may have had bits set since the last reset
+    dirty: Vec<usize>,
+}
+
+impl OverdrawMask {
+    /// A mask with no tiles, which is resized on the first reset
+    pub(crate) const fn empty() -> Self {
+        OverdrawMask {
+            tiles: Vec::new(),
+            dirty: Vec::new(),
+        }
+    }
+    pub(crate) fn new(width: u32, height: u32) -> Self {
+        let tiles = width.div_ceil(TILE_SIZE) * height.div_ceil(TILE_SIZE);
+        OverdrawMask {
+            tiles: vec![BitVec::from_elem((TILE_SIZE * TILE_SIZE) as usize, false); tiles as usize],
+            dirty: Vec::new(),
+        }
+    }
+    /// Clear the mask for an image of the given size, only reallocating if the number of tiles changed
+    fn reset(&mut self, width: u32, height: u32) {
+        let tiles = width.div_ceil(TILE_SIZE) * height.div_ceil(TILE_SIZE);
+        if self.tiles.len() != tiles as usize {
+            *self = OverdrawMask::new(width, height);
+            return;
+        }
+        for i in self.dirty.drain(..) {
+            self.tiles[i].clear();
+        }
     }
+    /// Get the approximate number of bytes of heap memory used by the mask
+    pub(crate) fn memory_usage(&self) -> usize {
+        let tiles: usize = self
+            .tiles
+            .iter()
+            .map(|tile| tile.capacity() / 8 + std::mem::size_of_val(tile))
+            .sum();
+        tiles + self.dirty.capacity() * std::mem::size_of::<usize>()
+    }
+}
+
+/**
+A tile of a `Canvas` and its planes, which is drawn independently of the other tiles.
+
+Each plane holds the part of every row within the tile, and is empty if
+the canvas does not have that plane. Pixels are stored as channels of type `T`.
+*/
+struct Tile<'a, T> {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    pixels: Vec<&'a mut [T]>,
+    used: &'a mut BitVec,
+    coverage: Vec<&'a mut [u8]>,
+    stencil: Vec<&'a mut [u8]>,
+    ids: Vec<&'a mut [u32]>,
+    /// The triangles that overlap the tile and their bounds
+    tris: Vec<(usize, [u32; 4])>,
+}
 
-    fn tri_list_c<F>(&mut self, _: &DrawState, _: F)
+/// Split an optional slice into chunks, yielding `None` forever if there is no slice
+fn chunks_or_none<T>(
+    slice: Option<&mut [T]>,
+    size: usize,
+) -> impl Iterator<Item = Option<&mut [T]>> {
+    slice
+        .into_iter()
+        .flat_map(move |slice| slice.chunks_mut(size))
+        .map(Some)
+        .chain(std::iter::repeat_with(|| None))
+}
+
+impl<P: BufferPixel> Graphics for RenderBuffer<P> {
+    type Texture = RenderBuffer;
+    fn clear_color(&mut self, color: Color) {
+        self.clear_pixels(color);
+    }
+    fn clear_stencil(&mut self, value: u8) {
+        self.clear_stencil_plane(value);
+    }
+    fn tri_list<F>(&mut self, draw_state: &DrawState, color: &[f32; 4], f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]])),
+    {
+        self.draw_tris(draw_state.into(), color, f);
+    }
+    fn tri_list_uv<F>(
+        &mut self,
+        draw_state: &DrawState,
+        color: &[f32; 4],
+        texture: &Self::Texture,
+        f: F,
+    ) where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]])),
+    {
+        self.draw_tris_uv(draw_state.into(), color, texture, f);
+    }
+
+    fn tri_list_c<F>(&mut self, draw_state: &DrawState, f: F)
     where
         F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 4]])),
     {
-        unimplemented!("<RenderBuffer as Graphics>::tri_list_c is currently unimplemented")
+        self.draw_tris_c(draw_state.into(), f);
     }
 
-    fn tri_list_uv_c<F>(&mut self, _: &DrawState, _: &Self::Texture, _: F)
+    fn tri_list_uv_c<F>(&mut self, draw_state: &DrawState, texture: &Self::Texture, f: F)
     where
         F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]], &[[f32; 4]])),
     {
-        unimplemented!("<RenderBuffer as Graphics>::tri_list_uv_c is currently unimplemented")
+        self.draw_tris_uv_c(draw_state.into(), texture, f);
     }
 }
 
 fn color_f32_rgba(color: &[f32; 4]) -> Rgba<u8> {
-    Rgba([
-        (color[0] * 255.0) as u8,
-        (color[1] * 255.0) as u8,
-        (color[2] * 255.0) as u8,
-        (color[3] * 255.0) as u8,
-    ])
+    depth::color_to_pixel(color)
 }
 
 fn color_rgba_f32(color: Rgba<u8>) -> [f32; 4] {
-    [
-        f32::from(color[0]) / 255.0,
-        f32::from(color[1]) / 255.0,
-        f32::from(color[2]) / 255.0,
-        f32::from(color[3]) / 255.0,
-    ]
+    depth::pixel_to_color(color)
 }
 
 fn color_mul(a: &[f32; 4], b: &[f32; 4]) -> [f32; 4] {
     [a[0] * b[0], a[1] * b[1], a[2] * b[2], a[3] * b[3]]
 }
 
+/// Premultiply the color channels of RGBA8 pixel data by alpha in place
+fn premultiply_rgba8(data: &mut [u8]) {
+    for pixel in data.chunks_exact_mut(4) {
+        let alpha = u16::from(pixel[3]);
+        for c in &mut pixel[..3] {
+            *c = ((u16::from(*c) * alpha + 127) / 255) as u8;
+        }
+    }
+}
+
+/// Undo `premultiply_rgba8` in place
+fn unpremultiply_rgba8(data: &mut [u8]) {
+    for pixel in data.chunks_exact_mut(4) {
+        let alpha = u16::from(pixel[3]);
+        if alpha == 0 {
+            continue;
+        }
+        for c in &mut pixel[..3] {
+            *c = ((u16::from(*c) * 255 + alpha / 2) / alpha).min(255) as u8;
+        }
+    }
+}
+
+/// The Rec. 709 luminance of a color, weighting its channels as they are given
+pub(crate) fn luminance(color: [f32; 4]) -> f32 {
+    0.2126 * color[0] + 0.7152 * color[1] + 0.0722 * color[2]
+}
+
+/// Convert an sRGB-encoded channel to linear light
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Convert a linear light channel to sRGB encoding, clamping to `[0, 1]`
+fn linear_to_srgb(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Composite a color over another with Porter-Duff source-over
 fn layer_color(over: &[f32; 4], under: &[f32; 4]) -> [f32; 4] {
+    let alpha = over[3] + under[3] * (1.0 - over[3]);
+    if alpha <= 0.0 {
+        return [0.0; 4];
+    }
+    let under_weight = under[3] * (1.0 - over[3]);
+    let channel = |i: usize| (over[i] * over[3] + under[i] * under_weight) / alpha;
+    [channel(0), channel(1), channel(2), alpha]
+}
+
+/// Layer a color over another with the quadratic alpha weighting of older versions
+fn layer_color_legacy(over: &[f32; 4], under: &[f32; 4]) -> [f32; 4] {
     let over_weight = 1.0 - (1.0 - over[3]).powf(2.0);
     let under_weight = 1.0 - over_weight;
     [
@@ -399,37 +1338,107 @@ fn layer_color(over: &[f32; 4], under: &[f32; 4]) -> [f32; 4] {
     ]
 }
 
-fn sign(p1: [f32; 2], p2: [f32; 2], p3: [f32; 2]) -> f32 {
-    (p1[0] - p3[0]) * (p2[1] - p3[1]) - (p2[0] - p3[0]) * (p1[1] - p3[1])
+/// Get the overlap of two `[x, y, width, height]` rectangles
+fn intersect_bounds(a: [u32; 4], b: [u32; 4]) -> [u32; 4] {
+    let x = a[0].max(b[0]);
+    let y = a[1].max(b[1]);
+    let right = (a[0] + a[2]).min(b[0].saturating_add(b[2]));
+    let bottom = (a[1] + a[3]).min(b[1].saturating_add(b[3]));
+    [x, y, right.saturating_sub(x), bottom.saturating_sub(y)]
 }
 
-fn triangle_contains(tri: &[[f32; 2]], point: [f32; 2]) -> bool {
-    let b1 = sign(point, tri[0], tri[1]) < 0.0;
-    let b2 = sign(point, tri[1], tri[2]) < 0.0;
-    let b3 = sign(point, tri[2], tri[0]) < 0.0;
-    b1 == b2 && b2 == b3
+/// Get the smallest rectangle containing a rectangle and another optional one,
+/// ignoring empty rectangles
+pub(crate) fn union_rect(a: Option<[u32; 4]>, b: [u32; 4]) -> Option<[u32; 4]> {
+    if b[2] == 0 || b[3] == 0 {
+        return a;
+    }
+    let a = match a {
+        Some(a) => a,
+        None => return Some(b),
+    };
+    let (left, top) = (a[0].min(b[0]), a[1].min(b[1]));
+    let right = (a[0] + a[2]).max(b[0] + b[2]);
+    let bottom = (a[1] + a[3]).max(b[1] + b[3]);
+    Some([left, top, right - left, bottom - top])
+}
+
+/// Get the bounds of a triangle as `[x, y, width, height]`, clamped to an image of the given size
+fn tri_bounds(tri: &[[f32; 2]], (width, height): (u32, u32)) -> [u32; 4] {
+    let mut tl = [f32::INFINITY; 2];
+    let mut br = [f32::NEG_INFINITY; 2];
+    for v in tri {
+        tl[0] = tl[0].min(v[0]);
+        tl[1] = tl[1].min(v[1]);
+        br[0] = br[0].max(v[0]);
+        br[1] = br[1].max(v[1]);
+    }
+    let tl = [
+        tl[0].floor().max(0.0).min(width as f32) as u32,
+        tl[1].floor().max(0.0).min(height as f32) as u32,
+    ];
+    let br = [
+        br[0].ceil().max(0.0).min(width as f32) as u32,
+        br[1].ceil().max(0.0).min(height as f32) as u32,
+    ];
+    [
+        tl[0],
+        tl[1],
+        br[0].saturating_sub(tl[0]),
+        br[1].saturating_sub(tl[1]),
+    ]
 }
 
+/**
+Get the affine transform that maps points in one triangle to the matching
+points in another, through their barycentric coordinates.
+
+The transform is computed once per triangle, so mapping each pixel only takes
+two multiply-adds per axis.
+*/
 #[allow(clippy::many_single_char_names)]
-fn map_to_triangle(point: [f32; 2], from_tri: &[[f32; 2]], to_tri: &[[f32; 2]]) -> [f32; 2] {
+fn triangle_mapping(from_tri: &[[f32; 2]], to_tri: &[[f32; 2]]) -> [[f32; 3]; 2] {
     let t = from_tri;
-    let p = point;
     // Computer some values that are used multiple times
     let a = t[1][1] - t[2][1];
-    let b = p[0] - t[2][0];
     let c = t[2][0] - t[1][0];
-    let d = p[1] - t[2][1];
     let e = t[0][0] - t[2][0];
     let f = t[0][1] - t[2][1];
     let g = t[2][1] - t[0][1];
     let ae_cf = a * e + c * f;
-    let bary_a = (a * b + c * d) / ae_cf;
-    let bary_b = (g * b + e * d) / ae_cf;
-    let bary_c = 1.0 - bary_a - bary_b;
-    [
-        bary_a * to_tri[0][0] + bary_b * to_tri[1][0] + bary_c * to_tri[2][0],
-        bary_a * to_tri[0][1] + bary_b * to_tri[1][1] + bary_c * to_tri[2][1],
-    ]
+    let mut mapping = [[0.0; 3]; 2];
+    for (axis, row) in mapping.iter_mut().enumerate() {
+        let (from_a, from_b) = (
+            to_tri[0][axis] - to_tri[2][axis],
+            to_tri[1][axis] - to_tri[2][axis],
+        );
+        let dx = (a * from_a + g * from_b) / ae_cf;
+        let dy = (c * from_a + e * from_b) / ae_cf;
+        *row = [dx, dy, to_tri[2][axis] - dx * t[2][0] - dy * t[2][1]];
+    }
+    mapping
+}
+
+/// Get the transform from a point to its barycentric weights for the first two vertices of a triangle
+fn barycentric_mapping(tri: &[[f32; 2]]) -> [[f32; 3]; 2] {
+    triangle_mapping(tri, &[[1.0, 0.0], [0.0, 1.0], [0.0, 0.0]])
+}
+
+/// Interpolate the colors at the vertices of a triangle to a point inside it
+///
+/// Only alpha is clamped to 1, so that floating point buffers keep bright colors.
+fn interpolate_color(mapping: &[[f32; 3]; 2], colors: &[[f32; 4]; 3], point: [f32; 2]) -> [f32; 4] {
+    let [a, b] = map_point(mapping, point);
+    let c = 1.0 - a - b;
+    let mut color =
+        [0, 1, 2, 3].map(|i| (a * colors[0][i] + b * colors[1][i] + c * colors[2][i]).max(0.0));
+    color[3] = color[3].min(1.0);
+    color
+}
+
+/// Map a point with a transform from `triangle_mapping`
+fn map_point(mapping: &[[f32; 3]; 2], point: [f32; 2]) -> [f32; 2] {
+    mapping.map(|row| row[0] * point[0] + row[1] * point[1] + row[2])
 }
 
 fn point_image_scale(point: [f32; 2], size: (u32, u32)) -> [f32; 2] {
@@ -0,0 +1,266 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use graphics::{draw_state::DrawState, types::Color, Context, Graphics, ImageSize};
+
+use crate::{Error, RenderBuffer};
+
+/// A limit from `RenderLimits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Limit {
+    /// The maximum width and height of a buffer
+    Dimensions,
+    /// The maximum number of draw calls
+    DrawCalls,
+    /// The maximum number of bytes of textures drawn with
+    TextureMemory,
+    /// The maximum number of bytes of fonts loaded by a scene
+    FontMemory,
+    /// The maximum number of characters in a piece of text
+    TextLength,
+    /// The maximum time spent drawing
+    Time,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Limit::Dimensions => "the image is too large",
+            Limit::DrawCalls => "there are too many draw calls",
+            Limit::TextureMemory => "the textures use too much memory",
+            Limit::FontMemory => "the fonts use too much memory",
+            Limit::TextLength => "some text is too long",
+            Limit::Time => "drawing took too long",
+        })
+    }
+}
+
+/**
+Limits on the resources a render may use.
+
+Services that render untrusted scenes or templates can use these to keep a
+single request from using unbounded memory or time. Scenes are checked by
+`Scene::render_with_limits`, and drawing code by `RenderBuffer::draw_with_limits`.
+
+Every limit is `None`, meaning unlimited, by default.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct RenderLimits {
+    /// The maximum width and height of a buffer
+    pub max_dimensions: Option<(u32, u32)>,
+    /// The maximum number of draw calls, including one for every glyph of text
+    pub max_draw_calls: Option<usize>,
+    /// The maximum total number of bytes of the distinct textures drawn with
+    pub max_texture_bytes: Option<usize>,
+    /// The maximum total number of bytes of the distinct fonts loaded by a scene
+    pub max_font_bytes: Option<usize>,
+    /// The maximum number of characters in a piece of text
+    pub max_text_len: Option<usize>,
+    /// The maximum time spent drawing, including loading assets for a scene
+    pub time_budget: Option<Duration>,
+}
+
+impl RenderLimits {
+    /// Check that a buffer of the given size is allowed.
+    pub fn check_dimensions(&self, width: u32, height: u32) -> Result<(), Error> {
+        match self.max_dimensions {
+            Some((max_width, max_height)) if width > max_width || height > max_height => {
+                Err(Error::LimitExceeded(Limit::Dimensions))
+            }
+            _ => Ok(()),
+        }
+    }
+    /**
+    Check that a piece of text is allowed.
+
+    Text drawn with a `LimitedTarget` cannot be seen by it, so drawing code
+    should check untrusted text with this before drawing it.
+    */
+    pub fn check_text(&self, text: &str) -> Result<(), Error> {
+        match self.max_text_len {
+            Some(max) if text.chars().count() > max => Err(Error::LimitExceeded(Limit::TextLength)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/**
+A render target that enforces `RenderLimits` while drawing into a `RenderBuffer`.
+
+Once a limit is exceeded, every later draw call is skipped, so drawing code
+that loops forever over draw calls still finishes quickly. Check `exceeded`
+to see if the drawing was cut short.
+
+Textures are counted once each, by their address, the first time they are
+drawn with.
+*/
+#[derive(Debug)]
+pub struct LimitedTarget<'a> {
+    buffer: &'a mut RenderBuffer,
+    limits: RenderLimits,
+    started: Instant,
+    draw_calls: usize,
+    textures: HashSet<usize>,
+    texture_bytes: usize,
+    exceeded: Option<Limit>,
+}
+
+impl<'a> LimitedTarget<'a> {
+    /// Start enforcing limits on drawing into a buffer. The time budget starts now.
+    pub fn new(buffer: &'a mut RenderBuffer, limits: RenderLimits) -> Self {
+        LimitedTarget {
+            buffer,
+            limits,
+            started: Instant::now(),
+            draw_calls: 0,
+            textures: HashSet::new(),
+            texture_bytes: 0,
+            exceeded: None,
+        }
+    }
+    /// Count the time budget from an earlier instant, such as when loading began
+    #[cfg(feature = "scene")]
+    pub(crate) fn started_at(mut self, started: Instant) -> Self {
+        self.started = started;
+        self
+    }
+    /// Get the buffer being drawn into.
+    pub fn buffer(&self) -> &RenderBuffer {
+        self.buffer
+    }
+    /// Get the number of draw calls made so far, including skipped ones.
+    pub fn draw_calls(&self) -> usize {
+        self.draw_calls
+    }
+    /// Get the limit that was exceeded, if any.
+    pub fn exceeded(&self) -> Option<Limit> {
+        self.exceeded
+    }
+    /// Check that no limit has been exceeded.
+    pub fn check(&self) -> Result<(), Error> {
+        match self.exceeded {
+            Some(limit) => Err(Error::LimitExceeded(limit)),
+            None => Ok(()),
+        }
+    }
+    /// Count a draw call, returning whether it is allowed
+    fn allow(&mut self, texture: Option<&RenderBuffer>) -> bool {
+        self.draw_calls += 1;
+        if self.exceeded.is_some() {
+            return false;
+        }
+        let limits = self.limits;
+        if limits
+            .max_draw_calls
+            .is_some_and(|max| self.draw_calls > max)
+        {
+            self.exceeded = Some(Limit::DrawCalls);
+        } else if limits
+            .time_budget
+            .is_some_and(|budget| self.started.elapsed() > budget)
+        {
+            self.exceeded = Some(Limit::Time);
+        } else if let Some(texture) = texture {
+            if self
+                .textures
+                .insert(texture as *const RenderBuffer as usize)
+            {
+                self.texture_bytes += texture.as_raw().len();
+            }
+            if limits
+                .max_texture_bytes
+                .is_some_and(|max| self.texture_bytes > max)
+            {
+                self.exceeded = Some(Limit::TextureMemory);
+            }
+        }
+        self.exceeded.is_none()
+    }
+}
+
+impl ImageSize for LimitedTarget<'_> {
+    fn get_size(&self) -> (u32, u32) {
+        self.buffer.dimensions()
+    }
+}
+
+impl Graphics for LimitedTarget<'_> {
+    type Texture = RenderBuffer;
+    fn clear_color(&mut self, color: Color) {
+        if self.allow(None) {
+            self.buffer.clear_pixels(color);
+        }
+    }
+    fn clear_stencil(&mut self, value: u8) {
+        if self.allow(None) {
+            self.buffer.clear_stencil_plane(value);
+        }
+    }
+    fn tri_list<F>(&mut self, draw_state: &DrawState, color: &[f32; 4], f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]])),
+    {
+        if self.allow(None) {
+            self.buffer.draw_tris(draw_state.into(), color, f);
+        }
+    }
+    fn tri_list_uv<F>(
+        &mut self,
+        draw_state: &DrawState,
+        color: &[f32; 4],
+        texture: &Self::Texture,
+        f: F,
+    ) where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]])),
+    {
+        if self.allow(Some(texture)) {
+            self.buffer
+                .draw_tris_uv(draw_state.into(), color, texture, f);
+        }
+    }
+    fn tri_list_c<F>(&mut self, draw_state: &DrawState, f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 4]])),
+    {
+        if self.allow(None) {
+            self.buffer.draw_tris_c(draw_state.into(), f);
+        }
+    }
+    fn tri_list_uv_c<F>(&mut self, draw_state: &DrawState, texture: &Self::Texture, f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]], &[[f32; 4]])),
+    {
+        if self.allow(Some(texture)) {
+            self.buffer.draw_tris_uv_c(draw_state.into(), texture, f);
+        }
+    }
+}
+
+impl RenderBuffer {
+    /**
+    Create a new buffer and draw into it, enforcing some limits.
+
+    The size is checked against the limits and the `MemoryBudget` before the
+    buffer is allocated. If any limit is exceeded while drawing, the partly
+    drawn buffer is thrown away and the error is returned.
+    */
+    pub fn draw_with_limits<F>(
+        width: u32,
+        height: u32,
+        limits: &RenderLimits,
+        draw: F,
+    ) -> Result<RenderBuffer, Error>
+    where
+        F: FnOnce(Context, &mut LimitedTarget<'_>),
+    {
+        limits.check_dimensions(width, height)?;
+        let mut buffer = RenderBuffer::try_new(width, height)?;
+        let mut target = LimitedTarget::new(&mut buffer, *limits);
+        draw(Context::new(), &mut target);
+        target.check()?;
+        Ok(buffer)
+    }
+}
@@ -0,0 +1,63 @@
+use std::{error, path::Path};
+
+use image::ImageResult;
+
+use crate::RenderBuffer;
+
+impl RenderBuffer {
+    /// Creates a new `RenderBuffer` by opening it from a file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<RenderBuffer, Box<dyn error::Error>> {
+        #[cfg(feature = "png_decode")]
+        {
+            if path
+                .as_ref()
+                .extension()
+                .map(|ext| ext == "png")
+                .unwrap_or(false)
+            {
+                if let Some(buffer) = open_png(path.as_ref())? {
+                    return Ok(buffer);
+                }
+            }
+        }
+        Ok(image::open(path)?.into())
+    }
+    /// Creates a new `RenderBuffer` by decoding image data.
+    pub fn decode_from_bytes(bytes: &[u8]) -> ImageResult<RenderBuffer> {
+        image::load_from_memory(bytes).map(RenderBuffer::from)
+    }
+}
+
+/// Check from its header that an image decodes to at most `max_bytes` of RGBA8 pixels
+#[cfg(any(feature = "scene", feature = "http_assets"))]
+pub(crate) fn check_decoded_size<R: std::io::BufRead + std::io::Seek>(
+    reader: image::io::Reader<R>,
+    max_bytes: usize,
+) -> ImageResult<()> {
+    use image::{
+        error::{LimitError, LimitErrorKind},
+        ImageError,
+    };
+
+    let (width, height) = reader.with_guessed_format()?.into_dimensions()?;
+    if width as usize * height as usize * 4 > max_bytes {
+        return Err(ImageError::Limits(LimitError::from_kind(
+            LimitErrorKind::InsufficientMemory,
+        )));
+    }
+    Ok(())
+}
+
+/// Decode an RGBA PNG file directly, or return `None` if it has some other color type
+#[cfg(feature = "png_decode")]
+fn open_png(path: &Path) -> Result<Option<RenderBuffer>, Box<dyn error::Error>> {
+    use std::fs::File;
+
+    use png::{Decoder as PngDecoder, Limits};
+
+    let (info, mut reader) =
+        PngDecoder::new_with_limits(File::open(path)?, Limits { bytes: usize::MAX }).read_info()?;
+    let mut buf = vec![0; info.buffer_size()];
+    reader.next_frame(&mut buf)?;
+    Ok(image::RgbaImage::from_raw(info.width, info.height, buf).map(RenderBuffer::from))
+}
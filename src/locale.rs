@@ -0,0 +1,123 @@
+/// The order of the parts of a date formatted by a `Locale`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DateOrder {
+    /// Year, month, day, as in ISO 8601
+    YearMonthDay,
+    /// Month, day, year, as in the United States
+    MonthDayYear,
+    /// Day, month, year, as in most of Europe
+    DayMonthYear,
+}
+
+/**
+How numbers and dates are written in labels, such as the ticks of a plot.
+
+This covers the separators and date orders that differ between common
+locales without depending on a full localization library. Other locales can
+be described by setting the fields directly.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Locale {
+    /// The character between the whole and fractional parts of a number
+    pub decimal_separator: char,
+    /// The character between groups of three digits, if any
+    pub group_separator: Option<char>,
+    /// The order of the parts of a date
+    pub date_order: DateOrder,
+    /// The character between the parts of a date
+    pub date_separator: char,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale::iso()
+    }
+}
+
+impl Locale {
+    /// Numbers without grouping and ISO 8601 dates, like `1234.5` and `2024-03-07`
+    pub fn iso() -> Self {
+        Locale {
+            decimal_separator: '.',
+            group_separator: None,
+            date_order: DateOrder::YearMonthDay,
+            date_separator: '-',
+        }
+    }
+    /// American English, like `1,234.5` and `03/07/2024`
+    pub fn en_us() -> Self {
+        Locale {
+            decimal_separator: '.',
+            group_separator: Some(','),
+            date_order: DateOrder::MonthDayYear,
+            date_separator: '/',
+        }
+    }
+    /// British English, like `1,234.5` and `07/03/2024`
+    pub fn en_gb() -> Self {
+        Locale {
+            date_order: DateOrder::DayMonthYear,
+            ..Locale::en_us()
+        }
+    }
+    /// German, like `1.234,5` and `07.03.2024`
+    pub fn de_de() -> Self {
+        Locale {
+            decimal_separator: ',',
+            group_separator: Some('.'),
+            date_order: DateOrder::DayMonthYear,
+            date_separator: '.',
+        }
+    }
+    /// French, like `1 234,5` and `07/03/2024`, grouped with a narrow no-break space
+    pub fn fr_fr() -> Self {
+        Locale {
+            decimal_separator: ',',
+            group_separator: Some('\u{202F}'),
+            date_order: DateOrder::DayMonthYear,
+            date_separator: '/',
+        }
+    }
+    /**
+    Format a number with a fixed number of decimal places.
+
+    Infinite and NaN values are written as Rust writes them.
+    */
+    pub fn format_number(&self, value: f64, decimals: usize) -> String {
+        if !value.is_finite() {
+            return value.to_string();
+        }
+        let formatted = format!("{:.*}", decimals, value.abs());
+        let (whole, fraction) = match formatted.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (formatted.as_str(), None),
+        };
+        let mut number = String::new();
+        // Rounding to zero drops the sign
+        if value < 0.0 && formatted.bytes().any(|b| b.is_ascii_digit() && b != b'0') {
+            number.push('-');
+        }
+        for (i, digit) in whole.chars().enumerate() {
+            if let Some(separator) = self.group_separator {
+                if i > 0 && (whole.len() - i) % 3 == 0 {
+                    number.push(separator);
+                }
+            }
+            number.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            number.push(self.decimal_separator);
+            number.push_str(fraction);
+        }
+        number
+    }
+    /// Format a date, with the day and month padded to two digits.
+    pub fn format_date(&self, year: i32, month: u32, day: u32) -> String {
+        let s = self.date_separator;
+        match self.date_order {
+            DateOrder::YearMonthDay => format!("{:04}{}{:02}{}{:02}", year, s, month, s, day),
+            DateOrder::MonthDayYear => format!("{:02}{}{:02}{}{:04}", month, s, day, s, year),
+            DateOrder::DayMonthYear => format!("{:02}{}{:02}{}{:04}", day, s, month, s, year),
+        }
+    }
+}
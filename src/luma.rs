@@ -0,0 +1,43 @@
+use std::path::Path;
+
+use image::{GrayImage, ImageResult, LumaA};
+
+use crate::RenderBuffer;
+
+/**
+A render target with 8-bit grayscale and alpha pixels.
+
+This uses half the memory of an RGBA `RenderBuffer`, which suits font
+atlases, heightmaps and masks. Colors drawn are stored as their luminance.
+*/
+pub type LumaBuffer = RenderBuffer<LumaA<u8>>;
+
+impl LumaBuffer {
+    /// Get the gray channel as an image.
+    pub fn luma_image(&self) -> GrayImage {
+        self.channel(0)
+    }
+    /// Get the alpha channel as an image.
+    pub fn alpha_image(&self) -> GrayImage {
+        self.channel(1)
+    }
+    /// Save the gray channel to a single-channel file, with the format deduced from the extension.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        self.luma_image().save(path)
+    }
+    /**
+    Save the alpha channel to a single-channel file, with the format deduced from the extension.
+
+    This is the coverage of everything drawn, which is what masks and font
+    atlases usually need.
+    */
+    pub fn save_alpha<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        self.alpha_image().save(path)
+    }
+    /// Copy one channel of every pixel into an image
+    fn channel(&self, channel: usize) -> GrayImage {
+        let (width, height) = self.dimensions();
+        let raw = self.pixels().map(|pixel| pixel.0[channel]).collect();
+        GrayImage::from_raw(width, height, raw).expect("Channel has one byte per pixel")
+    }
+}
@@ -0,0 +1,173 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{BufferPixel, Error, RenderBuffer};
+
+static LIMIT: AtomicUsize = AtomicUsize::new(usize::MAX);
+static USED: AtomicUsize = AtomicUsize::new(0);
+
+/**
+A crate-wide budget for the pixel memory of all live `RenderBuffer`s.
+
+Fallible constructors like `RenderBuffer::try_new` return an error instead of
+allocating past the budget, and asset caches stop caching or evict their
+contents when it is exceeded. The coverage, stencil and ID planes of a buffer
+count against the budget too, and can be allocated with the fallible
+`try_set_coverage_tracking`, `try_set_id_tracking` and `try_allocate_stencil`. This is important for long-running server
+processes that render untrusted sizes.
+
+There is no limit by default.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MemoryBudget;
+
+impl MemoryBudget {
+    /// Set the limit in bytes, or remove it with `None`.
+    pub fn set_limit(limit: Option<usize>) {
+        LIMIT.store(limit.unwrap_or(usize::MAX), Ordering::SeqCst);
+    }
+    /// Get the limit in bytes, if there is one.
+    pub fn limit() -> Option<usize> {
+        match LIMIT.load(Ordering::SeqCst) {
+            usize::MAX => None,
+            limit => Some(limit),
+        }
+    }
+    /// Get the number of bytes of pixel memory used by live buffers.
+    pub fn used() -> usize {
+        USED.load(Ordering::SeqCst)
+    }
+    /// Get the number of bytes that can still be allocated within the budget.
+    pub fn available() -> usize {
+        LIMIT.load(Ordering::SeqCst).saturating_sub(Self::used())
+    }
+    /// Check if the given number of bytes can be allocated within the budget.
+    pub fn fits(bytes: usize) -> bool {
+        bytes <= Self::available()
+    }
+    /// Check that the given number of bytes can be allocated within the budget.
+    pub fn check(bytes: usize) -> Result<(), Error> {
+        if Self::fits(bytes) {
+            Ok(())
+        } else {
+            Err(Error::OverBudget(bytes, Self::available()))
+        }
+    }
+}
+
+/// A record of some memory counted against the `MemoryBudget`, released when dropped
+#[derive(Debug)]
+pub(crate) struct Allocation(usize);
+
+impl Allocation {
+    pub(crate) fn new(bytes: usize) -> Self {
+        USED.fetch_add(bytes, Ordering::SeqCst);
+        Allocation(bytes)
+    }
+    /// Change the number of bytes counted
+    fn set(&mut self, bytes: usize) {
+        USED.fetch_add(bytes, Ordering::SeqCst);
+        USED.fetch_sub(self.0, Ordering::SeqCst);
+        self.0 = bytes;
+    }
+}
+
+impl Clone for Allocation {
+    fn clone(&self) -> Self {
+        Allocation::new(self.0)
+    }
+}
+
+impl Drop for Allocation {
+    fn drop(&mut self) {
+        USED.fetch_sub(self.0, Ordering::SeqCst);
+    }
+}
+
+/// The number of bytes of pixel data in an image of the given size
+fn pixel_bytes(width: u32, height: u32) -> usize {
+    plane_bytes((width, height), 4)
+}
+
+/// The number of bytes of a plane of the given size with some bytes per pixel
+fn plane_bytes((width, height): (u32, u32), bytes_per_pixel: usize) -> usize {
+    (width as usize)
+        .saturating_mul(height as usize)
+        .saturating_mul(bytes_per_pixel)
+}
+
+impl<P: BufferPixel> RenderBuffer<P> {
+    /// Count the pixel data and any tracking planes against the budget
+    pub(crate) fn track_memory(&mut self) {
+        let pixels = std::mem::size_of_val(self.inner.as_raw().as_slice());
+        let coverage = self.coverage.as_ref().map_or(0, |c| c.as_raw().len());
+        let stencil = self.stencil.as_ref().map_or(0, |s| s.as_raw().len());
+        let ids = self.ids.as_ref().map_or(0, |ids| ids.len() * 4);
+        self._allocation.set(pixels + coverage + stencil + ids);
+    }
+    /// Check that a plane with some bytes per pixel can be allocated, unless it already is
+    fn check_plane(&self, allocated: bool, bytes_per_pixel: usize) -> Result<(), Error> {
+        if allocated {
+            return Ok(());
+        }
+        MemoryBudget::check(plane_bytes(self.inner.dimensions(), bytes_per_pixel))
+    }
+    /// Enable or disable coverage tracking like `set_coverage_tracking`, or return
+    /// an error if the coverage plane does not fit within the `MemoryBudget`.
+    pub fn try_set_coverage_tracking(&mut self, enabled: bool) -> Result<(), Error> {
+        if enabled {
+            self.check_plane(self.coverage.is_some(), 1)?;
+        }
+        self.set_coverage_tracking(enabled);
+        Ok(())
+    }
+    /// Enable or disable object ID tracking like `set_id_tracking`, or return
+    /// an error if the ID plane does not fit within the `MemoryBudget`.
+    pub fn try_set_id_tracking(&mut self, enabled: bool) -> Result<(), Error> {
+        if enabled {
+            self.check_plane(self.ids.is_some(), 4)?;
+        }
+        self.set_id_tracking(enabled);
+        Ok(())
+    }
+    /**
+    Allocate the stencil plane, cleared to 0, if it is not already, or return
+    an error if it does not fit within the `MemoryBudget`.
+
+    Draw calls with stencil operations allocate the plane when they first need
+    it, and cannot fail, so call this first to keep the plane within the budget.
+    */
+    pub fn try_allocate_stencil(&mut self) -> Result<(), Error> {
+        let size = self.inner.dimensions();
+        let allocated = self
+            .stencil
+            .as_ref()
+            .is_some_and(|stencil| stencil.dimensions() == size);
+        self.check_plane(allocated, 1)?;
+        if !allocated {
+            self.clear_stencil_plane(0);
+        }
+        Ok(())
+    }
+}
+
+impl RenderBuffer {
+    /// Create a new `RenderBuffer` with the given width and height, or return an
+    /// error if it does not fit within the `MemoryBudget`.
+    pub fn try_new(width: u32, height: u32) -> Result<RenderBuffer, Error> {
+        MemoryBudget::check(pixel_bytes(width, height))?;
+        Ok(RenderBuffer::new(width, height))
+    }
+    /**
+    Get the approximate number of bytes of heap memory used by the buffer.
+
+    This includes the pixel data, the overdraw mask and any tracking planes.
+    */
+    pub fn memory_usage(&self) -> usize {
+        let pixels = self.inner.as_raw().capacity();
+        let used = self.used.memory_usage();
+        let coverage = self.coverage.as_ref().map_or(0, |c| c.as_raw().capacity());
+        let stencil = self.stencil.as_ref().map_or(0, |s| s.as_raw().capacity());
+        let ids = self.ids.as_ref().map_or(0, |ids| ids.capacity() * 4);
+        pixels + used + coverage + stencil + ids
+    }
+}
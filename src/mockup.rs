@@ -0,0 +1,302 @@
+use graphics::{draw_state::DrawState, types::Color, Ellipse, Polygon, Rectangle};
+#[cfg(feature = "text")]
+use graphics::{Text, Transformed};
+
+#[cfg(feature = "text")]
+use crate::BufferGlyphs;
+use crate::{RenderBuffer, IDENTITY};
+
+/// The height of the title bar of a browser frame
+const TITLE_BAR: u32 = 40;
+/// The width of the bezel around a phone screen
+const PHONE_BEZEL: u32 = 20;
+/// The height of the bezel above and below a phone screen
+const PHONE_CHIN: u32 = 48;
+/// The width of the bezel around a monitor screen
+const MONITOR_BEZEL: u32 = 16;
+/// The color of the title of a browser frame
+const TITLE_COLOR: Color = [0.2, 0.2, 0.22, 1.0];
+
+/// A device frame that a screenshot can be placed into with `frame_screenshot`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Frame {
+    /// A browser window with a title bar and window buttons
+    Browser {
+        /// The title shown in the title bar
+        title: String,
+    },
+    /// A phone with rounded corners and bezels
+    Phone,
+    /// A monitor on a stand
+    Monitor,
+}
+
+impl Frame {
+    /// Get the rectangle of the screen within a frame built around a screenshot of the given size.
+    ///
+    /// The rectangle is given as `[x, y, width, height]` in pixels.
+    pub fn screen_rect(&self, width: u32, height: u32) -> [u32; 4] {
+        match self {
+            Frame::Browser { .. } => [0, TITLE_BAR, width, height],
+            Frame::Phone => [PHONE_BEZEL, PHONE_CHIN, width, height],
+            Frame::Monitor => [MONITOR_BEZEL, MONITOR_BEZEL, width, height],
+        }
+    }
+    /// Get the size of a frame built around a screenshot of the given size.
+    pub fn size(&self, width: u32, height: u32) -> (u32, u32) {
+        match self {
+            Frame::Browser { .. } => (width, height + TITLE_BAR),
+            Frame::Phone => (width + 2 * PHONE_BEZEL, height + 2 * PHONE_CHIN),
+            Frame::Monitor => {
+                let (neck, base) = monitor_stand(width, height);
+                (
+                    width + 2 * MONITOR_BEZEL,
+                    height + 2 * MONITOR_BEZEL + neck + base,
+                )
+            }
+        }
+    }
+}
+
+/**
+Place a screenshot into a device frame.
+
+The frame is built around the screenshot at its full size, so the result is
+larger than the screenshot. Use `Frame::screen_rect` to find where the
+screenshot ends up. Areas outside of the frame are transparent.
+
+The title of a browser frame is drawn with a small built-in bitmap font that
+only has ASCII characters. Use `frame_screenshot_with_glyphs` to draw it with
+a real font.
+*/
+pub fn frame_screenshot(screenshot: &RenderBuffer, frame: Frame) -> RenderBuffer {
+    let mut buffer = draw_frame(screenshot, &frame);
+    if let Frame::Browser { title } = &frame {
+        draw_bitmap_title(&mut buffer, title);
+    }
+    buffer
+}
+
+/// Place a screenshot into a device frame without drawing any text
+fn draw_frame(screenshot: &RenderBuffer, frame: &Frame) -> RenderBuffer {
+    let screenshot = RenderBuffer::from(screenshot.straight_pixels().into_owned());
+    let (width, height) = screenshot.dimensions();
+    let (frame_width, frame_height) = frame.size(width, height);
+    let (w, h) = (f64::from(frame_width), f64::from(frame_height));
+    let mut buffer = RenderBuffer::new(frame_width, frame_height);
+    let state = DrawState::default();
+    let screen = frame.screen_rect(width, height);
+    let rounded = |color: Color, radius: f64, rect: [f64; 4], g: &mut RenderBuffer| {
+        Rectangle::new_round(color, radius).draw(rect, &state, IDENTITY, g);
+    };
+    match frame {
+        Frame::Browser { .. } => {
+            rounded([0.87, 0.87, 0.88, 1.0], 8.0, [0.0, 0.0, w, h], &mut buffer);
+            let buttons = [
+                [1.0, 0.37, 0.34, 1.0],
+                [1.0, 0.74, 0.18, 1.0],
+                [0.16, 0.79, 0.25, 1.0],
+            ];
+            for (i, color) in buttons.iter().enumerate() {
+                let x = 14.0 + 20.0 * i as f64;
+                Ellipse::new(*color).draw([x, 14.0, 12.0, 12.0], &state, IDENTITY, &mut buffer);
+            }
+            let bar = title_bar(frame_width);
+            if bar[2] > 0.0 {
+                rounded([1.0; 4], 6.0, bar, &mut buffer);
+            }
+            buffer.place(&screenshot, screen);
+        }
+        Frame::Phone => {
+            rounded([0.1, 0.1, 0.11, 1.0], 36.0, [0.0, 0.0, w, h], &mut buffer);
+            let center = w / 2.0;
+            rounded(
+                [0.25, 0.25, 0.27, 1.0],
+                3.0,
+                [center - 30.0, 21.0, 60.0, 6.0],
+                &mut buffer,
+            );
+            let indicator = f64::from(width) / 3.0;
+            rounded(
+                [0.6, 0.6, 0.62, 1.0],
+                2.5,
+                [center - indicator / 2.0, h - 27.0, indicator, 5.0],
+                &mut buffer,
+            );
+            buffer.place(&rounded_corners(&screenshot, 20.0), screen);
+        }
+        Frame::Monitor => {
+            let (neck, base) = monitor_stand(width, height);
+            let (neck, base) = (f64::from(neck), f64::from(base));
+            let screen_bottom = f64::from(height + 2 * MONITOR_BEZEL);
+            let center = w / 2.0;
+            let stand = [0.75, 0.75, 0.77, 1.0];
+            let (top, bottom) = (w / 24.0, w / 14.0);
+            Polygon::new(stand).draw(
+                &[
+                    [center - top, screen_bottom],
+                    [center + top, screen_bottom],
+                    [center + bottom, screen_bottom + neck],
+                    [center - bottom, screen_bottom + neck],
+                ],
+                &state,
+                IDENTITY,
+                &mut buffer,
+            );
+            rounded(
+                stand,
+                base / 2.0,
+                [center - w / 6.0, screen_bottom + neck, w / 3.0, base],
+                &mut buffer,
+            );
+            rounded(
+                [0.12, 0.12, 0.13, 1.0],
+                10.0,
+                [0.0, 0.0, w, screen_bottom],
+                &mut buffer,
+            );
+            buffer.place(&screenshot, screen);
+        }
+    }
+    buffer
+}
+
+/**
+Place a screenshot into a device frame, drawing any text with a glyph cache.
+
+This is like `frame_screenshot`, but the title of a browser frame is drawn
+in the title bar. Titles that do not fit are cut off.
+*/
+#[cfg(feature = "text")]
+pub fn frame_screenshot_with_glyphs(
+    screenshot: &RenderBuffer,
+    frame: Frame,
+    glyphs: &mut BufferGlyphs,
+) -> RenderBuffer {
+    let mut buffer = draw_frame(screenshot, &frame);
+    if let Frame::Browser { title } = &frame {
+        let [x, y, width, height] = title_bar(buffer.width());
+        if width > 24.0 {
+            let clip = [x as u32 + 12, y as u32, width as u32 - 24, height as u32];
+            // The title is only decoration, so glyphs that fail to load are skipped
+            let _ = Text::new_color(TITLE_COLOR, 13).draw(
+                title,
+                glyphs,
+                &DrawState::default().scissor(clip),
+                IDENTITY.trans(x + 12.0, y + 17.0),
+                &mut buffer,
+            );
+        }
+    }
+    buffer
+}
+
+/// The rectangle of the title bar in a browser frame of the given width
+fn title_bar(width: u32) -> [f64; 4] {
+    [80.0, 8.0, (f64::from(width) - 92.0).max(0.0), 24.0]
+}
+
+/// Draw the title of a browser frame with `FONT`, cutting off characters that do not fit
+fn draw_bitmap_title(buffer: &mut RenderBuffer, title: &str) {
+    let [x, y, width, height] = title_bar(buffer.width());
+    let (left, right) = (x as u32 + 12, (x + width) as u32 - 12);
+    let top = y as u32 + (height as u32 - FONT_HEIGHT) / 2;
+    for (i, c) in title.chars().enumerate() {
+        let glyph_x = left + i as u32 * FONT_ADVANCE;
+        if glyph_x + FONT_ADVANCE > right {
+            break;
+        }
+        let index = match c {
+            ' '..='~' => c as usize - ' ' as usize,
+            _ => '?' as usize - ' ' as usize,
+        };
+        for (dx, column) in FONT[index].iter().enumerate() {
+            for dy in 0..FONT_HEIGHT {
+                if (column >> dy) & 1 == 1 {
+                    buffer.set_pixel(glyph_x + dx as u32, top + dy, TITLE_COLOR);
+                }
+            }
+        }
+    }
+}
+
+/// The height of a character of `FONT` in pixels
+const FONT_HEIGHT: u32 = 7;
+/// The distance between the starts of characters of `FONT` in pixels
+const FONT_ADVANCE: u32 = 6;
+
+/// A 5x7 bitmap font for the printable ASCII characters, starting at `' '`.
+/// Each byte is a column, with the top row in the lowest bit.
+#[rustfmt::skip]
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], [0x00, 0x00, 0x5F, 0x00, 0x00],
+    [0x00, 0x07, 0x00, 0x07, 0x00], [0x14, 0x7F, 0x14, 0x7F, 0x14],
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], [0x23, 0x13, 0x08, 0x64, 0x62],
+    [0x36, 0x49, 0x56, 0x20, 0x50], [0x00, 0x05, 0x03, 0x00, 0x00],
+    [0x00, 0x1C, 0x22, 0x41, 0x00], [0x00, 0x41, 0x22, 0x1C, 0x00],
+    [0x14, 0x08, 0x3E, 0x08, 0x14], [0x08, 0x08, 0x3E, 0x08, 0x08],
+    [0x00, 0x50, 0x30, 0x00, 0x00], [0x08, 0x08, 0x08, 0x08, 0x08],
+    [0x00, 0x60, 0x60, 0x00, 0x00], [0x20, 0x10, 0x08, 0x04, 0x02],
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], [0x00, 0x42, 0x7F, 0x40, 0x00],
+    [0x42, 0x61, 0x51, 0x49, 0x46], [0x21, 0x41, 0x45, 0x4B, 0x31],
+    [0x18, 0x14, 0x12, 0x7F, 0x10], [0x27, 0x45, 0x45, 0x45, 0x39],
+    [0x3C, 0x4A, 0x49, 0x49, 0x30], [0x01, 0x71, 0x09, 0x05, 0x03],
+    [0x36, 0x49, 0x49, 0x49, 0x36], [0x06, 0x49, 0x49, 0x29, 0x1E],
+    [0x00, 0x36, 0x36, 0x00, 0x00], [0x00, 0x56, 0x36, 0x00, 0x00],
+    [0x08, 0x14, 0x22, 0x41, 0x00], [0x14, 0x14, 0x14, 0x14, 0x14],
+    [0x00, 0x41, 0x22, 0x14, 0x08], [0x02, 0x01, 0x51, 0x09, 0x06],
+    [0x32, 0x49, 0x79, 0x41, 0x3E], [0x7E, 0x11, 0x11, 0x11, 0x7E],
+    [0x7F, 0x49, 0x49, 0x49, 0x36], [0x3E, 0x41, 0x41, 0x41, 0x22],
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], [0x7F, 0x49, 0x49, 0x49, 0x41],
+    [0x7F, 0x09, 0x09, 0x09, 0x01], [0x3E, 0x41, 0x49, 0x49, 0x7A],
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], [0x00, 0x41, 0x7F, 0x41, 0x00],
+    [0x20, 0x40, 0x41, 0x3F, 0x01], [0x7F, 0x08, 0x14, 0x22, 0x41],
+    [0x7F, 0x40, 0x40, 0x40, 0x40], [0x7F, 0x02, 0x0C, 0x02, 0x7F],
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], [0x3E, 0x41, 0x41, 0x41, 0x3E],
+    [0x7F, 0x09, 0x09, 0x09, 0x06], [0x3E, 0x41, 0x51, 0x21, 0x5E],
+    [0x7F, 0x09, 0x19, 0x29, 0x46], [0x46, 0x49, 0x49, 0x49, 0x31],
+    [0x01, 0x01, 0x7F, 0x01, 0x01], [0x3F, 0x40, 0x40, 0x40, 0x3F],
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], [0x3F, 0x40, 0x38, 0x40, 0x3F],
+    [0x63, 0x14, 0x08, 0x14, 0x63], [0x07, 0x08, 0x70, 0x08, 0x07],
+    [0x61, 0x51, 0x49, 0x45, 0x43], [0x00, 0x7F, 0x41, 0x41, 0x00],
+    [0x02, 0x04, 0x08, 0x10, 0x20], [0x00, 0x41, 0x41, 0x7F, 0x00],
+    [0x04, 0x02, 0x01, 0x02, 0x04], [0x40, 0x40, 0x40, 0x40, 0x40],
+    [0x00, 0x01, 0x02, 0x04, 0x00], [0x20, 0x54, 0x54, 0x54, 0x78],
+    [0x7F, 0x48, 0x44, 0x44, 0x38], [0x38, 0x44, 0x44, 0x44, 0x20],
+    [0x38, 0x44, 0x44, 0x48, 0x7F], [0x38, 0x54, 0x54, 0x54, 0x18],
+    [0x08, 0x7E, 0x09, 0x01, 0x02], [0x0C, 0x52, 0x52, 0x52, 0x3E],
+    [0x7F, 0x08, 0x04, 0x04, 0x78], [0x00, 0x44, 0x7D, 0x40, 0x00],
+    [0x20, 0x40, 0x44, 0x3D, 0x00], [0x7F, 0x10, 0x28, 0x44, 0x00],
+    [0x00, 0x41, 0x7F, 0x40, 0x00], [0x7C, 0x04, 0x18, 0x04, 0x78],
+    [0x7C, 0x08, 0x04, 0x04, 0x78], [0x38, 0x44, 0x44, 0x44, 0x38],
+    [0x7C, 0x14, 0x14, 0x14, 0x08], [0x08, 0x14, 0x14, 0x18, 0x7C],
+    [0x7C, 0x08, 0x04, 0x04, 0x08], [0x48, 0x54, 0x54, 0x54, 0x20],
+    [0x04, 0x3F, 0x44, 0x40, 0x20], [0x3C, 0x40, 0x40, 0x20, 0x7C],
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], [0x3C, 0x40, 0x30, 0x40, 0x3C],
+    [0x44, 0x28, 0x10, 0x28, 0x44], [0x0C, 0x50, 0x50, 0x50, 0x3C],
+    [0x44, 0x64, 0x54, 0x4C, 0x44], [0x00, 0x08, 0x36, 0x41, 0x00],
+    [0x00, 0x00, 0x7F, 0x00, 0x00], [0x00, 0x41, 0x36, 0x08, 0x00],
+    [0x10, 0x08, 0x08, 0x10, 0x08],
+];
+
+/// The heights of the neck and base of a monitor stand
+fn monitor_stand(width: u32, height: u32) -> (u32, u32) {
+    ((height / 8).clamp(16, 120), (width / 48).clamp(6, 16))
+}
+
+/// Make the corners of a straight alpha buffer transparent outside of a rounded rectangle
+fn rounded_corners(buffer: &RenderBuffer, radius: f64) -> RenderBuffer {
+    let (width, height) = buffer.dimensions();
+    let mut mask = RenderBuffer::new(width, height);
+    Rectangle::new_round([1.0; 4], radius).draw(
+        [0.0, 0.0, f64::from(width), f64::from(height)],
+        &DrawState::default(),
+        IDENTITY,
+        &mut mask,
+    );
+    let mut result = buffer.clone();
+    for (pixel, mask) in result.inner.pixels_mut().zip(mask.inner.pixels()) {
+        pixel[3] = (u16::from(pixel[3]) * u16::from(mask[3]) / 255) as u8;
+    }
+    result
+}
@@ -0,0 +1,84 @@
+use std::path::Path;
+
+use image::{imageops, ImageResult};
+
+use crate::RenderBuffer;
+
+/**
+The corner of a `RenderBuffer` that drawing coordinates are relative to.
+
+Pixel data is always stored with the first row at the top. With
+`Origin::BottomLeft`, vertices are flipped vertically when drawn and the
+image is flipped when saved, matching OpenGL's texture conventions.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Origin {
+    /// `y` increases downward from the top-left corner
+    #[default]
+    TopLeft,
+    /// `y` increases upward from the bottom-left corner
+    BottomLeft,
+}
+
+impl Origin {
+    /// Map a triangle in this convention to top-left coordinates in an image of the given height
+    pub(crate) fn apply(self, tri: &[[f32; 2]], height: u32) -> [[f32; 2]; 3] {
+        [
+            self.apply_point(tri[0], height),
+            self.apply_point(tri[1], height),
+            self.apply_point(tri[2], height),
+        ]
+    }
+    /// Map a point in this convention to top-left coordinates in an image of the given height
+    pub(crate) fn apply_point(self, [x, y]: [f32; 2], height: u32) -> [f32; 2] {
+        match self {
+            Origin::TopLeft => [x, y],
+            Origin::BottomLeft => [x, height as f32 - y],
+        }
+    }
+    /// Map an `[x, y, width, height]` rectangle in this convention to top-left
+    /// coordinates in an image of the given height
+    pub(crate) fn apply_rect(self, [x, y, width, rect_height]: [u32; 4], height: u32) -> [u32; 4] {
+        match self {
+            Origin::TopLeft => [x, y, width, rect_height],
+            Origin::BottomLeft => [
+                x,
+                height.saturating_sub(y.saturating_add(rect_height)),
+                width,
+                rect_height.min(height.saturating_sub(y)),
+            ],
+        }
+    }
+}
+
+impl RenderBuffer {
+    /// Get the origin convention used for drawing and saving.
+    pub fn origin(&self) -> Origin {
+        self.origin
+    }
+    /// Set the origin convention used for drawing and saving.
+    ///
+    /// The default is `Origin::TopLeft`.
+    pub fn set_origin(&mut self, origin: Origin) {
+        self.origin = origin;
+    }
+    /// Set the origin convention used for drawing and saving.
+    pub fn with_origin(mut self, origin: Origin) -> Self {
+        self.set_origin(origin);
+        self
+    }
+    /**
+    Save the buffer to a file, with the format deduced from the extension.
+
+    If the origin is `Origin::BottomLeft`, the saved image is flipped vertically
+    so that its first row is the bottom row of the buffer. Premultiplied pixels
+    are converted back to straight alpha.
+    */
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        let image = self.straight_pixels();
+        match self.origin {
+            Origin::TopLeft => image.save(path),
+            Origin::BottomLeft => imageops::flip_vertical(&*image).save(path),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{linear_to_srgb, srgb_to_linear, RenderBuffer};
+
+/// The most pixels sampled when finding dominant colors
+const MAX_SAMPLES: usize = 16384;
+/// The most rounds of k-means refinement
+const MAX_ITERATIONS: usize = 24;
+/// The D65 white point in XYZ
+const WHITE: [f32; 3] = [0.950_47, 1.0, 1.088_83];
+
+impl RenderBuffer {
+    /**
+    Get the average color of a region.
+
+    The region is given as `[x, y, width, height]` and is clamped to the buffer.
+    Colors are averaged in linear light and weighted by alpha, so transparent
+    pixels do not pull the average toward black. The alpha of the result is the
+    average alpha. An empty or fully transparent region gives transparent black.
+    */
+    pub fn average_color(&self, region: [u32; 4]) -> [f32; 4] {
+        let x_end = region[0].saturating_add(region[2]).min(self.width());
+        let y_end = region[1].saturating_add(region[3]).min(self.height());
+        let mut sum = [0.0f64; 4];
+        let mut count = 0usize;
+        for y in region[1]..y_end {
+            for x in region[0]..x_end {
+                let color = self.pixel(x, y);
+                let alpha = f64::from(color[3]);
+                for (total, &channel) in sum.iter_mut().zip(&color[..3]) {
+                    *total += f64::from(srgb_to_linear(channel)) * alpha;
+                }
+                sum[3] += alpha;
+                count += 1;
+            }
+        }
+        if count == 0 || sum[3] <= 0.0 {
+            return [0.0; 4];
+        }
+        let channel = |total: f64| linear_to_srgb((total / sum[3]) as f32);
+        [
+            channel(sum[0]),
+            channel(sum[1]),
+            channel(sum[2]),
+            (sum[3] / count as f64) as f32,
+        ]
+    }
+    /**
+    Find up to `k` colors that best represent the buffer, most common first.
+
+    Colors are clustered with k-means in the CIE Lab color space, so that the
+    clusters match how different colors look. Mostly transparent pixels are
+    ignored, and large buffers are sampled on a grid. The result is
+    deterministic, and is shorter than `k` if the buffer has fewer distinct
+    colors. Every returned color is opaque.
+    */
+    pub fn dominant_colors(&self, k: usize) -> Vec<[f32; 4]> {
+        let (width, height) = self.dimensions();
+        let step = ((width as usize * height as usize / MAX_SAMPLES) as f64)
+            .sqrt()
+            .ceil()
+            .max(1.0) as usize;
+        let samples: Vec<[f32; 3]> = (0..height as usize)
+            .step_by(step)
+            .flat_map(|y| (0..width as usize).step_by(step).map(move |x| (x, y)))
+            .map(|(x, y)| self.pixel(x as u32, y as u32))
+            .filter(|color| color[3] >= 0.5)
+            .map(|color| srgb_to_lab([color[0], color[1], color[2]]))
+            .collect();
+        if samples.is_empty() || k == 0 {
+            return Vec::new();
+        }
+        // Start from the first sample, then repeatedly add the farthest one
+        let mut centers = vec![samples[0]];
+        while centers.len() < k {
+            let (distance, farthest) = samples
+                .iter()
+                .map(|sample| nearest(&centers, sample).1)
+                .zip(&samples)
+                .fold((0.0, None), |best, (distance, sample)| {
+                    if distance > best.0 {
+                        (distance, Some(*sample))
+                    } else {
+                        best
+                    }
+                });
+            match farthest {
+                Some(sample) if distance > 0.0 => centers.push(sample),
+                _ => break,
+            }
+        }
+        let mut counts = vec![0usize; centers.len()];
+        for _ in 0..MAX_ITERATIONS {
+            let mut sums = vec![[0.0f64; 3]; centers.len()];
+            counts.fill(0);
+            for sample in &samples {
+                let (i, _) = nearest(&centers, sample);
+                for (sum, &value) in sums[i].iter_mut().zip(sample) {
+                    *sum += f64::from(value);
+                }
+                counts[i] += 1;
+            }
+            let mut moved = false;
+            for ((center, sum), &count) in centers.iter_mut().zip(&sums).zip(&counts) {
+                if count == 0 {
+                    continue;
+                }
+                let mean = sum.map(|total| (total / count as f64) as f32);
+                moved |= squared_distance(center, &mean) > 1e-4;
+                *center = mean;
+            }
+            if !moved {
+                break;
+            }
+        }
+        let mut clusters: Vec<([f32; 3], usize)> = centers
+            .into_iter()
+            .zip(counts)
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        clusters.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        clusters
+            .into_iter()
+            .map(|(center, _)| {
+                let [r, g, b] = lab_to_srgb(center);
+                [r, g, b, 1.0]
+            })
+            .collect()
+    }
+}
+
+/// Get the index of and squared distance to the center nearest a color
+fn nearest(centers: &[[f32; 3]], color: &[f32; 3]) -> (usize, f32) {
+    centers
+        .iter()
+        .map(|center| squared_distance(center, color))
+        .enumerate()
+        .fold((0, f32::INFINITY), |best, (i, distance)| {
+            if distance < best.1 {
+                (i, distance)
+            } else {
+                best
+            }
+        })
+}
+
+fn squared_distance(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+/// Convert an sRGB color to CIE Lab
+fn srgb_to_lab(color: [f32; 3]) -> [f32; 3] {
+    let [r, g, b] = color.map(srgb_to_linear);
+    let xyz = [
+        0.412_456 * r + 0.357_576 * g + 0.180_438 * b,
+        0.212_673 * r + 0.715_152 * g + 0.072_175 * b,
+        0.019_334 * r + 0.119_192 * g + 0.950_304 * b,
+    ];
+    let f = |t: f32| {
+        if t > 0.008_856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    };
+    let [fx, fy, fz] = [
+        f(xyz[0] / WHITE[0]),
+        f(xyz[1] / WHITE[1]),
+        f(xyz[2] / WHITE[2]),
+    ];
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// Convert a CIE Lab color to sRGB, clamping colors outside of the sRGB gamut
+fn lab_to_srgb(lab: [f32; 3]) -> [f32; 3] {
+    let fy = (lab[0] + 16.0) / 116.0;
+    let (fx, fz) = (fy + lab[1] / 500.0, fy - lab[2] / 200.0);
+    let f_inv = |t: f32| {
+        if t > 0.206_893 {
+            t * t * t
+        } else {
+            (t - 16.0 / 116.0) / 7.787
+        }
+    };
+    let [x, y, z] = [
+        f_inv(fx) * WHITE[0],
+        f_inv(fy) * WHITE[1],
+        f_inv(fz) * WHITE[2],
+    ];
+    [
+        3.240_45 * x - 1.537_14 * y - 0.498_531 * z,
+        -0.969_266 * x + 1.876_01 * y + 0.041_556 * z,
+        0.055_643_4 * x - 0.204_026 * y + 1.057_23 * z,
+    ]
+    .map(linear_to_srgb)
+}
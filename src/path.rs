@@ -0,0 +1,249 @@
+use graphics::{
+    draw_state::Blend,
+    math::{self, Matrix2d, Vec2d},
+};
+use image::Luma;
+
+use crate::{accumulate_coverage, color_f32_rgba, color_rgba_f32, tri_bounds, RenderBuffer};
+
+/// The number of line segments each curve is flattened into
+const CURVE_SEGMENTS: usize = 16;
+
+/// The rule that decides which parts of a self-intersecting path are inside it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FillRule {
+    /// A point is inside if the path winds around it a nonzero number of times
+    #[default]
+    NonZero,
+    /// A point is inside if the path winds around it an odd number of times
+    EvenOdd,
+}
+
+impl FillRule {
+    /// Check whether a winding count is inside the path
+    fn is_inside(self, winding: u8) -> bool {
+        match self {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding & 1 == 1,
+        }
+    }
+}
+
+/**
+A shape made of closed subpaths of straight lines and curves.
+
+Curves are flattened into line segments as they are added. Every subpath is
+implicitly closed, and subpaths may overlap and intersect themselves.
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Path {
+    subpaths: Vec<Vec<Vec2d>>,
+}
+
+impl Path {
+    /// Create a new empty `Path`.
+    pub fn new() -> Self {
+        Path::default()
+    }
+    /// Create a new `Path` from a single polygon.
+    pub fn polygon(points: &[Vec2d]) -> Self {
+        Path {
+            subpaths: vec![points.to_vec()],
+        }
+    }
+    /// Start a new subpath at a point.
+    pub fn move_to(&mut self, point: Vec2d) -> &mut Self {
+        self.subpaths.push(vec![point]);
+        self
+    }
+    /// Add a straight line from the current point to a point.
+    pub fn line_to(&mut self, point: Vec2d) -> &mut Self {
+        match self.subpaths.last_mut() {
+            Some(subpath) => subpath.push(point),
+            None => self.subpaths.push(vec![point]),
+        }
+        self
+    }
+    /// Add a quadratic Bézier curve from the current point to a point.
+    pub fn quad_to(&mut self, control: Vec2d, point: Vec2d) -> &mut Self {
+        let start = self.current();
+        for i in 1..=CURVE_SEGMENTS {
+            let t = i as f64 / CURVE_SEGMENTS as f64;
+            let u = 1.0 - t;
+            self.line_to([
+                u * u * start[0] + 2.0 * u * t * control[0] + t * t * point[0],
+                u * u * start[1] + 2.0 * u * t * control[1] + t * t * point[1],
+            ]);
+        }
+        self
+    }
+    /// Add a cubic Bézier curve from the current point to a point.
+    pub fn cubic_to(&mut self, control1: Vec2d, control2: Vec2d, point: Vec2d) -> &mut Self {
+        let start = self.current();
+        for i in 1..=CURVE_SEGMENTS {
+            let t = i as f64 / CURVE_SEGMENTS as f64;
+            let u = 1.0 - t;
+            let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+            self.line_to([
+                a * start[0] + b * control1[0] + c * control2[0] + d * point[0],
+                a * start[1] + b * control1[1] + c * control2[1] + d * point[1],
+            ]);
+        }
+        self
+    }
+    /// Get the points of each subpath.
+    pub fn subpaths(&self) -> impl Iterator<Item = &[Vec2d]> {
+        self.subpaths.iter().map(Vec::as_slice)
+    }
+    /// Check if the path has no subpaths that enclose any area.
+    pub fn is_empty(&self) -> bool {
+        self.subpaths.iter().all(|subpath| subpath.len() < 3)
+    }
+    /**
+    Get the winding number of the path around a point.
+
+    This is how many times the subpaths wind around the point. Windings in
+    opposite directions cancel out, so the sign depends on the direction.
+    */
+    pub fn winding_number(&self, point: Vec2d) -> i32 {
+        let [px, py] = point;
+        let mut winding = 0;
+        for subpath in self.subpaths.iter().filter(|subpath| subpath.len() >= 3) {
+            let next = subpath.iter().skip(1).chain(subpath.first());
+            for (a, b) in subpath.iter().zip(next) {
+                let side = (b[0] - a[0]) * (py - a[1]) - (px - a[0]) * (b[1] - a[1]);
+                if a[1] <= py {
+                    if b[1] > py && side > 0.0 {
+                        winding += 1;
+                    }
+                } else if b[1] <= py && side < 0.0 {
+                    winding -= 1;
+                }
+            }
+        }
+        winding
+    }
+    /// Check if a point is inside the path under the nonzero fill rule.
+    pub fn contains(&self, point: Vec2d) -> bool {
+        self.contains_with_rule(point, FillRule::NonZero)
+    }
+    /// Check if a point is inside the path under a fill rule.
+    pub fn contains_with_rule(&self, point: Vec2d, fill_rule: FillRule) -> bool {
+        let winding = self.winding_number(point);
+        match fill_rule {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding % 2 != 0,
+        }
+    }
+    /// The last point of the path, or the origin if it is empty
+    fn current(&self) -> Vec2d {
+        self.subpaths
+            .last()
+            .and_then(|subpath| subpath.last())
+            .copied()
+            .unwrap_or([0.0; 2])
+    }
+}
+
+impl RenderBuffer {
+    /**
+    Fill a path with a color using the stencil-then-cover approach.
+
+    Each subpath is drawn into the stencil plane as a fan of triangles that
+    count how many times the path winds around each pixel, so self-intersecting
+    and overlapping subpaths fill correctly under either fill rule without
+    triangulating the path. The covered pixels are then blended with alpha.
+
+    `transform` maps path coordinates to pixels. The stencil plane is left
+    cleared to 0 within the bounds of the path.
+    */
+    pub fn fill_path(
+        &mut self,
+        path: &Path,
+        fill_rule: FillRule,
+        color: [f32; 4],
+        transform: Matrix2d,
+    ) {
+        let (width, height) = self.dimensions();
+        let origin = self.origin;
+        let subpaths: Vec<Vec<[f32; 2]>> = path
+            .subpaths()
+            .filter(|subpath| subpath.len() >= 3)
+            .map(|subpath| {
+                subpath
+                    .iter()
+                    .map(|&point| {
+                        let [x, y] = math::transform_pos(transform, point);
+                        origin.apply_point([x as f32, y as f32], height)
+                    })
+                    .collect()
+            })
+            .collect();
+        let all_points: Vec<[f32; 2]> = subpaths.iter().flatten().copied().collect();
+        if all_points.is_empty() {
+            return;
+        }
+        let bounds = tri_bounds(&all_points, (width, height));
+        self.mark_dirty(bounds);
+        if self.stencil.is_none() {
+            self.clear_stencil_plane(0);
+        }
+        let rasterizer = self.rasterizer.clone();
+        let color_space = self.color_space();
+        let stencil = self.stencil.as_mut().unwrap();
+        for y in bounds[1]..bounds[1] + bounds[3] {
+            for x in bounds[0]..bounds[0] + bounds[2] {
+                stencil.put_pixel(x, y, Luma([0]));
+            }
+        }
+        // Stencil: count the winding of every fan triangle
+        for points in &subpaths {
+            let pivot = points[0];
+            for pair in points[1..].windows(2) {
+                let tri = [pivot, pair[0], pair[1]];
+                let area = (tri[1][0] - tri[0][0]) * (tri[2][1] - tri[0][1])
+                    - (tri[2][0] - tri[0][0]) * (tri[1][1] - tri[0][1]);
+                if area == 0.0 || !area.is_finite() {
+                    continue;
+                }
+                let clip = tri_bounds(&tri, (width, height));
+                rasterizer.rasterize(&tri, clip, &mut |x, y, coverage| {
+                    if coverage < 0.5 {
+                        return;
+                    }
+                    let value = &mut stencil.get_pixel_mut(x, y)[0];
+                    *value = match fill_rule {
+                        FillRule::NonZero if area > 0.0 => value.wrapping_add(1),
+                        FillRule::NonZero => value.wrapping_sub(1),
+                        FillRule::EvenOdd => *value ^ 1,
+                    };
+                });
+            }
+        }
+        // Cover: fill every pixel the fill rule counts as inside, resetting the stencil
+        let mut written = 0;
+        for y in bounds[1]..bounds[1] + bounds[3] {
+            for x in bounds[0]..bounds[0] + bounds[2] {
+                let winding = &mut stencil.get_pixel_mut(x, y)[0];
+                let inside = fill_rule.is_inside(*winding);
+                *winding = 0;
+                if !inside {
+                    continue;
+                }
+                let under_color = color_rgba_f32(*self.inner.get_pixel(x, y));
+                let layered_color =
+                    color_space.blend(Some(Blend::Alpha), &color, &under_color, 1.0);
+                self.inner.put_pixel(x, y, color_f32_rgba(&layered_color));
+                if let Some(coverage) = &mut self.coverage {
+                    let pixel = coverage.get_pixel_mut(x, y);
+                    pixel[0] = accumulate_coverage(pixel[0], 1.0);
+                }
+                if let Some(ids) = &mut self.ids {
+                    ids[(y * width + x) as usize] = self.current_id;
+                }
+                written += 1;
+            }
+        }
+        self.pixels_written += written;
+    }
+}
@@ -0,0 +1,95 @@
+use std::f32::consts::PI;
+
+use image::imageops;
+
+use crate::{luminance, RenderBuffer};
+
+/// The size of the image that the DCT of a pHash is computed from
+const PHASH_SIZE: usize = 32;
+/// The number of low frequencies along each axis that a pHash keeps
+const PHASH_FREQUENCIES: usize = 8;
+
+impl RenderBuffer {
+    /**
+    Get the difference hash of the buffer.
+
+    The buffer is shrunk to 9×8 gray pixels, and each bit records whether a
+    pixel is brighter than its right neighbor. This is fast and tolerant of
+    scaling and small changes in color. Transparent pixels count as black.
+
+    Compare hashes with `hamming_distance`. Near-duplicate images usually
+    differ by fewer than 10 bits.
+    */
+    pub fn dhash(&self) -> u64 {
+        let gray = self.hash_source(9, 8);
+        let mut hash = 0;
+        for row in gray.chunks_exact(9) {
+            for pair in row.windows(2) {
+                hash = hash << 1 | u64::from(pair[0] > pair[1]);
+            }
+        }
+        hash
+    }
+    /**
+    Get the perceptual hash of the buffer.
+
+    The buffer is shrunk to 32×32 gray pixels, and each bit records whether one
+    of the 64 lowest frequencies of its discrete cosine transform is above the
+    median. This is slower than `dhash`, but more tolerant of blurring,
+    compression and changes in contrast. Transparent pixels count as black.
+
+    Compare hashes with `hamming_distance`.
+    */
+    pub fn phash(&self) -> u64 {
+        let gray = self.hash_source(PHASH_SIZE as u32, PHASH_SIZE as u32);
+        let cosines: Vec<f32> = (0..PHASH_FREQUENCIES)
+            .flat_map(|u| {
+                (0..PHASH_SIZE)
+                    .map(move |x| (PI / PHASH_SIZE as f32 * (x as f32 + 0.5) * u as f32).cos())
+            })
+            .collect();
+        let basis = |u: usize, x: usize| cosines[u * PHASH_SIZE + x];
+        // Only the low frequencies are needed, so the DCT is computed directly
+        let mut frequencies = Vec::with_capacity(PHASH_FREQUENCIES * PHASH_FREQUENCIES);
+        for v in 0..PHASH_FREQUENCIES {
+            for u in 0..PHASH_FREQUENCIES {
+                let mut sum = 0.0;
+                for (y, row) in gray.chunks_exact(PHASH_SIZE).enumerate() {
+                    for (x, value) in row.iter().enumerate() {
+                        sum += value * basis(u, x) * basis(v, y);
+                    }
+                }
+                frequencies.push(sum);
+            }
+        }
+        // The DC term is left out of the median, as it only reflects brightness
+        let mut sorted = frequencies[1..].to_vec();
+        sorted.sort_by(f32::total_cmp);
+        let median = (sorted[sorted.len() / 2 - 1] + sorted[sorted.len() / 2]) / 2.0;
+        frequencies
+            .iter()
+            .fold(0, |hash, &f| hash << 1 | u64::from(f > median))
+    }
+    /// Shrink the buffer to gray values of the given size, with alpha applied
+    fn hash_source(&self, width: u32, height: u32) -> Vec<f32> {
+        let pixels = self.straight_pixels();
+        if pixels.width() == 0 || pixels.height() == 0 {
+            return vec![0.0; (width * height) as usize];
+        }
+        imageops::resize(&*pixels, width, height, imageops::FilterType::Triangle)
+            .pixels()
+            .map(|p| luminance(p.0.map(f32::from)) * f32::from(p[3]) / (255.0 * 255.0))
+            .collect()
+    }
+}
+
+/**
+Get the number of bits that differ between two hashes from `RenderBuffer::dhash`
+or `RenderBuffer::phash`.
+
+A distance of 0 means the images look the same. Unrelated images are usually
+around 32 bits apart.
+*/
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
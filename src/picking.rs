@@ -0,0 +1,200 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use image::{ImageError, ImageResult};
+
+use crate::{BufferPixel, RenderBuffer};
+
+/// A tagged element found in a buffer's ID plane.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TaggedRegion {
+    /// The ID the element was drawn with
+    pub id: u32,
+    /// The tag of the ID
+    pub tag: String,
+    /// The bounding box of the element's pixels as `[x, y, width, height]`
+    pub bounds: [u32; 4],
+}
+
+impl<P: BufferPixel> RenderBuffer<P> {
+    /**
+    Enable or disable tracking of object IDs.
+
+    While enabled, every pixel drawn with at least half coverage has the
+    current ID, set with `set_current_id`, written to a separate plane. This
+    allows pixel-exact picking and automated tests that check which element
+    is at a pixel. Disabling tracking discards the plane.
+    */
+    pub fn set_id_tracking(&mut self, enabled: bool) {
+        self.ids = if enabled {
+            Some(vec![
+                0;
+                self.inner.width() as usize
+                    * self.inner.height() as usize
+            ])
+        } else {
+            None
+        };
+        self.track_memory();
+    }
+    /// Check if object ID tracking is enabled.
+    pub fn id_tracking(&self) -> bool {
+        self.ids.is_some()
+    }
+    /// Set the ID written for everything drawn from now on.
+    ///
+    /// The default is 0, which is also the ID of pixels that have not been drawn.
+    pub fn set_current_id(&mut self, id: u32) {
+        self.current_id = id;
+    }
+    /// Get the ID written for everything drawn.
+    pub fn current_id(&self) -> u32 {
+        self.current_id
+    }
+    /**
+    Get the ID of whatever was last drawn at the given coordinates.
+
+    Returns `None` if ID tracking is disabled or the coordinates are out of bounds.
+    */
+    pub fn id_at(&self, x: u32, y: u32) -> Option<u32> {
+        let (width, height) = self.inner.dimensions();
+        if x >= width || y >= height {
+            return None;
+        }
+        self.ids
+            .as_ref()
+            .map(|ids| ids[y as usize * width as usize + x as usize])
+    }
+    /// Get the IDs of every pixel, row by row, if ID tracking is enabled.
+    pub fn id_buffer(&self) -> Option<&[u32]> {
+        self.ids.as_deref()
+    }
+    /// Attach a string tag to an ID, returning the tag previously attached to it.
+    pub fn set_tag<S: Into<String>>(&mut self, id: u32, tag: S) -> Option<String> {
+        self.tags.insert(id, tag.into())
+    }
+    /// Get the tag attached to an ID.
+    pub fn tag(&self, id: u32) -> Option<&str> {
+        self.tags.get(&id).map(String::as_str)
+    }
+    /**
+    Tag everything drawn from now on, returning the new current ID.
+
+    The ID is one greater than the largest tagged ID, so each call starts a new
+    element.
+    */
+    pub fn set_current_tag<S: Into<String>>(&mut self, tag: S) -> u32 {
+        let id = self.tags.keys().next_back().map_or(1, |&id| id + 1);
+        self.set_tag(id, tag);
+        self.set_current_id(id);
+        id
+    }
+    /**
+    Find the bounding box of every tagged element in the ID plane.
+
+    Regions are sorted by ID. Tags with no pixels in the plane are skipped, and
+    the result is empty if ID tracking is disabled.
+    */
+    pub fn tagged_regions(&self) -> Vec<TaggedRegion> {
+        let ids = match &self.ids {
+            Some(ids) => ids,
+            None => return Vec::new(),
+        };
+        let width = self.inner.width() as usize;
+        if width == 0 {
+            return Vec::new();
+        }
+        let mut extents: BTreeMap<u32, [u32; 4]> = BTreeMap::new();
+        for (i, id) in ids.iter().enumerate() {
+            if !self.tags.contains_key(id) {
+                continue;
+            }
+            let (x, y) = ((i % width) as u32, (i / width) as u32);
+            let extent = extents.entry(*id).or_insert([x, y, x, y]);
+            extent[0] = extent[0].min(x);
+            extent[1] = extent[1].min(y);
+            extent[2] = extent[2].max(x);
+            extent[3] = extent[3].max(y);
+        }
+        extents
+            .into_iter()
+            .map(|(id, [x0, y0, x1, y1])| TaggedRegion {
+                id,
+                tag: self.tags[&id].clone(),
+                bounds: [x0, y0, x1 - x0 + 1, y1 - y0 + 1],
+            })
+            .collect()
+    }
+    /// Get a JSON description of the tagged regions found by `tagged_regions`.
+    pub fn tagged_regions_json(&self) -> String {
+        let regions: Vec<String> = self
+            .tagged_regions()
+            .iter()
+            .map(|region| {
+                let [x, y, width, height] = region.bounds;
+                format!(
+                    "{{\"id\":{},\"tag\":{},\"x\":{},\"y\":{},\"width\":{},\"height\":{}}}",
+                    region.id,
+                    json_string(&region.tag),
+                    x,
+                    y,
+                    width,
+                    height
+                )
+            })
+            .collect();
+        format!(
+            "{{\"width\":{},\"height\":{},\"regions\":[{}]}}",
+            self.width(),
+            self.height(),
+            regions.join(",")
+        )
+    }
+    /// Reset every ID to 0, if ID tracking is enabled.
+    pub fn reset_ids(&mut self) {
+        if let Some(ids) = &mut self.ids {
+            ids.iter_mut().for_each(|id| *id = 0);
+        }
+    }
+}
+
+impl RenderBuffer {
+    /**
+    Save the buffer to a file, along with a sidecar JSON file describing its
+    tagged regions.
+
+    The sidecar has the same path with a `.json` extension. Documentation
+    tools can use it to build image maps of clickable regions.
+    */
+    pub fn save_with_metadata<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        let path = path.as_ref();
+        self.save(path)?;
+        fs::write(path.with_extension("json"), self.tagged_regions_json())
+            .map_err(ImageError::IoError)
+    }
+}
+
+/// Encode a string as a JSON string literal
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Write an ID to a pixel of the ID plane if at least half of it is covered
+pub(crate) fn write_id(pixel: &mut u32, id: u32, coverage: f32) {
+    if coverage >= 0.5 {
+        *pixel = id;
+    }
+}
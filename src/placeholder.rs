@@ -0,0 +1,244 @@
+use std::f32::consts::PI;
+
+use image::{imageops, RgbaImage};
+
+use crate::{linear_to_srgb, srgb_to_linear, RenderBuffer};
+
+/// The largest size in either dimension that placeholders are computed from
+const MAX_SOURCE_SIZE: u32 = 100;
+/// The digits of the base 83 encoding used by blurhash
+const BASE83: &[u8] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+impl RenderBuffer {
+    /**
+    Encode the buffer as a [blurhash](https://blurha.sh).
+
+    `cx` and `cy` are the number of horizontal and vertical components. More
+    components keep more detail at the cost of a longer string. Alpha is ignored.
+    Buffers larger than 100 pixels in either dimension are downscaled first,
+    which makes little difference to such a blurry result.
+
+    # Panics
+
+    Panics if `cx` or `cy` is not in the range `1..=9`.
+    */
+    pub fn to_blurhash(&self, cx: u32, cy: u32) -> String {
+        assert!(
+            (1..=9).contains(&cx) && (1..=9).contains(&cy),
+            "Blurhash components must be between 1 and 9"
+        );
+        let image = self.placeholder_source();
+        let (width, height) = image.dimensions();
+        let pixels: Vec<[f32; 3]> = image
+            .pixels()
+            .map(|p| [0, 1, 2].map(|i| srgb_to_linear(f32::from(p[i]) / 255.0)))
+            .collect();
+        let mut factors = Vec::with_capacity((cx * cy) as usize);
+        for j in 0..cy {
+            for i in 0..cx {
+                let normalization = if i == 0 && j == 0 { 1.0 } else { 2.0 };
+                let fx = blurhash_cosines(width, i);
+                let fy = blurhash_cosines(height, j);
+                let mut sum = [0.0; 3];
+                for (y, row) in pixels.chunks_exact(width as usize).enumerate() {
+                    for (x, pixel) in row.iter().enumerate() {
+                        let basis = fx[x] * fy[y];
+                        for (total, channel) in sum.iter_mut().zip(pixel) {
+                            *total += basis * channel;
+                        }
+                    }
+                }
+                let scale = normalization / (width * height) as f32;
+                factors.push(sum.map(|total| total * scale));
+            }
+        }
+        let (dc, ac) = factors.split_first().expect("Blurhash has a DC component");
+        let mut hash = String::new();
+        encode83((cx - 1) + (cy - 1) * 9, 1, &mut hash);
+        let max_value = if ac.is_empty() {
+            encode83(0, 1, &mut hash);
+            1.0
+        } else {
+            let actual_max = ac
+                .iter()
+                .flatten()
+                .fold(0.0f32, |max, value| max.max(value.abs()));
+            let quantized_max = (actual_max * 166.0 - 0.5).floor().clamp(0.0, 82.0) as u32;
+            encode83(quantized_max, 1, &mut hash);
+            (quantized_max + 1) as f32 / 166.0
+        };
+        let [r, g, b] = dc.map(|channel| (linear_to_srgb(channel) * 255.0).round() as u32);
+        encode83((r << 16) + (g << 8) + b, 4, &mut hash);
+        for factor in ac {
+            let [r, g, b] = factor.map(|value| {
+                let value = value / max_value;
+                let signed_sqrt = value.abs().sqrt().copysign(value);
+                (signed_sqrt * 9.0 + 9.5).floor().clamp(0.0, 18.0) as u32
+            });
+            encode83(r * 19 * 19 + g * 19 + b, 2, &mut hash);
+        }
+        hash
+    }
+    /**
+    Encode the buffer as a [thumbhash](https://evanw.github.io/thumbhash/).
+
+    A thumbhash keeps the aspect ratio and alpha of the buffer, and is usually
+    around 25 bytes. Buffers larger than 100 pixels in either dimension are
+    downscaled first, as the format requires.
+    */
+    pub fn to_thumbhash(&self) -> Vec<u8> {
+        let image = self.placeholder_source();
+        let (w, h) = image.dimensions();
+        let area = (w * h) as f32;
+        // Average the color, weighted by alpha
+        let (mut avg_r, mut avg_g, mut avg_b, mut avg_a) = (0.0, 0.0, 0.0, 0.0);
+        for p in image.pixels() {
+            let alpha = f32::from(p[3]) / 255.0;
+            avg_r += alpha / 255.0 * f32::from(p[0]);
+            avg_g += alpha / 255.0 * f32::from(p[1]);
+            avg_b += alpha / 255.0 * f32::from(p[2]);
+            avg_a += alpha;
+        }
+        if avg_a > 0.0 {
+            avg_r /= avg_a;
+            avg_g /= avg_a;
+            avg_b /= avg_a;
+        }
+        let has_alpha = avg_a < area;
+        let l_limit = if has_alpha { 5.0 } else { 7.0 };
+        let longest = w.max(h) as f32;
+        let lx = ((l_limit * w as f32 / longest).round() as u32).max(1);
+        let ly = ((l_limit * h as f32 / longest).round() as u32).max(1);
+        // Convert to luminance, two chroma channels and alpha
+        let len = (w * h) as usize;
+        let (mut l, mut p, mut q, mut a) = (
+            Vec::with_capacity(len),
+            Vec::with_capacity(len),
+            Vec::with_capacity(len),
+            Vec::with_capacity(len),
+        );
+        for pixel in image.pixels() {
+            let alpha = f32::from(pixel[3]) / 255.0;
+            let r = avg_r * (1.0 - alpha) + alpha / 255.0 * f32::from(pixel[0]);
+            let g = avg_g * (1.0 - alpha) + alpha / 255.0 * f32::from(pixel[1]);
+            let b = avg_b * (1.0 - alpha) + alpha / 255.0 * f32::from(pixel[2]);
+            l.push((r + g + b) / 3.0);
+            p.push((r + g) / 2.0 - b);
+            q.push(r - g);
+            a.push(alpha);
+        }
+        let (l_dc, l_ac, l_scale) = encode_channel(&l, w, h, lx.max(3), ly.max(3));
+        let (p_dc, p_ac, p_scale) = encode_channel(&p, w, h, 3, 3);
+        let (q_dc, q_ac, q_scale) = encode_channel(&q, w, h, 3, 3);
+        let is_landscape = w > h;
+        let header24 = (63.0 * l_dc).round() as u32
+            | ((31.5 + 31.5 * p_dc).round() as u32) << 6
+            | ((31.5 + 31.5 * q_dc).round() as u32) << 12
+            | ((31.0 * l_scale).round() as u32) << 18
+            | u32::from(has_alpha) << 23;
+        let header16 = if is_landscape { ly } else { lx }
+            | ((63.0 * p_scale).round() as u32) << 3
+            | ((63.0 * q_scale).round() as u32) << 9
+            | u32::from(is_landscape) << 15;
+        let mut hash = vec![
+            header24 as u8,
+            (header24 >> 8) as u8,
+            (header24 >> 16) as u8,
+            header16 as u8,
+            (header16 >> 8) as u8,
+        ];
+        let mut acs = vec![l_ac, p_ac, q_ac];
+        if has_alpha {
+            let (a_dc, a_ac, a_scale) = encode_channel(&a, w, h, 5, 5);
+            hash.push((15.0 * a_dc).round() as u8 | ((15.0 * a_scale).round() as u8) << 4);
+            acs.push(a_ac);
+        }
+        // Pack the AC components two to a byte
+        for (i, f) in acs.iter().flatten().enumerate() {
+            let nibble = (15.0 * f).round() as u8;
+            if i % 2 == 0 {
+                hash.push(nibble);
+            } else {
+                *hash.last_mut().unwrap() |= nibble << 4;
+            }
+        }
+        hash
+    }
+    /// Get the straight alpha pixels to compute a placeholder from, downscaled if they are too large.
+    /// Empty buffers give a single transparent pixel.
+    fn placeholder_source(&self) -> RgbaImage {
+        let pixels = self.straight_pixels();
+        let (width, height) = pixels.dimensions();
+        if width == 0 || height == 0 {
+            return RgbaImage::new(1, 1);
+        }
+        if width.max(height) <= MAX_SOURCE_SIZE {
+            return pixels.into_owned();
+        }
+        let scale = MAX_SOURCE_SIZE as f32 / width.max(height) as f32;
+        imageops::resize(
+            &*pixels,
+            ((width as f32 * scale).round() as u32).max(1),
+            ((height as f32 * scale).round() as u32).max(1),
+            imageops::FilterType::Triangle,
+        )
+    }
+}
+
+/// Get the cosine basis function of a frequency at the corner of each pixel along an axis,
+/// as the reference blurhash encoder samples it
+fn blurhash_cosines(size: u32, frequency: u32) -> Vec<f32> {
+    (0..size)
+        .map(|x| (PI * frequency as f32 * x as f32 / size as f32).cos())
+        .collect()
+}
+
+/// Get the cosine basis function of a frequency at the center of each pixel along an axis
+fn cosines(size: u32, frequency: u32) -> Vec<f32> {
+    (0..size)
+        .map(|x| (PI / size as f32 * frequency as f32 * (x as f32 + 0.5)).cos())
+        .collect()
+}
+
+/// Append a value as the given number of base 83 digits
+fn encode83(value: u32, length: u32, hash: &mut String) {
+    for i in (0..length).rev() {
+        let digit = value / 83u32.pow(i) % 83;
+        hash.push(BASE83[digit as usize] as char);
+    }
+}
+
+/// Get the DC component, normalized AC components and AC scale of one thumbhash channel
+fn encode_channel(channel: &[f32], w: u32, h: u32, nx: u32, ny: u32) -> (f32, Vec<f32>, f32) {
+    let mut dc = 0.0;
+    let mut ac = Vec::new();
+    let mut scale = 0.0f32;
+    for cy in 0..ny {
+        let fy = cosines(h, cy);
+        let mut cx = 0;
+        while cx * ny < nx * (ny - cy) {
+            let fx = cosines(w, cx);
+            let mut f = 0.0;
+            for (y, row) in channel.chunks_exact(w as usize).enumerate() {
+                for (x, value) in row.iter().enumerate() {
+                    f += value * fx[x] * fy[y];
+                }
+            }
+            f /= (w * h) as f32;
+            if cx > 0 || cy > 0 {
+                ac.push(f);
+                scale = scale.max(f.abs());
+            } else {
+                dc = f;
+            }
+            cx += 1;
+        }
+    }
+    if scale > 0.0 {
+        for f in &mut ac {
+            *f = 0.5 + 0.5 / scale * *f;
+        }
+    }
+    (dc, ac, scale)
+}
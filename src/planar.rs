@@ -0,0 +1,181 @@
+use std::path::Path;
+
+use image::{ImageResult, RgbaImage};
+
+use crate::RenderBuffer;
+
+/// A channel of an RGBA color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Channel {
+    /// The red channel
+    Red,
+    /// The green channel
+    Green,
+    /// The blue channel
+    Blue,
+    /// The alpha channel
+    Alpha,
+}
+
+impl Channel {
+    /// All of the channels, in RGBA order.
+    pub const ALL: [Channel; 4] = [Channel::Red, Channel::Green, Channel::Blue, Channel::Alpha];
+    /// Get the index of the channel within an RGBA pixel.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/**
+A buffer that stores each channel in its own contiguous plane.
+
+A `RenderBuffer` stores its pixels interleaved, which is what drawing and
+image files want. Per-channel post-processing like blurs, lookup tables and
+channel operations touch far less memory on planar data, so converting a large
+buffer with `RenderBuffer::to_planar`, processing it, and converting it back
+can be significantly faster. Saving a `PlanarBuffer` converts it back to
+interleaved pixels.
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlanarBuffer {
+    width: u32,
+    height: u32,
+    planes: [Vec<u8>; 4],
+}
+
+impl PlanarBuffer {
+    /// Create a new `PlanarBuffer` with all pixels transparent black.
+    pub fn new(width: u32, height: u32) -> PlanarBuffer {
+        let plane = vec![0; width as usize * height as usize];
+        PlanarBuffer {
+            width,
+            height,
+            planes: [plane.clone(), plane.clone(), plane.clone(), plane],
+        }
+    }
+    /// Get the width of the buffer.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    /// Get the height of the buffer.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    /// Get the width and height of the buffer.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+    /// Get the values of a channel, row by row.
+    pub fn plane(&self, channel: Channel) -> &[u8] {
+        &self.planes[channel.index()]
+    }
+    /// Get the values of a channel mutably, row by row.
+    pub fn plane_mut(&mut self, channel: Channel) -> &mut [u8] {
+        &mut self.planes[channel.index()]
+    }
+    /// Replace every value of a channel with the result of a function.
+    ///
+    /// The function is only called once for each possible value.
+    pub fn map_plane<F: Fn(u8) -> u8>(&mut self, channel: Channel, f: F) {
+        let mut table = [0; 256];
+        for (i, value) in table.iter_mut().enumerate() {
+            *value = f(i as u8);
+        }
+        for value in self.plane_mut(channel) {
+            *value = table[*value as usize];
+        }
+    }
+    /// Copy the values of one channel into another.
+    pub fn copy_plane(&mut self, from: Channel, to: Channel) {
+        if from != to {
+            let source = self.planes[from.index()].clone();
+            self.planes[to.index()] = source;
+        }
+    }
+    /**
+    Blur a channel with a box filter of the given radius.
+
+    Pixels past the edges are treated as copies of the nearest edge pixel.
+    */
+    pub fn box_blur_plane(&mut self, channel: Channel, radius: u32) {
+        let (width, height) = (self.width as usize, self.height as usize);
+        if radius == 0 || width == 0 || height == 0 {
+            return;
+        }
+        let plane = &mut self.planes[channel.index()];
+        let mut line = Vec::with_capacity(width.max(height));
+        for row in plane.chunks_exact_mut(width) {
+            line.clear();
+            line.extend_from_slice(row);
+            blur_line(&line, radius as usize, |i, value| row[i] = value);
+        }
+        for x in 0..width {
+            line.clear();
+            line.extend((0..height).map(|y| plane[y * width + x]));
+            blur_line(&line, radius as usize, |y, value| {
+                plane[y * width + x] = value
+            });
+        }
+    }
+    /// Blur every channel with a box filter of the given radius.
+    pub fn box_blur(&mut self, radius: u32) {
+        for &channel in &Channel::ALL {
+            self.box_blur_plane(channel, radius);
+        }
+    }
+    /// Interleave the planes into a new `RenderBuffer`.
+    pub fn to_render_buffer(&self) -> RenderBuffer {
+        let [r, g, b, a] = &self.planes;
+        let mut data = Vec::with_capacity(r.len() * 4);
+        for (((r, g), b), a) in r.iter().zip(g).zip(b).zip(a) {
+            data.extend_from_slice(&[*r, *g, *b, *a]);
+        }
+        RgbaImage::from_raw(self.width, self.height, data)
+            .expect("Planes have the wrong number of values")
+            .into()
+    }
+    /// Save the buffer to a file, with the format deduced from the extension.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        self.to_render_buffer().save(path)
+    }
+}
+
+/// Box blur a line of values, writing each result with `write`
+fn blur_line<W: FnMut(usize, u8)>(line: &[u8], radius: usize, mut write: W) {
+    let len = line.len() as isize;
+    let radius = radius as isize;
+    let at = |i: isize| u32::from(line[i.clamp(0, len - 1) as usize]);
+    let count = (2 * radius + 1) as u32;
+    let mut sum: u32 = (-radius..=radius).map(at).sum();
+    for i in 0..len {
+        write(i as usize, ((sum + count / 2) / count) as u8);
+        sum += at(i + radius + 1);
+        sum -= at(i - radius);
+    }
+}
+
+impl RenderBuffer {
+    /// Split the buffer's channels into a new `PlanarBuffer`.
+    pub fn to_planar(&self) -> PlanarBuffer {
+        let (width, height) = self.dimensions();
+        let mut planar = PlanarBuffer::new(width, height);
+        for (i, pixel) in self.straight_pixels().pixels().enumerate() {
+            for (plane, &value) in planar.planes.iter_mut().zip(&pixel.0) {
+                plane[i] = value;
+            }
+        }
+        planar
+    }
+}
+
+impl From<&RenderBuffer> for PlanarBuffer {
+    fn from(buffer: &RenderBuffer) -> Self {
+        buffer.to_planar()
+    }
+}
+
+impl From<PlanarBuffer> for RenderBuffer {
+    fn from(planar: PlanarBuffer) -> Self {
+        planar.to_render_buffer()
+    }
+}
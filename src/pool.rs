@@ -0,0 +1,82 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::RenderBuffer;
+
+/**
+A thread-safe pool that recycles `RenderBuffer`s of the same size.
+
+Taking a buffer from the pool reuses one that was given back if there is one
+of the right size, which avoids repeated large allocations in video and
+animation pipelines.
+*/
+#[derive(Debug)]
+pub struct BufferPool {
+    buffers: Mutex<HashMap<(u32, u32), Vec<RenderBuffer>>>,
+    max_per_size: usize,
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        BufferPool::new()
+    }
+}
+
+impl BufferPool {
+    /// Create a new empty `BufferPool` that keeps up to 8 buffers of each size.
+    pub fn new() -> Self {
+        BufferPool::with_max_per_size(8)
+    }
+    /// Create a new empty `BufferPool` that keeps up to `max_per_size` buffers of each size.
+    ///
+    /// Buffers given back beyond this limit are dropped.
+    pub fn with_max_per_size(max_per_size: usize) -> Self {
+        BufferPool {
+            buffers: Mutex::default(),
+            max_per_size,
+        }
+    }
+    fn lock(&self) -> MutexGuard<'_, HashMap<(u32, u32), Vec<RenderBuffer>>> {
+        self.buffers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    /**
+    Take a buffer of the given size from the pool, or create one if there is none.
+
+    A recycled buffer still contains whatever was drawn to it. Use `take_cleared`
+    to get a buffer with known contents.
+    */
+    pub fn take(&self, width: u32, height: u32) -> RenderBuffer {
+        self.lock()
+            .get_mut(&(width, height))
+            .and_then(Vec::pop)
+            .unwrap_or_else(|| RenderBuffer::new(width, height))
+    }
+    /// Take a buffer of the given size from the pool and clear it with a color.
+    pub fn take_cleared(&self, width: u32, height: u32, color: [f32; 4]) -> RenderBuffer {
+        let mut buffer = self.take(width, height);
+        buffer.clear(color);
+        buffer
+    }
+    /// Give a buffer back to the pool so that it can be reused.
+    pub fn give(&self, buffer: RenderBuffer) {
+        let mut buffers = self.lock();
+        let same_size = buffers.entry(buffer.dimensions()).or_default();
+        if same_size.len() < self.max_per_size {
+            same_size.push(buffer);
+        }
+    }
+    /// Get the number of buffers in the pool.
+    pub fn len(&self) -> usize {
+        self.lock().values().map(Vec::len).sum()
+    }
+    /// Check if the pool has no buffers.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Drop all of the buffers in the pool.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+}
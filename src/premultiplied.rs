@@ -0,0 +1,49 @@
+use std::borrow::Cow;
+
+use image::RgbaImage;
+
+use crate::{premultiply_rgba8, unpremultiply_rgba8, RenderBuffer};
+
+impl RenderBuffer {
+    /// Check if the buffer stores its pixels with premultiplied alpha.
+    pub fn is_premultiplied(&self) -> bool {
+        self.premultiplied
+    }
+    /**
+    Set whether the buffer stores its pixels with premultiplied alpha.
+
+    In premultiplied mode, drawing blends in premultiplied space, which avoids
+    dark fringes when compositing semi-transparent colors and matches how most
+    GPU pipelines work. The existing pixels are converted, and saving converts
+    them back to straight alpha. Methods like `pixel` and `set_pixel` always use
+    straight colors, but the raw pixel data is premultiplied.
+
+    The default is `false`.
+    */
+    pub fn set_premultiplied(&mut self, premultiplied: bool) {
+        if premultiplied != self.premultiplied {
+            self.mark_all_dirty();
+            if premultiplied {
+                premultiply_rgba8(&mut self.inner);
+            } else {
+                unpremultiply_rgba8(&mut self.inner);
+            }
+            self.premultiplied = premultiplied;
+        }
+    }
+    /// Set whether the buffer stores its pixels with premultiplied alpha.
+    pub fn with_premultiplied(mut self, premultiplied: bool) -> Self {
+        self.set_premultiplied(premultiplied);
+        self
+    }
+    /// Get the pixels with straight alpha, converting them if they are premultiplied.
+    pub fn straight_pixels(&self) -> Cow<'_, RgbaImage> {
+        if self.premultiplied {
+            let mut image = self.inner.clone();
+            unpremultiply_rgba8(&mut image);
+            Cow::Owned(image)
+        } else {
+            Cow::Borrowed(&self.inner)
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use graphics::{math::Matrix2d, Transformed};
+use image::{
+    imageops::{self, FilterType},
+    DynamicImage, ImageFormat, ImageResult,
+};
+
+use crate::{Origin, RenderBuffer, IDENTITY};
+
+/// The progress of a long running operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Progress {
+    /// The amount of work done, such as tiles rendered or bytes written
+    pub done: u64,
+    /// The total amount of work, if it is known
+    pub total: Option<u64>,
+}
+
+impl Progress {
+    /// Get the fraction of the work that is done, from `0.0` to `1.0`, if the total is known.
+    pub fn fraction(&self) -> Option<f64> {
+        self.total.map(|total| {
+            if total == 0 {
+                1.0
+            } else {
+                self.done as f64 / total as f64
+            }
+        })
+    }
+    /// Get the percentage of the work that is done, from `0.0` to `100.0`, if the total is known.
+    pub fn percent(&self) -> Option<f64> {
+        self.fraction().map(|f| f * 100.0)
+    }
+}
+
+/// A writer that reports the number of bytes written
+struct ProgressWriter<W, P> {
+    inner: W,
+    written: u64,
+    progress: P,
+}
+
+impl<W: Write, P: FnMut(Progress)> Write for ProgressWriter<W, P> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        (self.progress)(Progress {
+            done: self.written,
+            total: None,
+        });
+        Ok(n)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl RenderBuffer {
+    /**
+    Draw in square tiles of the given size, reporting progress after each tile.
+
+    The drawing function is called once per tile with a transform that maps
+    buffer coordinates into the tile, and a buffer the size of the tile. This
+    allows huge images to show progress while they render.
+
+    Progress is reported as the number of tiles done out of the total.
+    */
+    pub fn render_tiled<D, P>(&mut self, tile_size: u32, mut draw: D, mut progress: P)
+    where
+        D: FnMut(Matrix2d, &mut RenderBuffer),
+        P: FnMut(Progress),
+    {
+        let tile_size = tile_size.max(1);
+        let (width, height) = self.inner.dimensions();
+        let columns = width.div_ceil(tile_size);
+        let rows = height.div_ceil(tile_size);
+        let total = u64::from(columns) * u64::from(rows);
+        let mut done = 0;
+        for row in 0..rows {
+            for column in 0..columns {
+                let (x, y) = (column * tile_size, row * tile_size);
+                let mut tile = self.crop(x, y, tile_size, tile_size);
+                draw(IDENTITY.trans(-f64::from(x), -f64::from(y)), &mut tile);
+                self.mark_dirty([x, y, tile.width(), tile.height()]);
+                imageops::replace(&mut self.inner, &tile.inner, x, y);
+                self.pixels_written += tile.pixels_written;
+                done += 1;
+                progress(Progress {
+                    done,
+                    total: Some(total),
+                });
+            }
+        }
+    }
+    /**
+    Build an image pyramid like `build_pyramid`, reporting progress after each level.
+
+    Progress is reported as the number of levels built out of `levels`.
+    */
+    pub fn build_pyramid_with_progress<P>(
+        &self,
+        levels: usize,
+        filter: FilterType,
+        mut progress: P,
+    ) -> Vec<RenderBuffer>
+    where
+        P: FnMut(Progress),
+    {
+        let total = levels.min(self.pyramid_levels()) as u64;
+        let mut pyramid: Vec<RenderBuffer> = Vec::with_capacity(levels);
+        if levels == 0 {
+            return pyramid;
+        }
+        pyramid.push(self.clone());
+        progress(Progress {
+            done: 1,
+            total: Some(total),
+        });
+        while pyramid.len() < levels {
+            let last = pyramid.last().unwrap();
+            let (width, height) = last.dimensions();
+            if width <= 1 && height <= 1 {
+                break;
+            }
+            let next =
+                imageops::resize(&last.inner, (width / 2).max(1), (height / 2).max(1), filter);
+            pyramid.push(next.into());
+            progress(Progress {
+                done: pyramid.len() as u64,
+                total: Some(total),
+            });
+        }
+        pyramid
+    }
+    /**
+    Save the buffer to a file like `save`, reporting the number of bytes encoded.
+
+    The total is not known ahead of time, since it depends on compression.
+    */
+    pub fn save_with_progress<Q, P>(&self, path: Q, progress: P) -> ImageResult<()>
+    where
+        Q: AsRef<Path>,
+        P: FnMut(Progress),
+    {
+        let path = path.as_ref();
+        let format = ImageFormat::from_path(path)?;
+        let image = match self.origin {
+            Origin::TopLeft => self.inner.clone(),
+            Origin::BottomLeft => imageops::flip_vertical(&self.inner),
+        };
+        let mut writer = ProgressWriter {
+            inner: BufWriter::new(File::create(path)?),
+            written: 0,
+            progress,
+        };
+        DynamicImage::ImageRgba8(image).write_to(&mut writer, format)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,39 @@
+use std::{fs, path::Path};
+
+use image::{imageops::FilterType, ImageResult};
+
+use crate::RenderBuffer;
+
+impl RenderBuffer {
+    /**
+    Build an image pyramid, or mip chain, from the buffer.
+
+    The first level is a copy of the buffer, and each following level is half
+    the size of the one before it, rounding down but never below 1 pixel.
+    At most `levels` levels are built, and building stops early once a 1x1
+    level is reached.
+    */
+    pub fn build_pyramid(&self, levels: usize, filter: FilterType) -> Vec<RenderBuffer> {
+        self.build_pyramid_with_progress(levels, filter, |_| {})
+    }
+    /// Get the number of levels in a full pyramid of the buffer, down to 1x1.
+    pub fn pyramid_levels(&self) -> usize {
+        let largest = self.width().max(self.height()).max(1);
+        (32 - largest.leading_zeros()) as usize
+    }
+}
+
+/**
+Save each level of a pyramid as a PNG in the given directory.
+
+Levels are named `level_0.png`, `level_1.png` and so on. The directory is
+created if it does not exist.
+*/
+pub fn save_pyramid<P: AsRef<Path>>(pyramid: &[RenderBuffer], dir: P) -> ImageResult<()> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+    for (i, level) in pyramid.iter().enumerate() {
+        level.save(dir.join(format!("level_{}.png", i)))?;
+    }
+    Ok(())
+}
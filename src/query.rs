@@ -0,0 +1,29 @@
+use crate::RenderBuffer;
+
+impl RenderBuffer {
+    /// Get the number of pixels written by drawing since the count was last reset.
+    ///
+    /// A pixel is counted once for every triangle that writes to it.
+    pub fn pixels_written(&self) -> usize {
+        self.pixels_written
+    }
+    /// Reset the count of pixels written to zero.
+    pub fn reset_pixels_written(&mut self) {
+        self.pixels_written = 0;
+    }
+    /**
+    Run some drawing code and return the number of pixels it wrote, like an
+    occlusion query.
+
+    This is useful for visibility testing, or for asserting in tests that
+    something was actually drawn.
+    */
+    pub fn count_pixels<F>(&mut self, draw: F) -> usize
+    where
+        F: FnOnce(&mut RenderBuffer),
+    {
+        let before = self.pixels_written;
+        draw(self);
+        self.pixels_written.saturating_sub(before)
+    }
+}
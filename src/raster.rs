@@ -0,0 +1,651 @@
+use std::fmt;
+
+/**
+Determines which pixels are covered by a triangle.
+
+A `RenderBuffer` uses its rasterizer for every triangle it draws, and blends
+each covered pixel according to its coverage. Different rasterizers trade
+speed for quality without changing any drawing code.
+*/
+pub trait Rasterizer: fmt::Debug + Send + Sync {
+    /**
+    Call `plot` with the coordinates and coverage of every pixel covered by `tri`.
+
+    `tri` contains exactly three vertices in pixel coordinates, wound in either
+    direction. Only pixels inside `clip`, given as `[x, y, width, height]`,
+    should be plotted. Coverage is in `(0, 1]`, and pixels with no coverage
+    should not be plotted.
+
+    Like on a GPU, degenerate triangles cover nothing. These are triangles
+    with collinear vertices or a vertex that is not finite.
+
+    Rasterizers with binary coverage should follow the top-left fill rule, so
+    that a pixel lying exactly on an edge shared by two triangles is plotted
+    by only one of them.
+    */
+    fn rasterize(&self, tri: &[[f32; 2]], clip: [u32; 4], plot: &mut dyn FnMut(u32, u32, f32));
+}
+
+/**
+The default rasterizer.
+
+Each pixel is either fully covered or not covered at all, depending on whether
+its position lies within the triangle. Pixels exactly on an edge are only
+covered if it is a top or left edge, so triangles that share an edge never
+both cover the pixels on it.
+
+Each row is filled as a single span found by solving the edge functions,
+so only the pixels at the ends of a span are tested individually.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ScanlineRasterizer;
+
+impl Rasterizer for ScanlineRasterizer {
+    fn rasterize(&self, tri: &[[f32; 2]], clip: [u32; 4], plot: &mut dyn FnMut(u32, u32, f32)) {
+        let sign = match orientation(tri) {
+            Some(sign) if clip[2] > 0 => sign,
+            _ => return,
+        };
+        let (left, right) = (clip[0] as i64, (clip[0] + clip[2]) as i64 - 1);
+        'rows: for y in clip[1]..clip[1] + clip[3] {
+            let py = y as f32;
+            // Each edge function is linear along the row, so solve for where it
+            // crosses zero to get the span of the row inside the triangle
+            let (mut start, mut end) = (f32::NEG_INFINITY, f32::INFINITY);
+            for (i, &p) in tri.iter().enumerate().take(3) {
+                let q = tri[(i + 1) % 3];
+                let slope = -(q[1] - p[1]) * sign;
+                let offset = ((q[0] - p[0]) * (py - p[1]) + (q[1] - p[1]) * p[0]) * sign;
+                if slope > 0.0 {
+                    start = start.max(-offset / slope);
+                } else if slope < 0.0 {
+                    end = end.min(-offset / slope);
+                } else if offset < 0.0 {
+                    continue 'rows;
+                }
+            }
+            if start > end + 1.0 {
+                continue;
+            }
+            // The solved span is only approximate, so pixels at its ends are
+            // checked exactly to keep the top-left fill rule
+            let start = (start.ceil() as i64 - 1).max(left);
+            let end = (end.floor() as i64 + 1).min(right);
+            let covers = |x: i64| triangle_contains(tri, [x as f32, py]);
+            let first = match (start..=end).find(|&x| covers(x)) {
+                Some(first) => first,
+                None => continue,
+            };
+            let last = (first..=end).rev().find(|&x| covers(x)).unwrap_or(first);
+            for x in first..=last {
+                plot(x as u32, y, 1.0);
+            }
+        }
+    }
+}
+
+/**
+A fast rasterizer that uses fixed-point integer edge functions.
+
+Vertices are snapped to a 1/256 pixel grid, and edge functions are stepped
+incrementally across each row. Like `ScanlineRasterizer`, coverage is binary
+and the top-left fill rule decides which triangle covers a shared edge.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct IntegerRasterizer;
+
+impl Rasterizer for IntegerRasterizer {
+    fn rasterize(&self, tri: &[[f32; 2]], clip: [u32; 4], plot: &mut dyn FnMut(u32, u32, f32)) {
+        const ONE: i64 = 256;
+        if orientation(tri).is_none() {
+            return;
+        }
+        let snap = |v: [f32; 2]| {
+            [
+                (v[0] * ONE as f32).round() as i64,
+                (v[1] * ONE as f32).round() as i64,
+            ]
+        };
+        let (a, mut b, mut c) = (snap(tri[0]), snap(tri[1]), snap(tri[2]));
+        // Triangles thinner than the grid can collapse when snapped, but then
+        // they are too thin to cover any pixel anyway
+        let area = (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]);
+        if area == 0 {
+            return;
+        }
+        if area < 0 {
+            std::mem::swap(&mut b, &mut c);
+        }
+        let edges = [(a, b), (b, c), (c, a)];
+        // Pixels on an edge only count if it is a top or left edge
+        let bias = edges.map(|(p, q)| {
+            let (dx, dy) = (q[0] - p[0], q[1] - p[1]);
+            if dy < 0 || dy == 0 && dx > 0 {
+                0
+            } else {
+                -1
+            }
+        });
+        let x0 = clip[0] as i64;
+        for y in clip[1]..clip[1] + clip[3] {
+            let py = y as i64 * ONE;
+            let px = x0 * ONE;
+            let mut w = [0i64; 3];
+            let mut step = [0i64; 3];
+            for (i, (p, q)) in edges.iter().enumerate() {
+                w[i] = (q[0] - p[0]) * (py - p[1]) - (q[1] - p[1]) * (px - p[0]) + bias[i];
+                step[i] = -(q[1] - p[1]) * ONE;
+            }
+            for x in clip[0]..clip[0] + clip[2] {
+                if w[0] >= 0 && w[1] >= 0 && w[2] >= 0 {
+                    plot(x, y, 1.0);
+                }
+                for (w, step) in w.iter_mut().zip(step) {
+                    *w += step;
+                }
+            }
+        }
+    }
+}
+
+/**
+An anti-aliasing rasterizer that computes fractional coverage at triangle edges.
+
+The coverage of a pixel is estimated from its distance to each of the
+triangle's edges, which gives smooth edges without the memory cost of
+supersampling. Pixels more than half a pixel inside every edge are fully covered.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct AnalyticRasterizer;
+
+impl Rasterizer for AnalyticRasterizer {
+    fn rasterize(&self, tri: &[[f32; 2]], clip: [u32; 4], plot: &mut dyn FnMut(u32, u32, f32)) {
+        let edges = match edge_equations(tri) {
+            Some(edges) => edges,
+            None => return,
+        };
+        for y in clip[1]..clip[1] + clip[3] {
+            for x in clip[0]..clip[0] + clip[2] {
+                let (px, py) = (x as f32, y as f32);
+                let coverage = edges
+                    .iter()
+                    .map(|e| (edge_distance(e, px, py) + 0.5).clamp(0.0, 1.0))
+                    .product::<f32>();
+                if coverage > 0.0 {
+                    plot(x, y, coverage);
+                }
+            }
+        }
+    }
+}
+
+/**
+An anti-aliasing rasterizer that only supersamples pixels on triangle edges.
+
+Pixels entirely inside a triangle are fully covered without any extra work,
+and only pixels that an edge passes through are sampled on a grid of
+`samples` × `samples` points. On large flat-filled shapes this gives quality
+close to full supersampling at a fraction of the cost.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AdaptiveRasterizer {
+    /// The number of samples along each axis of an edge pixel
+    pub samples: u32,
+}
+
+impl Default for AdaptiveRasterizer {
+    fn default() -> Self {
+        AdaptiveRasterizer { samples: 4 }
+    }
+}
+
+impl AdaptiveRasterizer {
+    /// Create a new `AdaptiveRasterizer` that takes `samples` × `samples` samples of edge pixels.
+    pub fn new(samples: u32) -> Self {
+        AdaptiveRasterizer {
+            samples: samples.max(1),
+        }
+    }
+}
+
+impl Rasterizer for AdaptiveRasterizer {
+    fn rasterize(&self, tri: &[[f32; 2]], clip: [u32; 4], plot: &mut dyn FnMut(u32, u32, f32)) {
+        // A pixel is entirely on one side of an edge if its center is
+        // farther than half of its diagonal from it
+        const HALF_DIAGONAL: f32 = std::f32::consts::FRAC_1_SQRT_2;
+        let edges = match edge_equations(tri) {
+            Some(edges) => edges,
+            None => return,
+        };
+        let n = self.samples.max(1);
+        let step = 1.0 / n as f32;
+        for y in clip[1]..clip[1] + clip[3] {
+            for x in clip[0]..clip[0] + clip[2] {
+                let (px, py) = (x as f32, y as f32);
+                let distances = edges.map(|e| edge_distance(&e, px, py));
+                if distances.iter().any(|&d| d <= -HALF_DIAGONAL) {
+                    continue;
+                }
+                if distances.iter().all(|&d| d >= HALF_DIAGONAL) {
+                    plot(x, y, 1.0);
+                    continue;
+                }
+                let mut inside = 0;
+                for sy in 0..n {
+                    for sx in 0..n {
+                        let sample_x = px - 0.5 + (sx as f32 + 0.5) * step;
+                        let sample_y = py - 0.5 + (sy as f32 + 0.5) * step;
+                        if edges
+                            .iter()
+                            .all(|e| edge_distance(e, sample_x, sample_y) >= 0.0)
+                        {
+                            inside += 1;
+                        }
+                    }
+                }
+                if inside > 0 {
+                    plot(x, y, inside as f32 / (n * n) as f32);
+                }
+            }
+        }
+    }
+}
+
+/**
+A high quality anti-aliasing rasterizer backed by `tiny-skia`.
+
+Coverage is computed analytically by `tiny-skia`'s path filler, which produces
+smooth edges at some cost in speed.
+*/
+#[cfg(feature = "tiny_skia_rasterizer")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TinySkiaRasterizer;
+
+#[cfg(feature = "tiny_skia_rasterizer")]
+impl Rasterizer for TinySkiaRasterizer {
+    fn rasterize(&self, tri: &[[f32; 2]], clip: [u32; 4], plot: &mut dyn FnMut(u32, u32, f32)) {
+        use tiny_skia::{FillRule, Mask, PathBuilder, Transform};
+        let mut builder = PathBuilder::new();
+        builder.move_to(tri[0][0], tri[0][1]);
+        builder.line_to(tri[1][0], tri[1][1]);
+        builder.line_to(tri[2][0], tri[2][1]);
+        builder.close();
+        let (path, mut mask) = match (builder.finish(), Mask::new(clip[2], clip[3])) {
+            (Some(path), Some(mask)) => (path, mask),
+            _ => return,
+        };
+        // Pixels are sampled at their integer coordinates, so shift by half a pixel
+        let transform = Transform::from_translate(0.5 - clip[0] as f32, 0.5 - clip[1] as f32);
+        mask.fill_path(&path, FillRule::Winding, true, transform);
+        for (i, &coverage) in mask.data().iter().enumerate() {
+            if coverage > 0 {
+                let i = i as u32;
+                plot(
+                    clip[0] + i % clip[2],
+                    clip[1] + i / clip[2],
+                    f32::from(coverage) / 255.0,
+                );
+            }
+        }
+    }
+}
+
+/// Get each edge of a triangle as a normalized line equation that is positive
+/// inside the triangle, or `None` if the triangle is degenerate
+fn edge_equations(tri: &[[f32; 2]]) -> Option<[[f32; 3]; 3]> {
+    let sign = orientation(tri)?;
+    let mut edges = [[0.0f32; 3]; 3];
+    for (i, edge) in edges.iter_mut().enumerate() {
+        let p = tri[i];
+        let q = tri[(i + 1) % 3];
+        let (dx, dy) = (q[0] - p[0], q[1] - p[1]);
+        let len = (dx * dx + dy * dy).sqrt();
+        if len == 0.0 {
+            return None;
+        }
+        let s = sign / len;
+        *edge = [-dy * s, dx * s, (dy * p[0] - dx * p[1]) * s];
+    }
+    Some(edges)
+}
+
+/// The signed distance from a point to an edge, positive inside the triangle
+fn edge_distance(edge: &[f32; 3], x: f32, y: f32) -> f32 {
+    edge[0] * x + edge[1] * y + edge[2]
+}
+
+/**
+Get the winding of a triangle as `1.0` or `-1.0`, or `None` if it is degenerate.
+
+A triangle is degenerate if its vertices are collinear, including when two of
+them are the same, or if any of them is not finite. The area is computed in
+`f64`, so long thin triangles keep their winding instead of rounding to zero
+area and leaving holes in tessellated shapes.
+*/
+pub(crate) fn orientation(tri: &[[f32; 2]]) -> Option<f32> {
+    if tri.len() < 3 || tri[..3].iter().flatten().any(|v| !v.is_finite()) {
+        return None;
+    }
+    let [a, b, c] = [tri[0], tri[1], tri[2]].map(|[x, y]| [f64::from(x), f64::from(y)]);
+    let area = (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]);
+    if area > 0.0 {
+        Some(1.0)
+    } else if area < 0.0 {
+        Some(-1.0)
+    } else {
+        None
+    }
+}
+
+/// Check if a triangle of either winding covers a point under the top-left fill rule
+pub(crate) fn triangle_contains(tri: &[[f32; 2]], point: [f32; 2]) -> bool {
+    let sign = match orientation(tri) {
+        Some(sign) => sign,
+        None => return false,
+    };
+    (0..3).all(|i| {
+        let (p, q) = (tri[i], tri[(i + 1) % 3]);
+        let w = edge_function(p, q, point) * sign;
+        let (dx, dy) = ((q[0] - p[0]) * sign, (q[1] - p[1]) * sign);
+        w > 0.0 || w == 0.0 && (dy < 0.0 || dy == 0.0 && dx > 0.0)
+    })
+}
+
+/// The edge function of the line from `p` to `q` at a point
+///
+/// The endpoints are put in a fixed order before evaluating, so triangles that
+/// share an edge get exactly opposite values no matter which way they wind.
+fn edge_function(p: [f32; 2], q: [f32; 2], point: [f32; 2]) -> f32 {
+    if (p[1], p[0]) > (q[1], q[0]) {
+        return -edge_function(q, p, point);
+    }
+    (q[0] - p[0]) * (point[1] - p[1]) - (q[1] - p[1]) * (point[0] - p[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use graphics::{
+        draw_state::DrawState, math::Matrix2d, polygon, rectangle, Context, Graphics, Rectangle,
+    };
+    use image::{Luma, Rgba};
+
+    use super::*;
+    use crate::{luminance, BufferPixel, RenderBuffer};
+
+    const WHITE: [f32; 4] = [1.0; 4];
+    const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+    const HALF_BLACK: [f32; 4] = [0.0, 0.0, 0.0, 0.5];
+    /// A triangle that spans several tiles
+    const LARGE: [[f64; 2]; 3] = [[3.0, 5.0], [197.0, 60.0], [40.0, 190.0]];
+
+    const CCW: [[f32; 2]; 3] = [[0.0, 0.0], [4.0, 0.0], [0.0, 4.0]];
+    const CW: [[f32; 2]; 3] = [[0.0, 0.0], [0.0, 4.0], [4.0, 0.0]];
+
+    #[test]
+    fn orientation_of_either_winding() {
+        assert_eq!(orientation(&CCW), Some(1.0));
+        assert_eq!(orientation(&CW), Some(-1.0));
+    }
+
+    #[test]
+    fn orientation_of_degenerate_triangles() {
+        assert_eq!(orientation(&[[0.0, 0.0], [1.0, 1.0], [2.0, 2.0]]), None);
+        assert_eq!(orientation(&[[1.0, 1.0], [1.0, 1.0], [3.0, 0.0]]), None);
+        assert_eq!(
+            orientation(&[[0.0, 0.0], [f32::NAN, 1.0], [1.0, 0.0]]),
+            None
+        );
+        assert_eq!(orientation(&CCW[..2]), None);
+    }
+
+    #[test]
+    fn contains_interior_points_of_either_winding() {
+        for tri in [CCW, CW] {
+            assert!(triangle_contains(&tri, [1.0, 1.0]));
+            assert!(!triangle_contains(&tri, [3.0, 3.0]));
+            assert!(!triangle_contains(&tri, [-1.0, 1.0]));
+        }
+    }
+
+    #[test]
+    fn degenerate_triangles_contain_nothing() {
+        let tri = [[0.0, 0.0], [1.0, 1.0], [2.0, 2.0]];
+        assert!(!triangle_contains(&tri, [1.0, 1.0]));
+    }
+
+    #[test]
+    fn shared_edge_points_belong_to_one_triangle() {
+        // Two triangles splitting a square along its diagonal
+        let upper = [[0.0, 0.0], [4.0, 0.0], [4.0, 4.0]];
+        let lower = [[0.0, 0.0], [4.0, 4.0], [0.0, 4.0]];
+        for tri in [upper, lower] {
+            let reversed = [tri[0], tri[2], tri[1]];
+            assert_eq!(
+                triangle_contains(&tri, [2.0, 2.0]),
+                triangle_contains(&reversed, [2.0, 2.0])
+            );
+        }
+        assert!(triangle_contains(&upper, [2.0, 2.0]) != triangle_contains(&lower, [2.0, 2.0]));
+    }
+
+    fn transform() -> Matrix2d {
+        Context::new().transform
+    }
+
+    fn white_buffer(width: u32, height: u32) -> RenderBuffer {
+        let mut buffer = RenderBuffer::new(width, height);
+        buffer.clear_color(WHITE);
+        buffer
+    }
+
+    /// Draw overlapping translucent shapes that cross tile boundaries
+    fn draw_shapes<P: BufferPixel>(buffer: &mut RenderBuffer<P>) {
+        buffer.clear_color(WHITE);
+        polygon([0.8, 0.2, 0.1, 0.6], &LARGE, transform(), buffer);
+        rectangle(
+            [0.1, 0.3, 0.9, 0.5],
+            [50.0, 30.0, 100.0, 120.0],
+            transform(),
+            buffer,
+        );
+    }
+
+    #[test]
+    fn shared_edges_are_drawn_once() {
+        let mut buffer = white_buffer(8, 8);
+        polygon(
+            HALF_BLACK,
+            &[[0.0, 0.0], [8.0, 0.0], [8.0, 8.0]],
+            transform(),
+            &mut buffer,
+        );
+        polygon(
+            HALF_BLACK,
+            &[[0.0, 0.0], [8.0, 8.0], [0.0, 8.0]],
+            transform(),
+            &mut buffer,
+        );
+        let first = *buffer.get_pixel(0, 0);
+        assert!(first[0] > 100 && first[0] < 150, "{:?}", first);
+        assert!(buffer.pixels().all(|pixel| *pixel == first));
+    }
+
+    #[test]
+    fn triangles_crossing_tiles_cover_the_same_pixels() {
+        let tri = LARGE.map(|[x, y]| [x as f32, y as f32]);
+        let check = |buffer: &RenderBuffer| {
+            for (x, y, pixel) in buffer.enumerate_pixels() {
+                let inside = triangle_contains(&tri, [x as f32, y as f32]);
+                let drawn = pixel.0 == [0, 0, 0, 255];
+                assert_eq!(inside, drawn, "pixel ({}, {})", x, y);
+            }
+        };
+        let mut buffer = white_buffer(200, 200);
+        polygon(BLACK, &LARGE, transform(), &mut buffer);
+        check(&buffer);
+        #[cfg(feature = "parallel")]
+        for threads in [1, 4] {
+            let mut buffer = white_buffer(200, 200);
+            buffer.set_threads(threads).unwrap();
+            polygon(BLACK, &LARGE, transform(), &mut buffer);
+            check(&buffer);
+        }
+    }
+
+    #[test]
+    fn overdraw_mask_is_reset_between_draw_calls() {
+        let mut buffer = white_buffer(100, 100);
+        rectangle(
+            HALF_BLACK,
+            [0.0, 0.0, 100.0, 100.0],
+            transform(),
+            &mut buffer,
+        );
+        let once = buffer.get_pixel(70, 70)[0];
+        rectangle(
+            HALF_BLACK,
+            [0.0, 0.0, 100.0, 100.0],
+            transform(),
+            &mut buffer,
+        );
+        let twice = buffer.get_pixel(70, 70)[0];
+        assert!(twice < once - 40, "{} then {}", once, twice);
+        assert!(buffer.pixels().all(|pixel| pixel[0] == twice));
+    }
+
+    #[test]
+    fn overdraw_prevention_can_be_toggled() {
+        // A square drawn twice in one draw call
+        let square = [
+            [0.0, 0.0],
+            [4.0, 0.0],
+            [4.0, 4.0],
+            [0.0, 0.0],
+            [4.0, 4.0],
+            [0.0, 4.0],
+        ];
+        let draw = |buffer: &mut RenderBuffer| {
+            buffer.clear_color(WHITE);
+            buffer.tri_list(&DrawState::default(), &HALF_BLACK, |draw| {
+                draw(&square);
+                draw(&square);
+            });
+            buffer.get_pixel(1, 1)[0]
+        };
+        let mut buffer = RenderBuffer::new(4, 4);
+        assert!(buffer.overdraw_prevention());
+        let prevented = draw(&mut buffer);
+        buffer.set_overdraw_prevention(false);
+        let overdrawn = draw(&mut buffer);
+        buffer.set_overdraw_prevention(true);
+        assert!(
+            overdrawn < prevented - 40,
+            "{} and {}",
+            prevented,
+            overdrawn
+        );
+        assert_eq!(draw(&mut buffer), prevented);
+    }
+
+    #[test]
+    fn stencil_clips_and_clears() {
+        let mut buffer = white_buffer(8, 8);
+        buffer.clear_stencil(0);
+        Rectangle::new(BLACK).draw(
+            [0.0, 0.0, 4.0, 8.0],
+            &DrawState::new_clip(),
+            transform(),
+            &mut buffer,
+        );
+        // Clipping shapes only write to the stencil plane
+        assert!(buffer.pixels().all(|pixel| pixel.0 == [255; 4]));
+        Rectangle::new(BLACK).draw(
+            [0.0, 0.0, 8.0, 8.0],
+            &DrawState::new_inside(),
+            transform(),
+            &mut buffer,
+        );
+        for (x, _, pixel) in buffer.enumerate_pixels() {
+            let expected = if x < 4 { [0, 0, 0, 255] } else { [255; 4] };
+            assert_eq!(pixel.0, expected);
+        }
+        buffer.clear_color(WHITE);
+        buffer.clear_stencil(0);
+        Rectangle::new(BLACK).draw(
+            [0.0, 0.0, 8.0, 8.0],
+            &DrawState::new_inside(),
+            transform(),
+            &mut buffer,
+        );
+        assert!(buffer.pixels().all(|pixel| pixel.0 == [255; 4]));
+    }
+
+    #[test]
+    fn dirty_rect_tracks_drawing() {
+        let mut buffer = RenderBuffer::new(100, 100);
+        assert_eq!(buffer.take_dirty_rect(), Some([0, 0, 100, 100]));
+        assert_eq!(buffer.dirty_rect(), None);
+        rectangle(BLACK, [60.0, 10.0, 20.0, 5.0], transform(), &mut buffer);
+        assert_eq!(buffer.take_dirty_rect(), Some([60, 10, 20, 5]));
+        rectangle(BLACK, [5.0, 5.0, 2.0, 2.0], transform(), &mut buffer);
+        rectangle(BLACK, [90.0, 70.0, 3.0, 3.0], transform(), &mut buffer);
+        assert_eq!(buffer.take_dirty_rect(), Some([5, 5, 88, 68]));
+    }
+
+    #[test]
+    fn failed_transactions_roll_back() {
+        let mut buffer = white_buffer(100, 100);
+        buffer.take_dirty_rect();
+        let before = buffer.as_raw().clone();
+        let result: Result<(), ()> = buffer.transaction(|buffer| {
+            rectangle(BLACK, [50.0, 50.0, 30.0, 30.0], transform(), buffer);
+            buffer.set_overdraw_prevention(false);
+            Err(())
+        });
+        assert_eq!(result, Err(()));
+        assert!(buffer.as_raw() == &before);
+        assert!(buffer.overdraw_prevention());
+        assert_eq!(buffer.dirty_rect(), None);
+        let result: Result<(), ()> = buffer.transaction(|buffer| {
+            rectangle(BLACK, [50.0, 50.0, 30.0, 30.0], transform(), buffer);
+            Ok(())
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!(buffer.get_pixel(60, 60).0, [0, 0, 0, 255]);
+        assert_eq!(buffer.dirty_rect(), Some([50, 50, 30, 30]));
+    }
+
+    #[test]
+    fn deep_and_gray_buffers_match_rgba8() {
+        let mut rgba8 = RenderBuffer::new(200, 200);
+        draw_shapes(&mut rgba8);
+        let mut rgba16 = RenderBuffer::<Rgba<u16>>::blank(200, 200);
+        draw_shapes(&mut rgba16);
+        let mut luma = RenderBuffer::<Luma<u8>>::blank(200, 200);
+        draw_shapes(&mut luma);
+        let close = |a: f32, b: f32| (a - b).abs() <= 2.0 / 255.0;
+        for y in 0..200 {
+            for x in 0..200 {
+                let expected = rgba8.pixel(x, y);
+                let deep = rgba16.pixel(x, y);
+                assert!(
+                    (0..4).all(|i| close(deep[i], expected[i])),
+                    "pixel ({}, {}): {:?} and {:?}",
+                    x,
+                    y,
+                    deep,
+                    expected
+                );
+                let gray = luma.pixel(x, y);
+                assert!(
+                    close(gray[0], luminance(expected)),
+                    "pixel ({}, {}): {:?} and {:?}",
+                    x,
+                    y,
+                    gray,
+                    expected
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use graphics::Context;
+use image::imageops;
+
+use crate::RenderBuffer;
+
+/**
+A registry of named rectangular regions of a buffer.
+
+Regions are given in pixels as `[x, y, width, height]`. Drawing into a region
+with `draw_into` translates drawing so that the top-left corner of the region
+is the origin, and clips anything outside of it. This keeps template-driven
+image generation maintainable, since layouts can be changed in one place.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Regions {
+    regions: HashMap<String, [u32; 4]>,
+}
+
+impl Regions {
+    /// Create a new empty `Regions`.
+    pub fn new() -> Self {
+        Regions::default()
+    }
+    /// Add a region, returning the rectangle previously registered with the same name.
+    pub fn insert<S: Into<String>>(&mut self, name: S, rect: [u32; 4]) -> Option<[u32; 4]> {
+        self.regions.insert(name.into(), rect)
+    }
+    /// Add a region.
+    pub fn with<S: Into<String>>(mut self, name: S, rect: [u32; 4]) -> Self {
+        self.insert(name, rect);
+        self
+    }
+    /// Remove a region, returning its rectangle.
+    pub fn remove(&mut self, name: &str) -> Option<[u32; 4]> {
+        self.regions.remove(name)
+    }
+    /// Get the rectangle of a region.
+    pub fn get(&self, name: &str) -> Option<[u32; 4]> {
+        self.regions.get(name).copied()
+    }
+    /// Get the names of all regions, in no particular order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.regions.keys().map(String::as_str)
+    }
+    /**
+    Draw into a named region of a buffer.
+
+    The drawing function is given a `Context` and a buffer the size of the
+    region, with the origin at the region's top-left corner. Anything drawn
+    outside of the region is clipped. The buffer's rendering settings are used
+    for the region.
+
+    Returns `None` without drawing if there is no region with the given name.
+    */
+    pub fn draw_into<F, R>(&self, buffer: &mut RenderBuffer, name: &str, draw: F) -> Option<R>
+    where
+        F: FnOnce(Context, &mut RenderBuffer) -> R,
+    {
+        let [x, y, width, height] = self.get(name)?;
+        let mut region = buffer.crop(x, y, width, height);
+        let result = draw(Context::new(), &mut region);
+        buffer.mark_dirty([x, y, region.width(), region.height()]);
+        imageops::replace(&mut buffer.inner, &region.inner, x, y);
+        buffer.pixels_written += region.pixels_written;
+        Some(result)
+    }
+}
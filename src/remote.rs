@@ -0,0 +1,113 @@
+use std::{
+    io::{self, Cursor, Read},
+    time::Duration,
+};
+
+use image::{io::Reader, ImageResult};
+
+use crate::{load::check_decoded_size, RenderBuffer};
+
+/// The default maximum number of bytes downloaded for a remote image
+pub const DEFAULT_DOWNLOAD_LIMIT: usize = 16 * 1024 * 1024;
+
+/// The maximum number of bytes of RGBA8 pixels a downloaded image may decode to
+///
+/// Small compressed files can decode to huge images, so this is checked
+/// against the image's header before it is decoded.
+pub const MAX_DOWNLOAD_DECODED_BYTES: usize = 256 * 1024 * 1024;
+
+/// How long a download may take before it fails
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+impl RenderBuffer {
+    /**
+    Download an image from an `http` or `https` URL and decode it.
+
+    Downloads larger than `DEFAULT_DOWNLOAD_LIMIT` bytes, or that would decode
+    to more than `MAX_DOWNLOAD_DECODED_BYTES` bytes, fail. Failed requests are
+    returned as `ImageError::IoError`.
+    */
+    pub fn fetch(url: &str) -> ImageResult<RenderBuffer> {
+        RenderBuffer::fetch_with_limit(url, DEFAULT_DOWNLOAD_LIMIT)
+    }
+    /**
+    Download an image from an `http` or `https` URL and decode it, failing if
+    the download is larger than `limit` bytes.
+
+    The limit is checked against the `Content-Length` header before anything is
+    downloaded, and again while reading, so a server cannot make the renderer
+    buffer an unbounded amount of data. Images that would decode to more than
+    `MAX_DOWNLOAD_DECODED_BYTES` bytes fail before they are decoded.
+    */
+    pub fn fetch_with_limit(url: &str, limit: usize) -> ImageResult<RenderBuffer> {
+        decode_download(&download(url, limit, None)?, MAX_DOWNLOAD_DECODED_BYTES)
+    }
+}
+
+/// Get the URL an asset path holds, if it is an `http` or `https` URL
+#[cfg(feature = "scene")]
+pub(crate) fn url(path: &std::path::Path) -> Option<&str> {
+    path.to_str()
+        .filter(|path| path.starts_with("http://") || path.starts_with("https://"))
+}
+
+/// Decode a downloaded image, failing with `ImageError::Limits` before
+/// decoding if its pixels would take more than `max_bytes`
+pub(crate) fn decode_download(bytes: &[u8], max_bytes: usize) -> ImageResult<RenderBuffer> {
+    check_decoded_size(Reader::new(Cursor::new(bytes)), max_bytes)?;
+    RenderBuffer::decode_from_bytes(bytes)
+}
+
+/// Download the body of a URL, failing if it is larger than the limit
+///
+/// If `allowed_hosts` is given, the request fails with `PermissionDenied`
+/// unless the URL's host is one of them, and redirects are not followed.
+pub(crate) fn download(
+    url: &str,
+    limit: usize,
+    allowed_hosts: Option<&[String]>,
+) -> io::Result<Vec<u8>> {
+    let too_large = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is larger than the limit of {} bytes", url, limit),
+        )
+    };
+    let mut agent = ureq::AgentBuilder::new().timeout(DOWNLOAD_TIMEOUT);
+    if allowed_hosts.is_some() {
+        agent = agent.redirects(0);
+    }
+    let request = agent.build().get(url);
+    if let Some(hosts) = allowed_hosts {
+        let host = request
+            .request_url()
+            .map_err(io::Error::other)?
+            .host()
+            .to_owned();
+        if !hosts
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(&host))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("downloading from {} is not allowed", host),
+            ));
+        }
+    }
+    let response = request.call().map_err(io::Error::other)?;
+    let length = response
+        .header("Content-Length")
+        .and_then(|length| length.parse::<usize>().ok());
+    if length.is_some_and(|length| length > limit) {
+        return Err(too_large());
+    }
+    let mut bytes = Vec::with_capacity(length.unwrap_or(0));
+    response
+        .into_reader()
+        .take(limit as u64 + 1)
+        .read_to_end(&mut bytes)?;
+    if bytes.len() > limit {
+        return Err(too_large());
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,658 @@
+use std::{borrow::Cow, collections::HashMap, sync::Arc};
+
+use graphics::{
+    character::CharacterCache, draw_state::DrawState, math::Matrix2d, types::Color, Graphics,
+    Image, Text, Transformed,
+};
+
+use crate::RenderBuffer;
+
+/// A piece of a `RichText`.
+#[derive(Debug, Clone)]
+pub enum Span {
+    /// Text in a color
+    Text {
+        /// The text
+        text: String,
+        /// The color
+        color: Color,
+    },
+    /// An image that flows with the text, scaled to the height of the font
+    Image(Arc<RenderBuffer>),
+}
+
+/// What a shortcode is replaced with.
+#[derive(Debug, Clone)]
+pub enum Shortcode {
+    /// Some text, such as an emoji
+    Text(String),
+    /// An inline image
+    Image(Arc<RenderBuffer>),
+}
+
+/**
+A set of `:name:` shortcodes and what they are replaced with.
+
+Shortcode names may contain ASCII letters, digits, `_`, `+` and `-`. Text
+between colons that is not a known shortcode is left alone.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct Shortcodes {
+    codes: HashMap<String, Shortcode>,
+}
+
+impl Shortcodes {
+    /// Create a new empty `Shortcodes`.
+    pub fn new() -> Self {
+        Shortcodes::default()
+    }
+    /**
+    Create a `Shortcodes` with some of the most common emoji shortcodes.
+
+    Many fonts do not have emoji glyphs, so these can be replaced with images
+    by inserting image shortcodes with the same names.
+    */
+    pub fn emoji() -> Self {
+        let mut shortcodes = Shortcodes::new();
+        for &(name, emoji) in EMOJI {
+            shortcodes.insert_text(name, emoji);
+        }
+        shortcodes
+    }
+    /// Add a shortcode that is replaced with text.
+    pub fn insert_text<N, S>(&mut self, name: N, text: S)
+    where
+        N: Into<String>,
+        S: Into<String>,
+    {
+        self.codes.insert(name.into(), Shortcode::Text(text.into()));
+    }
+    /// Add a shortcode that is replaced with an inline image.
+    pub fn insert_image<N: Into<String>>(&mut self, name: N, image: Arc<RenderBuffer>) {
+        self.codes.insert(name.into(), Shortcode::Image(image));
+    }
+    /// Get what a shortcode is replaced with.
+    pub fn get(&self, name: &str) -> Option<&Shortcode> {
+        self.codes.get(name)
+    }
+    /// Split text into spans, replacing every known shortcode.
+    pub fn expand(&self, text: &str, color: Color) -> Vec<Span> {
+        let mut spans = Vec::new();
+        let mut literal = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find(':') {
+            let after = &rest[start + 1..];
+            let shortcode = after
+                .find(':')
+                .map(|end| &after[..end])
+                .filter(|name| is_shortcode_name(name))
+                .and_then(|name| Some((name, self.codes.get(name)?)));
+            let (name, shortcode) = match shortcode {
+                Some(found) => found,
+                None => {
+                    literal.push_str(&rest[..=start]);
+                    rest = after;
+                    continue;
+                }
+            };
+            literal.push_str(&rest[..start]);
+            match shortcode {
+                Shortcode::Text(text) => literal.push_str(text),
+                Shortcode::Image(image) => {
+                    push_text(&mut spans, &mut literal, color);
+                    spans.push(Span::Image(Arc::clone(image)));
+                }
+            }
+            rest = &after[name.len() + 1..];
+        }
+        literal.push_str(rest);
+        push_text(&mut spans, &mut literal, color);
+        spans
+    }
+}
+
+fn is_shortcode_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '+' || c == '-')
+}
+
+/// Move collected text into a new span
+fn push_text(spans: &mut Vec<Span>, text: &mut String, color: Color) {
+    if !text.is_empty() {
+        spans.push(Span::Text {
+            text: std::mem::take(text),
+            color,
+        });
+    }
+}
+
+const EMOJI: &[(&str, &str)] = &[
+    ("smile", "\u{1F604}"),
+    ("grin", "\u{1F601}"),
+    ("joy", "\u{1F602}"),
+    ("wink", "\u{1F609}"),
+    ("heart_eyes", "\u{1F60D}"),
+    ("thinking", "\u{1F914}"),
+    ("cry", "\u{1F622}"),
+    ("sunglasses", "\u{1F60E}"),
+    ("heart", "\u{2764}\u{FE0F}"),
+    ("+1", "\u{1F44D}"),
+    ("thumbsup", "\u{1F44D}"),
+    ("-1", "\u{1F44E}"),
+    ("thumbsdown", "\u{1F44E}"),
+    ("clap", "\u{1F44F}"),
+    ("wave", "\u{1F44B}"),
+    ("pray", "\u{1F64F}"),
+    ("fire", "\u{1F525}"),
+    ("star", "\u{2B50}"),
+    ("sparkles", "\u{2728}"),
+    ("tada", "\u{1F389}"),
+    ("rocket", "\u{1F680}"),
+    ("eyes", "\u{1F440}"),
+    ("check", "\u{2714}\u{FE0F}"),
+    ("x", "\u{274C}"),
+    ("warning", "\u{26A0}\u{FE0F}"),
+];
+
+/**
+How a `RichText` cuts off text that does not fit in its maximum lines and width.
+
+Except with `Clip`, lines with words too long to wrap are also cut off at the
+maximum width, the same way as the last line.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Truncation {
+    /// Lines past the maximum are dropped, and long words may overflow
+    #[default]
+    Clip,
+    /// The end of the last line is replaced with an ellipsis
+    Ellipsis,
+    /**
+    The middle of the remaining text is replaced with an ellipsis, keeping its
+    end, which suits file names and URLs
+
+    This needs a maximum width, and is the same as `Ellipsis` without one.
+    */
+    MiddleEllipsis,
+    /// The last line is cut off at the maximum width and fades out before its end
+    FadeOut,
+}
+
+/**
+Text made of differently colored spans and inline images, wrapped into lines.
+
+Images are scaled to the height of the font and sit on the baseline, which
+makes this suitable for chat transcripts and badges. Words are wrapped at
+spaces when a maximum width is set, and `\n` starts a new line.
+
+For fixed-size templates, the number of lines can be limited, with the rest
+of the text cut off by a `Truncation`, and words too long for a line can be
+hyphenated.
+
+Text is drawn with the top of the first line at the origin of the transform,
+and the first baseline `font_size` below it.
+*/
+#[derive(Debug, Clone)]
+pub struct RichText {
+    spans: Vec<Span>,
+    font_size: u32,
+    line_height: f64,
+    max_width: Option<f64>,
+    max_lines: Option<usize>,
+    truncation: Truncation,
+    hyphenate: bool,
+}
+
+/// A word, space or image to be placed on a line
+#[derive(Debug, Clone)]
+enum Item<'a> {
+    Word(Cow<'a, str>, Color, f64),
+    Space(f64),
+    Image(&'a RenderBuffer, f64),
+    Break,
+}
+
+/// Items with their x positions
+type Line<'a> = Vec<(f64, Item<'a>)>;
+
+/// The fewest characters left on either side of a hyphen
+const MIN_HYPHENATED: usize = 2;
+
+/// How far before its end a faded line starts fading out, in font sizes
+const FADE_LENGTH: f64 = 2.0;
+
+impl RichText {
+    /// Create a new empty `RichText` with the given font size.
+    pub fn new(font_size: u32) -> Self {
+        RichText {
+            spans: Vec::new(),
+            font_size,
+            line_height: f64::from(font_size) * 1.25,
+            max_width: None,
+            max_lines: None,
+            truncation: Truncation::Clip,
+            hyphenate: false,
+        }
+    }
+    /// Add text in a color.
+    pub fn text<S: Into<String>>(mut self, text: S, color: Color) -> Self {
+        self.spans.push(Span::Text {
+            text: text.into(),
+            color,
+        });
+        self
+    }
+    /// Add text in a color, replacing its shortcodes.
+    pub fn text_with_shortcodes(
+        mut self,
+        text: &str,
+        color: Color,
+        shortcodes: &Shortcodes,
+    ) -> Self {
+        self.spans.extend(shortcodes.expand(text, color));
+        self
+    }
+    /// Add an inline image.
+    pub fn image(mut self, image: Arc<RenderBuffer>) -> Self {
+        self.spans.push(Span::Image(image));
+        self
+    }
+    /// Set the distance between baselines. The default is 1.25 times the font size.
+    pub fn line_height(mut self, line_height: f64) -> Self {
+        self.line_height = line_height;
+        self
+    }
+    /// Set the width that lines are wrapped at.
+    pub fn max_width(mut self, max_width: f64) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+    /// Set the maximum number of lines. Text past them is cut off by the `Truncation`.
+    pub fn max_lines(mut self, max_lines: usize) -> Self {
+        self.max_lines = Some(max_lines.max(1));
+        self
+    }
+    /// Set how text that does not fit is cut off. The default is `Truncation::Clip`.
+    pub fn truncation(mut self, truncation: Truncation) -> Self {
+        self.truncation = truncation;
+        self
+    }
+    /**
+    Set whether words too long for the rest of a line are hyphenated.
+
+    Words are split between letters wherever the first part fits, leaving at
+    least two letters on each side. No dictionary is used.
+    */
+    pub fn hyphenate(mut self, hyphenate: bool) -> Self {
+        self.hyphenate = hyphenate;
+        self
+    }
+    /// Get the spans.
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+    /// Get the width and height of the wrapped text.
+    pub fn measure<C: CharacterCache>(&self, glyphs: &mut C) -> Result<[f64; 2], C::Error> {
+        let (lines, _) = self.lines(glyphs)?;
+        let width = lines.iter().map(line_width).fold(0.0, f64::max);
+        Ok([width, lines.len() as f64 * self.line_height])
+    }
+    /// Draw the text.
+    pub fn draw<C, G>(&self, glyphs: &mut C, transform: Matrix2d, g: &mut G) -> Result<(), C::Error>
+    where
+        C: CharacterCache<Texture = RenderBuffer>,
+        G: Graphics<Texture = RenderBuffer>,
+    {
+        let draw_state = DrawState::default();
+        let size = f64::from(self.font_size);
+        let (lines, cut) = self.lines(glyphs)?;
+        for (i, (line, cut)) in lines.into_iter().zip(cut).enumerate() {
+            let baseline = size + i as f64 * self.line_height;
+            // Faded lines are already cut off at the maximum width
+            let fade_end = if cut && self.truncation == Truncation::FadeOut {
+                Some(line_width(&line))
+            } else {
+                None
+            };
+            let fade = |x: f64| {
+                fade_end.map_or(1.0, |end| {
+                    ((end - x) / (FADE_LENGTH * size)).clamp(0.0, 1.0)
+                })
+            };
+            for (x, item) in line {
+                match item {
+                    Item::Word(word, color, _) if fade_end.is_some() => {
+                        for (j, c) in word.char_indices() {
+                            let x = x + glyphs.width(self.font_size, &word[..j])?;
+                            let alpha = fade(x) as f32;
+                            if alpha <= 0.0 {
+                                break;
+                            }
+                            let color = [color[0], color[1], color[2], color[3] * alpha];
+                            Text::new_color(color, self.font_size).draw(
+                                &word[j..j + c.len_utf8()],
+                                glyphs,
+                                &draw_state,
+                                transform.trans(x, baseline),
+                                g,
+                            )?;
+                        }
+                    }
+                    Item::Word(word, color, _) => Text::new_color(color, self.font_size).draw(
+                        &word,
+                        glyphs,
+                        &draw_state,
+                        transform.trans(x, baseline),
+                        g,
+                    )?,
+                    Item::Image(image, width) => {
+                        let alpha = fade(x) as f32;
+                        if alpha > 0.0 {
+                            Image::new_color([1.0, 1.0, 1.0, alpha])
+                                .rect([x, baseline - size, width, size])
+                                .draw(image, &draw_state, transform, g)
+                        }
+                    }
+                    Item::Space(_) | Item::Break => {}
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Split the spans into items and measure them
+    fn items<C: CharacterCache>(&self, glyphs: &mut C) -> Result<Vec<Item<'_>>, C::Error> {
+        let size = f64::from(self.font_size);
+        let space = glyphs.width(self.font_size, " ")?;
+        let mut items = Vec::new();
+        for span in &self.spans {
+            match span {
+                Span::Text { text, color } => {
+                    for (i, line) in text.split('\n').enumerate() {
+                        if i > 0 {
+                            items.push(Item::Break);
+                        }
+                        for (j, word) in line.split(' ').enumerate() {
+                            if j > 0 {
+                                items.push(Item::Space(space));
+                            }
+                            if !word.is_empty() {
+                                let width = glyphs.width(self.font_size, word)?;
+                                items.push(Item::Word(word.into(), *color, width));
+                            }
+                        }
+                    }
+                }
+                Span::Image(image) => {
+                    let (width, height) = image.dimensions();
+                    let width = if height == 0 {
+                        0.0
+                    } else {
+                        size * f64::from(width) / f64::from(height)
+                    };
+                    items.push(Item::Image(image, width));
+                }
+            }
+        }
+        Ok(items)
+    }
+    /// Wrap the items into lines, and truncate them. Also returns which lines had text cut off.
+    fn lines<C: CharacterCache>(
+        &self,
+        glyphs: &mut C,
+    ) -> Result<(Vec<Line<'_>>, Vec<bool>), C::Error> {
+        let mut lines = self.wrap(glyphs)?;
+        let max_lines = self.max_lines.unwrap_or(usize::MAX);
+        let kept = lines.len().min(max_lines);
+        let overflows = |line: &Line| self.max_width.is_some_and(|max| line_width(line) > max);
+        let mut cut = vec![false; kept];
+        cut[kept - 1] = lines.len() > max_lines || overflows(&lines[kept - 1]);
+        if self.truncation == Truncation::Clip {
+            lines.truncate(kept);
+            return Ok((lines, cut));
+        }
+        // Lines too long to wrap, such as ones with a single long word
+        for (line, cut) in lines.iter_mut().zip(&mut cut).take(kept - 1) {
+            if overflows(line) {
+                let items: Vec<Item> = line.drain(..).map(|(_, item)| item).collect();
+                let len = items.len();
+                *line = self.truncate_line(glyphs, items, len)?;
+                *cut = true;
+            }
+        }
+        if !cut[kept - 1] {
+            return Ok((lines, cut));
+        }
+        // The last kept line and everything after it, joined with spaces
+        let space = glyphs.width(self.font_size, " ")?;
+        let last_len = lines[kept - 1].len();
+        let mut rest = Vec::new();
+        for line in lines.drain(kept - 1..) {
+            if !rest.is_empty() {
+                rest.push(Item::Space(space));
+            }
+            rest.extend(line.into_iter().map(|(_, item)| item));
+        }
+        let last = self.truncate_line(glyphs, rest, last_len)?;
+        lines.push(last);
+        Ok((lines, cut))
+    }
+    /**
+    Cut off items that do not fit on a line with the truncation, which must not be `Clip`.
+
+    Only the first `len` items are kept, unless the middle of the items is cut.
+    */
+    fn truncate_line<'a, C: CharacterCache>(
+        &self,
+        glyphs: &mut C,
+        mut items: Vec<Item<'a>>,
+        len: usize,
+    ) -> Result<Line<'a>, C::Error> {
+        let color = items
+            .iter()
+            .rev()
+            .find_map(|item| match item {
+                Item::Word(_, color, _) => Some(*color),
+                _ => None,
+            })
+            .unwrap_or([0.0, 0.0, 0.0, 1.0]);
+        let ellipsis = Item::Word(
+            "\u{2026}".into(),
+            color,
+            glyphs.width(self.font_size, "\u{2026}")?,
+        );
+        let max_width = self.max_width.unwrap_or(f64::INFINITY);
+        let budget = (max_width - item_width(&ellipsis)).max(0.0);
+        let line = match self.truncation {
+            Truncation::Clip => unreachable!(),
+            Truncation::MiddleEllipsis if self.max_width.is_some() => {
+                let (mut head, head_width) =
+                    self.take_fitting(glyphs, items.iter().cloned(), budget / 2.0, false)?;
+                let (mut tail, _) =
+                    self.take_fitting(glyphs, items.into_iter().rev(), budget - head_width, true)?;
+                trim_spaces(&mut head);
+                trim_spaces(&mut tail);
+                tail.reverse();
+                head.push(ellipsis);
+                head.extend(tail);
+                head
+            }
+            Truncation::Ellipsis | Truncation::MiddleEllipsis => {
+                items.truncate(len);
+                let (mut items, _) = self.take_fitting(glyphs, items.into_iter(), budget, false)?;
+                trim_spaces(&mut items);
+                items.push(ellipsis);
+                items
+            }
+            Truncation::FadeOut => {
+                items.truncate(len);
+                let (items, _) = self.take_fitting(glyphs, items.into_iter(), max_width, false)?;
+                items
+            }
+        };
+        let mut x = 0.0;
+        Ok(line
+            .into_iter()
+            .map(|item| {
+                let start = x;
+                x += item_width(&item);
+                (start, item)
+            })
+            .collect())
+    }
+    /// Place the items on lines, wrapping and hyphenating words
+    fn wrap<C: CharacterCache>(&self, glyphs: &mut C) -> Result<Vec<Line<'_>>, C::Error> {
+        let mut lines: Vec<Line> = vec![Vec::new()];
+        let mut x = 0.0;
+        let mut space = 0.0;
+        let mut pending = self.items(glyphs)?.into_iter().rev().collect::<Vec<_>>();
+        while let Some(item) = pending.pop() {
+            let width = match item {
+                Item::Break => {
+                    lines.push(Vec::new());
+                    x = 0.0;
+                    space = 0.0;
+                    continue;
+                }
+                Item::Space(width) => {
+                    space += width;
+                    continue;
+                }
+                ref item => item_width(item),
+            };
+            let line = lines.last_mut().expect("There is always a line");
+            // Spaces at the start of a line are dropped
+            let start = if line.is_empty() { 0.0 } else { x + space };
+            if let Some(max) = self.max_width.filter(|&max| start + width > max) {
+                if let (true, Item::Word(word, color, _)) = (self.hyphenate, &item) {
+                    if let Some((head, tail)) =
+                        self.split_word(glyphs, word, *color, max - start)?
+                    {
+                        if !line.is_empty() {
+                            line.push((x, Item::Space(space)));
+                        }
+                        x = start + item_width(&head);
+                        line.push((start, head));
+                        pending.push(tail);
+                        pending.push(Item::Break);
+                        continue;
+                    }
+                }
+                if !line.is_empty() {
+                    pending.push(item);
+                    pending.push(Item::Break);
+                    continue;
+                }
+            }
+            if !line.is_empty() {
+                line.push((x, Item::Space(space)));
+            }
+            line.push((start, item));
+            x = start + width;
+            space = 0.0;
+        }
+        Ok(lines)
+    }
+    /// Split a word with a hyphen so that the first part fits in the width
+    fn split_word<'a, C: CharacterCache>(
+        &self,
+        glyphs: &mut C,
+        word: &Cow<'a, str>,
+        color: Color,
+        width: f64,
+    ) -> Result<Option<(Item<'a>, Item<'a>)>, C::Error> {
+        let boundaries: Vec<usize> = word.char_indices().map(|(i, _)| i).collect();
+        if boundaries.len() < 2 * MIN_HYPHENATED {
+            return Ok(None);
+        }
+        for &at in boundaries[MIN_HYPHENATED..=boundaries.len() - MIN_HYPHENATED]
+            .iter()
+            .rev()
+        {
+            let (head, tail) = word.split_at(at);
+            let between_letters = head.chars().next_back().is_some_and(char::is_alphabetic)
+                && tail.chars().next().is_some_and(char::is_alphabetic);
+            if !between_letters {
+                continue;
+            }
+            let head = format!("{}-", head);
+            let head_width = glyphs.width(self.font_size, &head)?;
+            if head_width <= width {
+                let tail_width = glyphs.width(self.font_size, tail)?;
+                let tail = match *word {
+                    Cow::Borrowed(word) => Cow::Borrowed(&word[at..]),
+                    Cow::Owned(ref word) => Cow::Owned(word[at..].to_string()),
+                };
+                return Ok(Some((
+                    Item::Word(head.into(), color, head_width),
+                    Item::Word(tail, color, tail_width),
+                )));
+            }
+        }
+        Ok(None)
+    }
+    /**
+    Take items while they fit in the width, cutting the last word that does
+    not fit down to the characters that do. If `from_end` is set, the items
+    are taken from the end of the text, so they are returned in reverse, and
+    words are cut from the front.
+    */
+    fn take_fitting<'a, C, I>(
+        &self,
+        glyphs: &mut C,
+        items: I,
+        width: f64,
+        from_end: bool,
+    ) -> Result<(Vec<Item<'a>>, f64), C::Error>
+    where
+        C: CharacterCache,
+        I: Iterator<Item = Item<'a>>,
+    {
+        let mut taken = Vec::new();
+        let mut used = 0.0;
+        for item in items {
+            let item_width = item_width(&item);
+            if used + item_width <= width {
+                used += item_width;
+                taken.push(item);
+                continue;
+            }
+            if let Item::Word(word, color, _) = item {
+                let mut boundaries: Vec<usize> = word.char_indices().map(|(i, _)| i).collect();
+                if !from_end {
+                    boundaries.reverse();
+                }
+                for at in boundaries {
+                    let part = if from_end { &word[at..] } else { &word[..at] };
+                    let part_width = glyphs.width(self.font_size, part)?;
+                    if !part.is_empty() && used + part_width <= width {
+                        used += part_width;
+                        taken.push(Item::Word(part.to_string().into(), color, part_width));
+                        break;
+                    }
+                }
+            }
+            break;
+        }
+        Ok((taken, used))
+    }
+}
+
+/// Remove spaces from the end of a line
+fn trim_spaces(items: &mut Vec<Item>) {
+    while let Some(Item::Space(_)) = items.last() {
+        items.pop();
+    }
+}
+
+fn line_width(line: &Line) -> f64 {
+    line.last().map_or(0.0, |(x, item)| x + item_width(item))
+}
+
+fn item_width(item: &Item) -> f64 {
+    match item {
+        Item::Word(_, _, width) | Item::Space(width) | Item::Image(_, width) => *width,
+        Item::Break => 0.0,
+    }
+}
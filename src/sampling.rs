@@ -0,0 +1,123 @@
+use texture::{Filter, Wrap};
+
+use crate::RenderBuffer;
+
+/// How a texture is sampled when it is drawn scaled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TextureFilter {
+    /// Use the nearest texel, keeping pixel art crisp
+    #[default]
+    Nearest,
+    /// Interpolate between the nearest texels for smooth results
+    Linear,
+}
+
+impl From<Filter> for TextureFilter {
+    fn from(filter: Filter) -> Self {
+        match filter {
+            Filter::Nearest => TextureFilter::Nearest,
+            Filter::Linear => TextureFilter::Linear,
+        }
+    }
+}
+
+/// How texture coordinates outside of `[0, 1]` are mapped onto a texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WrapMode {
+    /// Use the nearest edge texel
+    #[default]
+    ClampToEdge,
+    /// Tile the texture
+    Repeat,
+    /// Tile the texture, mirroring every other tile
+    MirroredRepeat,
+}
+
+impl WrapMode {
+    /// Map a texel index onto a texture axis of the given size
+    pub(crate) fn apply(self, i: i64, size: u32) -> u32 {
+        let size = i64::from(size.max(1));
+        (match self {
+            WrapMode::ClampToEdge => i.clamp(0, size - 1),
+            WrapMode::Repeat => i.rem_euclid(size),
+            WrapMode::MirroredRepeat => {
+                let i = i.rem_euclid(size * 2);
+                if i < size {
+                    i
+                } else {
+                    size * 2 - 1 - i
+                }
+            }
+        }) as u32
+    }
+}
+
+impl From<Wrap> for WrapMode {
+    /// Borders are not supported, so `ClampToBorder` clamps to the edge
+    fn from(wrap: Wrap) -> Self {
+        match wrap {
+            Wrap::ClampToEdge | Wrap::ClampToBorder => WrapMode::ClampToEdge,
+            Wrap::Repeat => WrapMode::Repeat,
+            Wrap::MirroredRepeat => WrapMode::MirroredRepeat,
+        }
+    }
+}
+
+impl RenderBuffer {
+    /// Get the filter used when the buffer is drawn as a texture at a smaller size.
+    pub fn min_filter(&self) -> TextureFilter {
+        self.min_filter
+    }
+    /// Get the filter used when the buffer is drawn as a texture at a larger size.
+    pub fn mag_filter(&self) -> TextureFilter {
+        self.mag_filter
+    }
+    /**
+    Set the filter used when the buffer is drawn as a texture at a smaller size.
+
+    The default is `TextureFilter::Nearest`. Textures created through
+    `CreateTexture` use the filters of their `TextureSettings`.
+    */
+    pub fn set_min_filter(&mut self, filter: TextureFilter) {
+        self.min_filter = filter;
+    }
+    /**
+    Set the filter used when the buffer is drawn as a texture at a larger size.
+
+    The default is `TextureFilter::Nearest`. Textures created through
+    `CreateTexture` use the filters of their `TextureSettings`.
+    */
+    pub fn set_mag_filter(&mut self, filter: TextureFilter) {
+        self.mag_filter = filter;
+    }
+    /// Set the filter used when the buffer is drawn as a texture at any size.
+    pub fn set_filter(&mut self, filter: TextureFilter) {
+        self.set_min_filter(filter);
+        self.set_mag_filter(filter);
+    }
+    /// Set the filter used when the buffer is drawn as a texture at any size.
+    pub fn with_filter(mut self, filter: TextureFilter) -> Self {
+        self.set_filter(filter);
+        self
+    }
+    /// Get the wrap modes along the `u` and `v` texture axes.
+    pub fn wrap(&self) -> (WrapMode, WrapMode) {
+        (self.wrap_u, self.wrap_v)
+    }
+    /**
+    Set how texture coordinates outside of `[0, 1]` are mapped along the `u`
+    and `v` axes when the buffer is drawn as a texture.
+
+    The default is `WrapMode::ClampToEdge`. Textures created through
+    `CreateTexture` use the wrap modes of their `TextureSettings`.
+    */
+    pub fn set_wrap(&mut self, wrap_u: WrapMode, wrap_v: WrapMode) {
+        self.wrap_u = wrap_u;
+        self.wrap_v = wrap_v;
+    }
+    /// Set how texture coordinates outside of `[0, 1]` are mapped along both axes.
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.set_wrap(wrap, wrap);
+        self
+    }
+}
@@ -0,0 +1,130 @@
+use graphics::{draw_state::DrawState, types::Color, Graphics};
+
+use crate::RenderBuffer;
+
+/**
+A `Graphics` wrapper that scales everything drawn by a device pixel ratio.
+
+This lets code written against logical window coordinates render at full
+resolution into a larger buffer, such as for crisp HiDPI screenshots.
+Vertices and scissor rectangles are scaled, so transforms should stay in
+logical coordinates.
+*/
+#[derive(Debug)]
+pub struct ScaleAware<'a, G> {
+    graphics: &'a mut G,
+    scale: f32,
+}
+
+impl<'a, G: Graphics> ScaleAware<'a, G> {
+    /// Wrap a graphics backend so that all drawing is scaled by `scale`.
+    pub fn new(graphics: &'a mut G, scale: f64) -> Self {
+        ScaleAware {
+            graphics,
+            scale: scale as f32,
+        }
+    }
+    /// Get the device pixel ratio.
+    pub fn scale(&self) -> f64 {
+        f64::from(self.scale)
+    }
+    /// Get the wrapped graphics backend.
+    pub fn inner(&mut self) -> &mut G {
+        self.graphics
+    }
+}
+
+/// Scale the scissor rectangle of a draw state, growing it to whole pixels
+fn scale_draw_state(draw_state: &DrawState, scale: f32) -> DrawState {
+    let scale = f64::from(scale);
+    let scissor = draw_state.scissor.map(|[x, y, w, h]| {
+        let scale_down = |v: u32| (f64::from(v) * scale).floor() as u32;
+        let scale_up = |v: u32| (f64::from(v) * scale).ceil() as u32;
+        let (left, top) = (scale_down(x), scale_down(y));
+        let right = scale_up(x.saturating_add(w));
+        let bottom = scale_up(y.saturating_add(h));
+        [left, top, right - left, bottom - top]
+    });
+    DrawState {
+        scissor,
+        ..*draw_state
+    }
+}
+
+fn scale_vertices(vertices: &[[f32; 2]], scale: f32) -> Vec<[f32; 2]> {
+    vertices
+        .iter()
+        .map(|v| [v[0] * scale, v[1] * scale])
+        .collect()
+}
+
+impl RenderBuffer {
+    /// Create a new `RenderBuffer` large enough to hold the given logical size
+    /// at the given device pixel ratio.
+    pub fn new_scaled(logical_width: u32, logical_height: u32, scale: f64) -> RenderBuffer {
+        RenderBuffer::new(
+            (f64::from(logical_width) * scale).ceil() as u32,
+            (f64::from(logical_height) * scale).ceil() as u32,
+        )
+    }
+    /// Wrap the buffer so that drawing in logical coordinates is scaled by `scale`.
+    pub fn scale_aware(&mut self, scale: f64) -> ScaleAware<'_, RenderBuffer> {
+        ScaleAware::new(self, scale)
+    }
+}
+
+impl<'a, G: Graphics> Graphics for ScaleAware<'a, G> {
+    type Texture = G::Texture;
+    fn clear_color(&mut self, color: Color) {
+        self.graphics.clear_color(color);
+    }
+    fn clear_stencil(&mut self, value: u8) {
+        self.graphics.clear_stencil(value);
+    }
+    fn tri_list<F>(&mut self, draw_state: &DrawState, color: &[f32; 4], mut f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]])),
+    {
+        let scale = self.scale;
+        let draw_state = scale_draw_state(draw_state, scale);
+        self.graphics.tri_list(&draw_state, color, |draw| {
+            f(&mut |vertices| draw(&scale_vertices(vertices, scale)))
+        });
+    }
+    fn tri_list_c<F>(&mut self, draw_state: &DrawState, mut f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 4]])),
+    {
+        let scale = self.scale;
+        let draw_state = scale_draw_state(draw_state, scale);
+        self.graphics.tri_list_c(&draw_state, |draw| {
+            f(&mut |vertices, colors| draw(&scale_vertices(vertices, scale), colors))
+        });
+    }
+    fn tri_list_uv<F>(
+        &mut self,
+        draw_state: &DrawState,
+        color: &[f32; 4],
+        texture: &Self::Texture,
+        mut f: F,
+    ) where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]])),
+    {
+        let scale = self.scale;
+        let draw_state = scale_draw_state(draw_state, scale);
+        self.graphics
+            .tri_list_uv(&draw_state, color, texture, |draw| {
+                f(&mut |vertices, uvs| draw(&scale_vertices(vertices, scale), uvs))
+            });
+    }
+    fn tri_list_uv_c<F>(&mut self, draw_state: &DrawState, texture: &Self::Texture, mut f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]], &[[f32; 4]])),
+    {
+        let scale = self.scale;
+        let draw_state = scale_draw_state(draw_state, scale);
+        self.graphics.tri_list_uv_c(&draw_state, texture, |draw| {
+            f(&mut |vertices, uvs, colors| draw(&scale_vertices(vertices, scale), uvs, colors))
+        });
+    }
+}
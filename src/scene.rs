@@ -0,0 +1,1045 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    error, fmt, fs,
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use graphics::{
+    draw_state::DrawState, math::Matrix2d, types::Color, Ellipse, Graphics, Image, Line, Polygon,
+    Rectangle, Transformed,
+};
+use image::{
+    error::{LimitError, LimitErrorKind},
+    io::Reader as ImageReader,
+    ImageError,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    buffer_glyphs_from_bytes, load::check_decoded_size, BufferGlyphs, Error, FontError, Limit,
+    LimitedTarget, MemoryBudget, RenderBuffer, RenderLimits, IDENTITY,
+};
+#[cfg(feature = "http_assets")]
+use crate::{remote, DEFAULT_DOWNLOAD_LIMIT};
+
+/// An error that can occur when loading or rendering a `Scene`.
+#[derive(Debug)]
+pub enum SceneError {
+    /// A file could not be read
+    Io(PathBuf, io::Error),
+    /// The scene description could not be parsed
+    Parse(String),
+    /// The scene file extension is not `.json` or `.ron`
+    UnknownFormat(PathBuf),
+    /// An image could not be loaded
+    Image(PathBuf, ImageError),
+    /// A font could not be loaded
+    Font(PathBuf, FontError),
+    /// Text could not be rendered
+    Render(Error),
+    /// A template variable was used but not bound
+    UnboundVariable(String),
+    /// A template variable used in a path was bound to a value that could
+    /// leave the path's directory
+    InvalidPathVariable(String),
+    /// An image or font path is absolute or leaves the scene's directory
+    InvalidAssetPath(PathBuf),
+    /// A limit from `RenderLimits` was exceeded
+    LimitExceeded(Limit),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(path, error) => {
+                write!(f, "Unable to read {}: {}", path.display(), error)
+            }
+            SceneError::Parse(message) => write!(f, "Unable to parse scene: {}", message),
+            SceneError::UnknownFormat(path) => write!(
+                f,
+                "Unknown scene format for {}. Expected a .json or .ron file",
+                path.display()
+            ),
+            SceneError::Image(path, error) => {
+                write!(f, "Unable to load image {}: {}", path.display(), error)
+            }
+            SceneError::Font(path, error) => {
+                write!(f, "Unable to load font {}: {}", path.display(), error)
+            }
+            SceneError::Render(error) => write!(f, "Unable to render text: {}", error),
+            SceneError::UnboundVariable(name) => {
+                write!(f, "No value was given for the variable {{{{{}}}}}", name)
+            }
+            SceneError::InvalidPathVariable(name) => write!(
+                f,
+                "The value of the variable {{{{{}}}}} cannot be used in a path",
+                name
+            ),
+            SceneError::InvalidAssetPath(path) => write!(
+                f,
+                "The asset path {} is outside of the scene's directory",
+                path.display()
+            ),
+            SceneError::LimitExceeded(limit) => write!(f, "Render limit exceeded: {}", limit),
+        }
+    }
+}
+
+impl error::Error for SceneError {}
+
+/**
+A transformation applied to an element of a `Scene`.
+
+The element is scaled, then rotated, then translated.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneTransform {
+    /// The offset in pixels
+    pub translate: [f64; 2],
+    /// The clockwise rotation in degrees
+    pub rotate: f64,
+    /// The scale factor on each axis
+    pub scale: [f64; 2],
+}
+
+impl Default for SceneTransform {
+    fn default() -> Self {
+        SceneTransform {
+            translate: [0.0; 2],
+            rotate: 0.0,
+            scale: [1.0; 2],
+        }
+    }
+}
+
+impl SceneTransform {
+    /// Apply the transformation to a matrix.
+    pub fn apply(&self, matrix: Matrix2d) -> Matrix2d {
+        matrix
+            .trans(self.translate[0], self.translate[1])
+            .rot_deg(self.rotate)
+            .scale(self.scale[0], self.scale[1])
+    }
+}
+
+fn default_line_width() -> f64 {
+    1.0
+}
+
+fn default_font_size() -> u32 {
+    16
+}
+
+fn white() -> Color {
+    [1.0; 4]
+}
+
+/// An element of a `Scene`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SceneElement {
+    /// A rectangle, optionally with rounded corners
+    Rectangle {
+        /// The rectangle as `[x, y, width, height]`
+        rect: [f64; 4],
+        /// The fill color
+        color: Color,
+        /// The corner radius
+        #[serde(default)]
+        radius: f64,
+        /// The transformation
+        #[serde(default)]
+        transform: SceneTransform,
+    },
+    /// An ellipse that fills a rectangle
+    Ellipse {
+        /// The bounding rectangle as `[x, y, width, height]`
+        rect: [f64; 4],
+        /// The fill color
+        color: Color,
+        /// The transformation
+        #[serde(default)]
+        transform: SceneTransform,
+    },
+    /// A line segment with round ends
+    Line {
+        /// The end points as `[x1, y1, x2, y2]`
+        line: [f64; 4],
+        /// The color
+        color: Color,
+        /// The width
+        #[serde(default = "default_line_width")]
+        width: f64,
+        /// The transformation
+        #[serde(default)]
+        transform: SceneTransform,
+    },
+    /// A filled convex polygon
+    Polygon {
+        /// The corners
+        points: Vec<[f64; 2]>,
+        /// The fill color
+        color: Color,
+        /// The transformation
+        #[serde(default)]
+        transform: SceneTransform,
+    },
+    /// An image loaded from a file
+    Image {
+        /// The path to the image, relative to the scene file, or an `http` or
+        /// `https` URL if the `http_assets` feature is enabled and the
+        /// `AssetCache` allows its host
+        path: PathBuf,
+        /// The rectangle to stretch the image to, as `[x, y, width, height]`
+        #[serde(default)]
+        rect: Option<[f64; 4]>,
+        /// The color the image is multiplied by
+        #[serde(default = "white")]
+        color: Color,
+        /// The transformation
+        #[serde(default)]
+        transform: SceneTransform,
+    },
+    /// A line of text, with the baseline starting at the origin
+    Text {
+        /// The text
+        text: String,
+        /// The path to a TrueType or OpenType font, relative to the scene file
+        font: PathBuf,
+        /// The font size
+        #[serde(default = "default_font_size")]
+        size: u32,
+        /// The color
+        color: Color,
+        /// The transformation
+        #[serde(default)]
+        transform: SceneTransform,
+    },
+    /// A group of elements that share a transformation
+    Group {
+        /// The elements
+        elements: Vec<SceneElement>,
+        /// The transformation
+        #[serde(default)]
+        transform: SceneTransform,
+    },
+}
+
+/**
+A declarative description of an image to render.
+
+Scenes can be loaded from JSON or RON, which lets non-Rust users drive
+the renderer from data files. Elements are drawn in order.
+*/
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scene {
+    /// The width of the rendered buffer
+    pub width: u32,
+    /// The height of the rendered buffer
+    pub height: u32,
+    /// The color the buffer is cleared with
+    #[serde(default)]
+    pub background: Option<Color>,
+    /// The elements
+    #[serde(default)]
+    pub elements: Vec<SceneElement>,
+}
+
+impl Scene {
+    /// Parse a scene from JSON.
+    pub fn from_json(source: &str) -> Result<Scene, SceneError> {
+        serde_json::from_str(source).map_err(|e| SceneError::Parse(e.to_string()))
+    }
+    /// Parse a scene from RON.
+    pub fn from_ron(source: &str) -> Result<Scene, SceneError> {
+        ron::from_str(source).map_err(|e| SceneError::Parse(e.to_string()))
+    }
+    /// Load a scene from a `.json` or `.ron` file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Scene, SceneError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|e| SceneError::Io(path.to_path_buf(), e))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Scene::from_json(&source),
+            Some("ron") => Scene::from_ron(&source),
+            _ => Err(SceneError::UnknownFormat(path.to_path_buf())),
+        }
+    }
+    /**
+    Get a copy of the scene with its template variables replaced.
+
+    Text, image paths and font paths may contain placeholders like `{{title}}`
+    or `{{ avatar_path }}`, which are replaced by the value bound to the name.
+    This lets one scene file be used as a template for many images. Returns
+    `SceneError::UnboundVariable` if a placeholder has no value.
+
+    Values bound into image and font paths may not contain path separators,
+    `..` or `:`, so untrusted values cannot point a scene at other files. Such
+    values return `SceneError::InvalidPathVariable`. If the `http_assets`
+    feature is enabled, a placeholder that is a whole path may be bound to an
+    `http` or `https` URL, which is only downloaded if the `AssetCache` used to
+    render the scene allows its host.
+    */
+    pub fn bind(&self, variables: &HashMap<String, String>) -> Result<Scene, SceneError> {
+        Ok(Scene {
+            elements: bind_elements(&self.elements, variables)?,
+            ..self.clone()
+        })
+    }
+    /// Get the names of all template variables used in the scene.
+    pub fn variables(&self) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        collect_variables(&self.elements, &mut names);
+        names
+    }
+    /**
+    Render the scene to a new `RenderBuffer`.
+
+    Image and font paths are resolved against `base_dir`. Paths that are
+    absolute or contain `..` return `SceneError::InvalidAssetPath`.
+    */
+    pub fn render<P: AsRef<Path>>(&self, base_dir: P) -> Result<RenderBuffer, SceneError> {
+        self.render_with_cache(base_dir, &AssetCache::new())
+    }
+    /**
+    Render the scene to a new `RenderBuffer`, loading images and fonts through
+    a cache that can be shared with other renders.
+
+    Image and font paths are resolved against `base_dir`. Paths that are
+    absolute or contain `..` return `SceneError::InvalidAssetPath`. Images
+    are only downloaded from hosts allowed by the cache.
+    */
+    pub fn render_with_cache<P: AsRef<Path>>(
+        &self,
+        base_dir: P,
+        cache: &AssetCache,
+    ) -> Result<RenderBuffer, SceneError> {
+        self.render_with_limits(base_dir, cache, &RenderLimits::default())
+    }
+    /**
+    Render the scene to a new `RenderBuffer`, loading images and fonts through
+    a cache and enforcing some limits.
+
+    The size and text of the scene are checked before anything is loaded, the
+    size of each image is read from its header before it is decoded, and font
+    files are read no further than the font limit.
+    Rendering stops as soon as a limit is exceeded. The time budget counts
+    loading assets as well as drawing. Use this to render untrusted scenes.
+    */
+    pub fn render_with_limits<P: AsRef<Path>>(
+        &self,
+        base_dir: P,
+        cache: &AssetCache,
+        limits: &RenderLimits,
+    ) -> Result<RenderBuffer, SceneError> {
+        let started = Instant::now();
+        let base_dir = base_dir.as_ref();
+        limits
+            .check_dimensions(self.width, self.height)
+            .map_err(limit_error)?;
+        check_text(&self.elements, limits)?;
+        // Load every image and font up front
+        let mut loader = Loader {
+            base_dir,
+            cache,
+            images: HashMap::new(),
+            fonts: HashMap::new(),
+            texture_bytes: limits.max_texture_bytes,
+            font_bytes: limits.max_font_bytes,
+            deadline: limits.time_budget.map(|budget| (started, budget)),
+        };
+        loader.load(&self.elements)?;
+        let Loader {
+            images,
+            fonts: font_data,
+            ..
+        } = loader;
+        let mut fonts = HashMap::new();
+        for (path, data) in &font_data {
+            let glyphs =
+                buffer_glyphs_from_bytes(data).map_err(|e| SceneError::Font(path.clone(), e))?;
+            fonts.insert(path.clone(), glyphs);
+        }
+        let mut buffer = RenderBuffer::new(self.width, self.height);
+        if let Some(background) = self.background {
+            buffer.clear_color(background);
+        }
+        let mut resources = Resources {
+            images: &images,
+            fonts: &mut fonts,
+        };
+        let mut target = LimitedTarget::new(&mut buffer, *limits).started_at(started);
+        draw_elements(&self.elements, IDENTITY, &mut resources, &mut target)?;
+        target.check().map_err(limit_error)?;
+        Ok(buffer)
+    }
+}
+
+impl RenderBuffer {
+    /// Load a scene from a `.json` or `.ron` file and render it.
+    ///
+    /// Image and font paths are resolved against the scene file's directory.
+    pub fn from_scene_file<P: AsRef<Path>>(path: P) -> Result<RenderBuffer, SceneError> {
+        let path = path.as_ref();
+        let scene = Scene::open(path)?;
+        scene.render(path.parent().unwrap_or_else(|| Path::new("")))
+    }
+}
+
+/// Call a function with the name of each `{{name}}` placeholder in a template,
+/// replacing the placeholder with the returned text
+fn replace_placeholders<F>(template: &str, mut replace: F) -> Result<String, SceneError>
+where
+    F: FnMut(&str) -> Result<String, SceneError>,
+{
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start + 2..].find("}}") {
+            Some(end) => start + 2 + end,
+            None => break,
+        };
+        output.push_str(&rest[..start]);
+        output.push_str(&replace(rest[start + 2..end].trim())?);
+        rest = &rest[end + 2..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+fn substitute(template: &str, variables: &HashMap<String, String>) -> Result<String, SceneError> {
+    replace_placeholders(template, |name| {
+        variables
+            .get(name)
+            .cloned()
+            .ok_or_else(|| SceneError::UnboundVariable(name.into()))
+    })
+}
+
+fn substitute_path(
+    path: &Path,
+    variables: &HashMap<String, String>,
+) -> Result<PathBuf, SceneError> {
+    // Paths that are not valid UTF-8 cannot contain placeholders
+    let template = match path.to_str() {
+        Some(template) => template,
+        None => return Ok(path.to_path_buf()),
+    };
+    // A placeholder that is the whole path may be bound to a URL, which is
+    // only downloaded if the `AssetCache` allows its host
+    #[cfg(feature = "http_assets")]
+    {
+        let whole = template
+            .strip_prefix("{{")
+            .and_then(|rest| rest.strip_suffix("}}"))
+            .filter(|name| !name.contains("}}"));
+        if let Some(value) = whole.and_then(|name| variables.get(name.trim())) {
+            if remote::url(Path::new(value)).is_some() {
+                return Ok(PathBuf::from(value));
+            }
+        }
+    }
+    replace_placeholders(template, |name| {
+        let value = variables
+            .get(name)
+            .ok_or_else(|| SceneError::UnboundVariable(name.into()))?;
+        if value.contains(['/', '\\', ':']) || value.contains("..") {
+            return Err(SceneError::InvalidPathVariable(name.into()));
+        }
+        Ok(value.clone())
+    })
+    .map(PathBuf::from)
+}
+
+fn bind_elements(
+    elements: &[SceneElement],
+    variables: &HashMap<String, String>,
+) -> Result<Vec<SceneElement>, SceneError> {
+    elements
+        .iter()
+        .map(|element| {
+            Ok(match element {
+                SceneElement::Image {
+                    path,
+                    rect,
+                    color,
+                    transform,
+                } => SceneElement::Image {
+                    path: substitute_path(path, variables)?,
+                    rect: *rect,
+                    color: *color,
+                    transform: *transform,
+                },
+                SceneElement::Text {
+                    text,
+                    font,
+                    size,
+                    color,
+                    transform,
+                } => SceneElement::Text {
+                    text: substitute(text, variables)?,
+                    font: substitute_path(font, variables)?,
+                    size: *size,
+                    color: *color,
+                    transform: *transform,
+                },
+                SceneElement::Group {
+                    elements,
+                    transform,
+                } => SceneElement::Group {
+                    elements: bind_elements(elements, variables)?,
+                    transform: *transform,
+                },
+                element => element.clone(),
+            })
+        })
+        .collect()
+}
+
+fn collect_variables(elements: &[SceneElement], names: &mut BTreeSet<String>) {
+    let collect = |template: &str, names: &mut BTreeSet<String>| {
+        let _ = replace_placeholders(template, |name| {
+            names.insert(name.into());
+            Ok(String::new())
+        });
+    };
+    for element in elements {
+        match element {
+            SceneElement::Image { path, .. } => collect(&path.to_string_lossy(), names),
+            SceneElement::Text { text, font, .. } => {
+                collect(text, names);
+                collect(&font.to_string_lossy(), names);
+            }
+            SceneElement::Group { elements, .. } => collect_variables(elements, names),
+            _ => {}
+        }
+    }
+}
+
+/**
+A thread-safe cache of the images and fonts loaded by scenes.
+
+Sharing a cache between renders avoids loading the same assets repeatedly.
+An optional limit bounds the total size of the cached data. Once the limit
+is reached, new assets are still loaded but are no longer cached. If the
+crate-wide `MemoryBudget` is exceeded, the cache is emptied.
+*/
+#[derive(Debug, Default)]
+pub struct AssetCache {
+    inner: Mutex<CacheInner>,
+    limit: Option<usize>,
+    #[cfg(feature = "http_assets")]
+    download_limit: Option<usize>,
+    #[cfg(feature = "http_assets")]
+    allowed_hosts: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct CacheInner {
+    images: HashMap<PathBuf, Arc<RenderBuffer>>,
+    fonts: HashMap<PathBuf, Arc<[u8]>>,
+    bytes: usize,
+}
+
+impl AssetCache {
+    /// Create a new empty `AssetCache` with no size limit.
+    pub fn new() -> Self {
+        AssetCache::default()
+    }
+    /// Create a new empty `AssetCache` that caches at most `limit` bytes of asset data.
+    pub fn with_limit(limit: usize) -> Self {
+        AssetCache {
+            limit: Some(limit),
+            ..AssetCache::default()
+        }
+    }
+    /**
+    Set the maximum number of bytes downloaded for each image loaded from a URL.
+
+    The default is `DEFAULT_DOWNLOAD_LIMIT`.
+    */
+    #[cfg(feature = "http_assets")]
+    pub fn with_download_limit(mut self, limit: usize) -> Self {
+        self.download_limit = Some(limit);
+        self
+    }
+    /**
+    Allow images to be downloaded from the given hosts, such as `"example.com"`.
+
+    Images are only downloaded from allowed hosts, so scenes cannot make
+    requests to arbitrary servers. Hosts are compared without regard to case,
+    subdomains are not included, and redirects are not followed.
+    */
+    #[cfg(feature = "http_assets")]
+    pub fn with_allowed_hosts<I>(mut self, hosts: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.allowed_hosts.extend(hosts.into_iter().map(Into::into));
+        self
+    }
+    /// Get the number of bytes of asset data in the cache.
+    pub fn bytes(&self) -> usize {
+        self.lock().bytes
+    }
+    /// Remove all assets from the cache.
+    pub fn clear(&self) {
+        *self.lock() = CacheInner::default();
+    }
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    /// Check if an asset fits in the cache, evicting everything if the `MemoryBudget` is exceeded
+    fn fits(&self, inner: &mut CacheInner, bytes: usize) -> bool {
+        if MemoryBudget::limit().is_some_and(|limit| MemoryBudget::used() > limit) {
+            *inner = CacheInner::default();
+        }
+        match self.limit {
+            Some(limit) => inner.bytes + bytes <= limit,
+            None => true,
+        }
+    }
+    /**
+    Get an image, loading it if it is not cached.
+
+    If the `http_assets` feature is enabled, `http` and `https` URLs are
+    downloaded if their host is allowed with `with_allowed_hosts`. Other URLs
+    fail with an `ImageError::IoError` of kind `PermissionDenied`.
+    */
+    pub fn image(&self, path: &Path) -> Result<Arc<RenderBuffer>, image::ImageError> {
+        self.image_with_limit(path, None)
+    }
+    /// Get an image, failing with `ImageError::Limits` before it is decoded if
+    /// its pixels would take more than `max_bytes`
+    fn image_with_limit(
+        &self,
+        path: &Path,
+        max_bytes: Option<usize>,
+    ) -> Result<Arc<RenderBuffer>, image::ImageError> {
+        if let Some(image) = self.lock().images.get(path) {
+            if max_bytes.is_some_and(|max| image.as_raw().len() > max) {
+                return Err(ImageError::Limits(LimitError::from_kind(
+                    LimitErrorKind::InsufficientMemory,
+                )));
+            }
+            return Ok(Arc::clone(image));
+        }
+        // Load without holding the lock so other threads are not blocked
+        let image = Arc::new(self.load_image(path, max_bytes)?);
+        let mut inner = self.lock();
+        let bytes = image.as_raw().len();
+        if self.fits(&mut inner, bytes) && !inner.images.contains_key(path) {
+            inner.images.insert(path.to_path_buf(), Arc::clone(&image));
+            inner.bytes += bytes;
+        }
+        Ok(image)
+    }
+    fn load_image(
+        &self,
+        path: &Path,
+        max_bytes: Option<usize>,
+    ) -> Result<RenderBuffer, image::ImageError> {
+        #[cfg(feature = "http_assets")]
+        {
+            if let Some(url) = remote::url(path) {
+                let limit = self.download_limit.unwrap_or(DEFAULT_DOWNLOAD_LIMIT);
+                let bytes = remote::download(url, limit, Some(&self.allowed_hosts))?;
+                let max = max_bytes.map_or(remote::MAX_DOWNLOAD_DECODED_BYTES, |max| {
+                    max.min(remote::MAX_DOWNLOAD_DECODED_BYTES)
+                });
+                return remote::decode_download(&bytes, max);
+            }
+        }
+        if let Some(max) = max_bytes {
+            check_decoded_size(ImageReader::open(path)?, max)?;
+        }
+        Ok(image::open(path)?.into())
+    }
+    /// Get the data of a font file, loading it if it is not cached.
+    pub fn font(&self, path: &Path) -> io::Result<Arc<[u8]>> {
+        self.font_with_limit(path, None)
+            .map(|font| font.expect("fonts without a limit always load"))
+    }
+    /// Get the data of a font file, or `None` without reading it if it is
+    /// larger than `max_bytes`
+    fn font_with_limit(
+        &self,
+        path: &Path,
+        max_bytes: Option<usize>,
+    ) -> io::Result<Option<Arc<[u8]>>> {
+        let too_large = |len: usize| max_bytes.is_some_and(|max| len > max);
+        if let Some(font) = self.lock().fonts.get(path) {
+            return Ok(Some(Arc::clone(font)).filter(|font| !too_large(font.len())));
+        }
+        let font: Arc<[u8]> = match max_bytes {
+            Some(max) => {
+                // Read one byte more than the limit in case the file grows
+                let mut data = Vec::new();
+                fs::File::open(path)?
+                    .take(max as u64 + 1)
+                    .read_to_end(&mut data)?;
+                data.into()
+            }
+            None => fs::read(path)?.into(),
+        };
+        if too_large(font.len()) {
+            return Ok(None);
+        }
+        let mut inner = self.lock();
+        if self.fits(&mut inner, font.len()) && !inner.fonts.contains_key(path) {
+            inner.fonts.insert(path.to_path_buf(), Arc::clone(&font));
+            inner.bytes += font.len();
+        }
+        Ok(Some(font))
+    }
+}
+
+struct Resources<'a, 'f> {
+    images: &'a HashMap<PathBuf, Arc<RenderBuffer>>,
+    fonts: &'a mut HashMap<PathBuf, BufferGlyphs<'f>>,
+}
+
+/// Resolve an asset path against the scene's directory, leaving URLs alone
+///
+/// Paths that are absolute or contain `..` are rejected, so a scene cannot
+/// read files outside of its directory.
+fn resolve(base_dir: &Path, path: &Path) -> Result<PathBuf, SceneError> {
+    #[cfg(feature = "http_assets")]
+    {
+        if remote::url(path).is_some() {
+            return Ok(path.to_path_buf());
+        }
+    }
+    if path
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(SceneError::InvalidAssetPath(path.to_path_buf()));
+    }
+    Ok(base_dir.join(path))
+}
+
+/// Loads the images and fonts of a scene, enforcing the limits that apply to loading
+struct Loader<'a> {
+    base_dir: &'a Path,
+    cache: &'a AssetCache,
+    images: HashMap<PathBuf, Arc<RenderBuffer>>,
+    fonts: HashMap<PathBuf, Arc<[u8]>>,
+    /// The number of texture bytes left, if limited
+    texture_bytes: Option<usize>,
+    /// The number of font bytes left, if limited
+    font_bytes: Option<usize>,
+    /// When rendering started and the time budget, if limited
+    deadline: Option<(Instant, Duration)>,
+}
+
+impl Loader<'_> {
+    fn load(&mut self, elements: &[SceneElement]) -> Result<(), SceneError> {
+        for element in elements {
+            if self
+                .deadline
+                .is_some_and(|(started, budget)| started.elapsed() > budget)
+            {
+                return Err(SceneError::LimitExceeded(Limit::Time));
+            }
+            match element {
+                SceneElement::Image { path, .. } if !self.images.contains_key(path) => {
+                    let image = self
+                        .cache
+                        .image_with_limit(&resolve(self.base_dir, path)?, self.texture_bytes)
+                        .map_err(|e| match e {
+                            ImageError::Limits(_) if self.texture_bytes.is_some() => {
+                                SceneError::LimitExceeded(Limit::TextureMemory)
+                            }
+                            e => SceneError::Image(path.clone(), e),
+                        })?;
+                    if let Some(left) = &mut self.texture_bytes {
+                        *left -= image.as_raw().len();
+                    }
+                    self.images.insert(path.clone(), image);
+                }
+                SceneElement::Text { font, .. } if !self.fonts.contains_key(font) => {
+                    let data = self
+                        .cache
+                        .font_with_limit(&resolve(self.base_dir, font)?, self.font_bytes)
+                        .map_err(|e| SceneError::Io(font.clone(), e))?
+                        .ok_or(SceneError::LimitExceeded(Limit::FontMemory))?;
+                    if let Some(left) = &mut self.font_bytes {
+                        *left -= data.len();
+                    }
+                    self.fonts.insert(font.clone(), data);
+                }
+                SceneElement::Group { elements, .. } => self.load(elements)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Convert an error from checking `RenderLimits`
+fn limit_error(error: Error) -> SceneError {
+    match error {
+        Error::LimitExceeded(limit) => SceneError::LimitExceeded(limit),
+        error => SceneError::Render(error),
+    }
+}
+
+fn check_text(elements: &[SceneElement], limits: &RenderLimits) -> Result<(), SceneError> {
+    for element in elements {
+        match element {
+            SceneElement::Text { text, .. } => limits.check_text(text).map_err(limit_error)?,
+            SceneElement::Group { elements, .. } => check_text(elements, limits)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn draw_elements(
+    elements: &[SceneElement],
+    parent: Matrix2d,
+    resources: &mut Resources,
+    buffer: &mut LimitedTarget,
+) -> Result<(), SceneError> {
+    let draw_state = DrawState::default();
+    for element in elements {
+        // Stop as soon as a limit is exceeded instead of skipping every draw call
+        if let Some(limit) = buffer.exceeded() {
+            return Err(SceneError::LimitExceeded(limit));
+        }
+        match element {
+            SceneElement::Rectangle {
+                rect,
+                color,
+                radius,
+                transform,
+            } => Rectangle::new_round(*color, *radius).draw(
+                *rect,
+                &draw_state,
+                transform.apply(parent),
+                buffer,
+            ),
+            SceneElement::Ellipse {
+                rect,
+                color,
+                transform,
+            } => Ellipse::new(*color).draw(*rect, &draw_state, transform.apply(parent), buffer),
+            SceneElement::Line {
+                line,
+                color,
+                width,
+                transform,
+            } => Line::new(*color, width / 2.0).draw(
+                *line,
+                &draw_state,
+                transform.apply(parent),
+                buffer,
+            ),
+            SceneElement::Polygon {
+                points,
+                color,
+                transform,
+            } => Polygon::new(*color).draw(points, &draw_state, transform.apply(parent), buffer),
+            SceneElement::Image {
+                path,
+                rect,
+                color,
+                transform,
+            } => {
+                let texture = &*resources.images[path];
+                let mut image = Image::new_color(*color);
+                if let Some(rect) = rect {
+                    image = image.rect(*rect);
+                }
+                image.draw(texture, &draw_state, transform.apply(parent), buffer);
+            }
+            SceneElement::Text {
+                text,
+                font,
+                size,
+                color,
+                transform,
+            } => {
+                if let Some(glyphs) = resources.fonts.get_mut(font) {
+                    graphics::text(*color, *size, text, glyphs, transform.apply(parent), buffer)
+                        .map_err(SceneError::Render)?;
+                }
+            }
+            SceneElement::Group {
+                elements,
+                transform,
+            } => draw_elements(elements, transform.apply(parent), resources, buffer)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create an empty directory for a test's assets
+    fn asset_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "graphics_buffer-scene-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn image_scene(path: &str) -> Scene {
+        Scene::from_json(&format!(
+            r#"{{ "width": 4, "height": 4, "elements": [{{ "type": "image", "path": {:?} }}] }}"#,
+            path
+        ))
+        .unwrap()
+    }
+
+    fn text_scene(font: &str) -> Scene {
+        Scene::from_json(&format!(
+            r#"{{ "width": 4, "height": 4, "elements": [
+                {{ "type": "text", "text": "a", "font": {:?}, "color": [0, 0, 0, 1] }}
+            ] }}"#,
+            font
+        ))
+        .unwrap()
+    }
+
+    fn variables(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|&(name, value)| (name.into(), value.into()))
+            .collect()
+    }
+
+    fn bound_image_path(path: &str, value: &str) -> Result<PathBuf, SceneError> {
+        let scene = image_scene(path).bind(&variables(&[("avatar", value)]))?;
+        match &scene.elements[0] {
+            SceneElement::Image { path, .. } => Ok(path.clone()),
+            element => panic!("expected an image, got {:?}", element),
+        }
+    }
+
+    #[test]
+    fn bind_replaces_variables() {
+        let scene = Scene::from_json(
+            r#"{ "width": 4, "height": 4, "elements": [
+                { "type": "image", "path": "avatars/{{avatar}}.png" },
+                { "type": "text", "text": "Hello, {{ name }}!", "font": "font.ttf",
+                  "color": [0, 0, 0, 1] }
+            ] }"#,
+        )
+        .unwrap();
+        let names: Vec<_> = scene.variables().into_iter().collect();
+        assert_eq!(names, ["avatar", "name"]);
+        let bound = scene
+            .bind(&variables(&[("avatar", "ferris"), ("name", "World")]))
+            .unwrap();
+        match &bound.elements[..] {
+            [SceneElement::Image { path, .. }, SceneElement::Text { text, .. }] => {
+                assert_eq!(path, Path::new("avatars/ferris.png"));
+                assert_eq!(text, "Hello, World!");
+            }
+            elements => panic!("unexpected elements {:?}", elements),
+        }
+        match scene.bind(&variables(&[("avatar", "ferris")])) {
+            Err(SceneError::UnboundVariable(name)) => assert_eq!(name, "name"),
+            result => panic!("expected an unbound variable, got {:?}", result.err()),
+        }
+    }
+
+    #[test]
+    fn bound_paths_cannot_traverse() {
+        for value in ["../secret", "..", "/etc/passwd", "a\\b", "C:secret"] {
+            match bound_image_path("{{avatar}}", value) {
+                Err(SceneError::InvalidPathVariable(name)) => assert_eq!(name, "avatar"),
+                result => panic!("expected {:?} to be rejected, got {:?}", value, result),
+            }
+        }
+    }
+
+    #[cfg(feature = "http_assets")]
+    #[test]
+    fn whole_path_placeholders_can_be_urls() {
+        let url = "https://example.com/avatar.png";
+        assert_eq!(bound_image_path("{{avatar}}", url).unwrap(), Path::new(url));
+        assert_eq!(
+            bound_image_path("{{ avatar }}", url).unwrap(),
+            Path::new(url)
+        );
+        // URLs cannot be bound into part of a path
+        match bound_image_path("avatars/{{avatar}}", url) {
+            Err(SceneError::InvalidPathVariable(_)) => {}
+            result => panic!("expected the URL to be rejected, got {:?}", result),
+        }
+        // Values that are not URLs are still checked
+        match bound_image_path("{{avatar}}", "../avatar.png") {
+            Err(SceneError::InvalidPathVariable(_)) => {}
+            result => panic!("expected the path to be rejected, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn asset_paths_outside_the_base_dir_are_rejected() {
+        let dir = asset_dir("outside");
+        let absolute = dir.join("image.png");
+        for scene in [
+            image_scene("../image.png"),
+            image_scene("images/../../image.png"),
+            image_scene(absolute.to_str().unwrap()),
+            text_scene("../font.ttf"),
+            text_scene(dir.join("font.ttf").to_str().unwrap()),
+        ] {
+            match scene.render(&dir) {
+                Err(SceneError::InvalidAssetPath(_)) => {}
+                result => panic!("expected InvalidAssetPath, got {:?}", result.err()),
+            }
+        }
+    }
+
+    #[test]
+    fn font_reads_are_capped() {
+        let dir = asset_dir("font_cap");
+        fs::write(dir.join("font.ttf"), [0; 100]).unwrap();
+        let limits = RenderLimits {
+            max_font_bytes: Some(10),
+            ..RenderLimits::default()
+        };
+        let cache = AssetCache::new();
+        match text_scene("font.ttf").render_with_limits(&dir, &cache, &limits) {
+            Err(SceneError::LimitExceeded(Limit::FontMemory)) => {}
+            result => panic!("expected the font limit, got {:?}", result.err()),
+        }
+        // The font is neither read in full nor cached
+        assert_eq!(cache.bytes(), 0);
+    }
+
+    #[cfg(feature = "http_assets")]
+    #[test]
+    fn remote_images_need_an_allowed_host() {
+        let dir = asset_dir("hosts");
+        let scene = image_scene("http://127.0.0.1:1/image.png");
+        let denied = |cache: &AssetCache| match scene.render_with_cache(&dir, cache) {
+            Err(SceneError::Image(_, ImageError::IoError(e))) => {
+                e.kind() == io::ErrorKind::PermissionDenied
+            }
+            result => panic!("expected an IO error, got {:?}", result.err()),
+        };
+        assert!(denied(&AssetCache::new()));
+        assert!(denied(
+            &AssetCache::new().with_allowed_hosts(["example.com"])
+        ));
+        // Allowed hosts are requested, which fails because nothing is listening
+        assert!(!denied(
+            &AssetCache::new().with_allowed_hosts(["127.0.0.1"])
+        ));
+    }
+}
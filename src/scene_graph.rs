@@ -0,0 +1,288 @@
+use std::fmt;
+
+use graphics::{math::Matrix2d, types::Color, Context};
+
+use crate::{BlendMode, RenderBuffer, IDENTITY};
+
+/// The ID of a node in a `SceneGraph`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+type DrawFn = Box<dyn Fn(Context, &mut RenderBuffer) + Send + Sync>;
+
+struct Node {
+    draw: DrawFn,
+    transform: Matrix2d,
+    visible: bool,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    /// The rasterization of the node's own drawing
+    own: Option<RenderBuffer>,
+    /// The rasterization of the node and its visible descendants
+    subtree: Option<RenderBuffer>,
+}
+
+impl fmt::Debug for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Node")
+            .field("transform", &self.transform)
+            .field("visible", &self.visible)
+            .field("parent", &self.parent)
+            .field("children", &self.children)
+            .field("own_cached", &self.own.is_some())
+            .field("subtree_cached", &self.subtree.is_some())
+            .finish()
+    }
+}
+
+/**
+A retained-mode tree of drawable nodes that only redraws what has changed.
+
+Each node has a drawing function, a transform relative to its parent, and a
+visibility flag. Rasterizations are cached, so `render` only calls the
+drawing functions of nodes that were changed since the last render and
+recomposites their ancestors. This makes small tweaks to a large
+scene cheap, such as in a preview tool that edits one element at a time.
+
+Each node caches its own drawing and its subtree as full-size layers, so
+memory use grows with the number of nodes. Siblings are drawn in the order
+they were added.
+*/
+#[derive(Debug)]
+pub struct SceneGraph {
+    nodes: Vec<Option<Node>>,
+    roots: Vec<NodeId>,
+    background: Color,
+    output: RenderBuffer,
+    stale: bool,
+    redrawn: usize,
+}
+
+impl SceneGraph {
+    /// Create a new empty `SceneGraph` that renders to the given size.
+    pub fn new(width: u32, height: u32) -> Self {
+        SceneGraph {
+            nodes: Vec::new(),
+            roots: Vec::new(),
+            background: [0.0; 4],
+            output: RenderBuffer::new(width, height),
+            stale: true,
+            redrawn: 0,
+        }
+    }
+    /// Set the color the scene is drawn over.
+    ///
+    /// The default is transparent black.
+    pub fn set_background(&mut self, color: Color) {
+        self.background = color;
+        self.stale = true;
+    }
+    /// Set the color the scene is drawn over.
+    pub fn with_background(mut self, color: Color) -> Self {
+        self.set_background(color);
+        self
+    }
+    /**
+    Add a node, returning its ID.
+
+    The node is added as the last child of `parent`, or as a top-level node if
+    `parent` is `None`. `draw` is given a `Context` whose transform includes the
+    transforms of the node and all of its ancestors.
+
+    # Panics
+
+    Panics if `parent` has been removed.
+    */
+    pub fn add<F>(&mut self, parent: Option<NodeId>, draw: F) -> NodeId
+    where
+        F: Fn(Context, &mut RenderBuffer) + Send + Sync + 'static,
+    {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Some(Node {
+            draw: Box::new(draw),
+            transform: IDENTITY,
+            visible: true,
+            parent,
+            children: Vec::new(),
+            own: None,
+            subtree: None,
+        }));
+        match parent {
+            Some(parent) => {
+                self.node_mut(parent).children.push(id);
+                self.invalidate(parent);
+            }
+            None => {
+                self.roots.push(id);
+                self.stale = true;
+            }
+        }
+        id
+    }
+    /// Remove a node and all of its descendants.
+    ///
+    /// Does nothing if the node has already been removed.
+    pub fn remove(&mut self, id: NodeId) {
+        let node = match self.nodes.get_mut(id.0).and_then(Option::take) {
+            Some(node) => node,
+            None => return,
+        };
+        match node.parent {
+            Some(parent) => {
+                self.node_mut(parent).children.retain(|&child| child != id);
+                self.invalidate(parent);
+            }
+            None => {
+                self.roots.retain(|&root| root != id);
+                self.stale = true;
+            }
+        }
+        let mut removed = node.children;
+        while let Some(child) = removed.pop() {
+            if let Some(node) = self.nodes[child.0].take() {
+                removed.extend(node.children);
+            }
+        }
+    }
+    /// Check if a node has not been removed.
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.nodes.get(id.0).is_some_and(Option::is_some)
+    }
+    /// Get the parent of a node, or `None` if it is a top-level node.
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).parent
+    }
+    /// Get the children of a node, in drawing order.
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.node(id).children
+    }
+    /// Replace the drawing function of a node.
+    pub fn set_draw<F>(&mut self, id: NodeId, draw: F)
+    where
+        F: Fn(Context, &mut RenderBuffer) + Send + Sync + 'static,
+    {
+        self.node_mut(id).draw = Box::new(draw);
+        self.mark_dirty(id);
+    }
+    /// Get the transform of a node relative to its parent.
+    pub fn transform(&self, id: NodeId) -> Matrix2d {
+        self.node(id).transform
+    }
+    /// Set the transform of a node relative to its parent.
+    ///
+    /// This redraws the node and all of its descendants on the next render.
+    pub fn set_transform(&mut self, id: NodeId, transform: Matrix2d) {
+        self.node_mut(id).transform = transform;
+        let mut nodes = vec![id];
+        while let Some(id) = nodes.pop() {
+            let node = self.node_mut(id);
+            node.own = None;
+            node.subtree = None;
+            nodes.extend(node.children.iter().copied());
+        }
+        self.invalidate(id);
+    }
+    /// Check if a node is visible.
+    pub fn is_visible(&self, id: NodeId) -> bool {
+        self.node(id).visible
+    }
+    /// Show or hide a node and all of its descendants.
+    ///
+    /// Hiding a node keeps its cached rasterization, so showing it again is cheap.
+    pub fn set_visible(&mut self, id: NodeId, visible: bool) {
+        if self.node(id).visible == visible {
+            return;
+        }
+        self.node_mut(id).visible = visible;
+        match self.node(id).parent {
+            Some(parent) => self.invalidate(parent),
+            None => self.stale = true,
+        }
+    }
+    /// Mark a node to be redrawn on the next render, such as when state read by
+    /// its drawing function has changed.
+    pub fn mark_dirty(&mut self, id: NodeId) {
+        self.node_mut(id).own = None;
+        self.invalidate(id);
+    }
+    /// Get the number of nodes whose drawing functions were called by the last render.
+    pub fn redrawn_nodes(&self) -> usize {
+        self.redrawn
+    }
+    /**
+    Render the scene, redrawing only the nodes that have changed.
+
+    Returns the rendered buffer, which is reused by later renders.
+    */
+    pub fn render(&mut self) -> &RenderBuffer {
+        self.redrawn = 0;
+        if !self.stale {
+            return &self.output;
+        }
+        let roots = self.roots.clone();
+        // Nodes are drawn at the size of the output, which is taken out while compositing
+        let size = self.output.dimensions();
+        let mut output = std::mem::replace(&mut self.output, RenderBuffer::new(0, 0));
+        output.clear(self.background);
+        for root in roots {
+            if self.node(root).visible {
+                self.update_cache(root, IDENTITY, size);
+                let cache = self.node(root).subtree.as_ref().unwrap();
+                output.composite(cache, 1.0, BlendMode::Normal);
+            }
+        }
+        self.output = output;
+        self.stale = false;
+        &self.output
+    }
+    /// Get the last rendered buffer without rendering changes.
+    pub fn output(&self) -> &RenderBuffer {
+        &self.output
+    }
+    /// Rebuild the cache of a subtree if it is missing, only redrawing nodes without a cached drawing
+    fn update_cache(
+        &mut self,
+        id: NodeId,
+        parent_transform: Matrix2d,
+        (width, height): (u32, u32),
+    ) {
+        if self.node(id).subtree.is_some() {
+            return;
+        }
+        let transform = graphics::math::multiply(parent_transform, self.node(id).transform);
+        if self.node(id).own.is_none() {
+            let mut own = RenderBuffer::new(width, height);
+            let mut context = Context::new();
+            context.transform = transform;
+            (self.node(id).draw)(context, &mut own);
+            self.node_mut(id).own = Some(own);
+            self.redrawn += 1;
+        }
+        let mut layer = self.node(id).own.clone().unwrap();
+        let children = self.node(id).children.clone();
+        for child in children {
+            if self.node(child).visible {
+                self.update_cache(child, transform, (width, height));
+                let cache = self.node(child).subtree.as_ref().unwrap();
+                layer.composite(cache, 1.0, BlendMode::Normal);
+            }
+        }
+        self.node_mut(id).subtree = Some(layer);
+    }
+    /// Drop the cached subtrees of a node and its ancestors
+    fn invalidate(&mut self, id: NodeId) {
+        let mut current = Some(id);
+        while let Some(id) = current {
+            let node = self.node_mut(id);
+            node.subtree = None;
+            current = node.parent;
+        }
+        self.stale = true;
+    }
+    fn node(&self, id: NodeId) -> &Node {
+        self.nodes[id.0].as_ref().expect("Node has been removed")
+    }
+    fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        self.nodes[id.0].as_mut().expect("Node has been removed")
+    }
+}
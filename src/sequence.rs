@@ -0,0 +1,120 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use image::{ImageResult, RgbaImage};
+
+use crate::{Animation, RenderBuffer};
+
+/**
+Saves a sequence of frames as PNG files, skipping frames that are the same as
+the one before them.
+
+Each saved frame is written to `frame_NNNNN.png` in the directory, numbered by
+the first frame it was used for. `finish` writes a `manifest.json` that maps
+every frame, saved or not, to the file holding its contents:
+
+```json
+{"delay_ms":40,"frames":["frame_00000.png","frame_00000.png","frame_00002.png"]}
+```
+
+This greatly reduces disk writes when recording mostly static sequences.
+*/
+#[derive(Debug)]
+pub struct DeltaPngWriter {
+    dir: PathBuf,
+    delay_ms: u32,
+    /// The hash and pixels of the last saved frame
+    previous: Option<(u64, RgbaImage)>,
+    frames: Vec<String>,
+}
+
+impl DeltaPngWriter {
+    /// Create a new `DeltaPngWriter` that saves into a directory, creating it if needed.
+    pub fn new<P: AsRef<Path>>(dir: P, delay_ms: u32) -> ImageResult<DeltaPngWriter> {
+        fs::create_dir_all(&dir)?;
+        Ok(DeltaPngWriter {
+            dir: dir.as_ref().to_path_buf(),
+            delay_ms,
+            previous: None,
+            frames: Vec::new(),
+        })
+    }
+    /**
+    Add a frame to the sequence.
+
+    The frame is only saved if its contents differ from the previous frame.
+    Returns whether it was saved. A copy of the last saved frame is kept to
+    compare against, so frames whose hashes collide are never mistaken for
+    each other.
+    */
+    pub fn write_frame(&mut self, frame: &RenderBuffer) -> ImageResult<bool> {
+        let pixels = frame.straight_pixels();
+        let mut hasher = DefaultHasher::new();
+        pixels.dimensions().hash(&mut hasher);
+        pixels.as_raw().hash(&mut hasher);
+        let hash = hasher.finish();
+        let file = match (&self.previous, self.frames.last()) {
+            (Some((previous, previous_pixels)), Some(file))
+                if *previous == hash && *previous_pixels == *pixels =>
+            {
+                let file = file.clone();
+                self.frames.push(file);
+                return Ok(false);
+            }
+            _ => format!("frame_{:05}.png", self.frames.len()),
+        };
+        frame.save(self.dir.join(&file))?;
+        self.previous = Some((hash, pixels.into_owned()));
+        self.frames.push(file);
+        Ok(true)
+    }
+    /// Get the number of frames added, including ones that were not saved.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+    /// Check if no frames have been added.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+    /// Get the file name holding the contents of each frame added so far.
+    pub fn frame_files(&self) -> &[String] {
+        &self.frames
+    }
+    /// Write the manifest mapping frames to files, and return its path.
+    pub fn finish(self) -> ImageResult<PathBuf> {
+        let frames: Vec<String> = self
+            .frames
+            .iter()
+            .map(|file| format!("\"{}\"", file))
+            .collect();
+        let manifest = format!(
+            "{{\"delay_ms\":{},\"frames\":[{}]}}",
+            self.delay_ms,
+            frames.join(",")
+        );
+        let path = self.dir.join("manifest.json");
+        fs::write(&path, manifest)?;
+        Ok(path)
+    }
+}
+
+impl Animation {
+    /**
+    Save the animation as a directory of PNG files, skipping frames that are
+    the same as the one before them.
+
+    See `DeltaPngWriter` for the layout of the directory. Returns the path of
+    the manifest.
+    */
+    pub fn save_png_deltas<P: AsRef<Path>>(&self, dir: P) -> ImageResult<PathBuf> {
+        let mut writer = DeltaPngWriter::new(dir, self.delay_ms())?;
+        for frame in self.frames() {
+            writer.write_frame(frame)?;
+        }
+        writer.finish()
+    }
+}
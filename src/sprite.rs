@@ -0,0 +1,111 @@
+use image::{imageops, RgbaImage};
+
+use crate::RenderBuffer;
+
+/// The direction in which frames are laid out in a baked sprite strip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StripDirection {
+    /// Frames are placed left to right
+    Horizontal,
+    /// Frames are placed top to bottom
+    Vertical,
+}
+
+impl RenderBuffer {
+    /// Copy a rectangular region of the buffer into a new `RenderBuffer`.
+    ///
+    /// The region is clamped to the bounds of the buffer. The copy has the same
+    /// rendering settings, including whether its pixels are premultiplied.
+    pub fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> RenderBuffer {
+        let region = imageops::crop_imm(&self.inner, x, y, width, height).to_image();
+        RenderBuffer::with_settings_of(self, region)
+    }
+    /// Create a copy of the buffer that is mirrored left to right.
+    ///
+    /// The copy has the same rendering settings.
+    pub fn flipped_horizontal(&self) -> RenderBuffer {
+        RenderBuffer::with_settings_of(self, imageops::flip_horizontal(&self.inner))
+    }
+    /// Create a copy of the buffer that is mirrored top to bottom.
+    ///
+    /// The copy has the same rendering settings.
+    pub fn flipped_vertical(&self) -> RenderBuffer {
+        RenderBuffer::with_settings_of(self, imageops::flip_vertical(&self.inner))
+    }
+    /**
+    Slice a sprite sheet into frames using a grid with the given number of columns and rows.
+
+    Frames are returned in row-major order. Any pixels that do not fit evenly
+    into the grid are discarded. The frames can be previewed as a GIF with
+    `Animation::from_frames`.
+    */
+    pub fn slice_grid(&self, columns: u32, rows: u32) -> Vec<RenderBuffer> {
+        if columns == 0 || rows == 0 {
+            return Vec::new();
+        }
+        let frame_width = self.width() / columns;
+        let frame_height = self.height() / rows;
+        let mut frames = Vec::with_capacity((columns * rows) as usize);
+        for row in 0..rows {
+            for column in 0..columns {
+                frames.push(self.crop(
+                    column * frame_width,
+                    row * frame_height,
+                    frame_width,
+                    frame_height,
+                ));
+            }
+        }
+        frames
+    }
+}
+
+/// Reorder frames by index, such as to reverse or ping-pong a sequence.
+///
+/// Indices may repeat. Out-of-range indices are skipped.
+pub fn reorder_frames(frames: &[RenderBuffer], order: &[usize]) -> Vec<RenderBuffer> {
+    order
+        .iter()
+        .filter_map(|&i| frames.get(i).cloned())
+        .collect()
+}
+
+/**
+Bake frames into a single strip in the given direction.
+
+Each cell of the strip is as large as the largest frame, and smaller frames
+are placed in the top-left corner of their cell.
+*/
+pub fn bake_strip(frames: &[RenderBuffer], direction: StripDirection) -> RenderBuffer {
+    match direction {
+        StripDirection::Horizontal => bake_grid(frames, frames.len() as u32),
+        StripDirection::Vertical => bake_grid(frames, 1),
+    }
+}
+
+/**
+Bake frames into a sprite sheet with the given number of columns.
+
+Frames are placed in row-major order. Each cell of the sheet is as large as
+the largest frame.
+*/
+pub fn bake_grid(frames: &[RenderBuffer], columns: u32) -> RenderBuffer {
+    let columns = columns.max(1);
+    let rows = (frames.len() as u32).div_ceil(columns);
+    let cell_width = frames.iter().map(|f| f.width()).max().unwrap_or(0);
+    let cell_height = frames.iter().map(|f| f.height()).max().unwrap_or(0);
+    let mut sheet = RgbaImage::new(
+        cell_width * columns.min(frames.len() as u32),
+        cell_height * rows,
+    );
+    for (i, frame) in frames.iter().enumerate() {
+        let i = i as u32;
+        imageops::replace(
+            &mut sheet,
+            &*frame.straight_pixels(),
+            (i % columns) * cell_width,
+            (i / columns) * cell_height,
+        );
+    }
+    sheet.into()
+}
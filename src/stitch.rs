@@ -0,0 +1,382 @@
+use graphics::{
+    draw_state::Blend,
+    math::{self, Matrix2d},
+    Transformed,
+};
+
+use crate::{
+    draw_state::{premultiply, unpremultiply, DrawParams},
+    Progress, RenderBuffer, IDENTITY,
+};
+
+/// How the overlapping edges of stitched tiles are blended together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SeamBlend {
+    /// Fade linearly from one tile to the other across the overlap
+    #[default]
+    Linear,
+    /**
+    Blend each frequency band of a Laplacian pyramid separately.
+
+    Low frequencies are blended over a wide area and high frequencies over a
+    narrow one, which hides seams between tiles with different exposure
+    without blurring detail. `levels` is the number of bands.
+    */
+    Multiband {
+        /// The number of pyramid levels
+        levels: u32,
+    },
+}
+
+/**
+Options for stitching overlapping tiles together.
+
+Blending is alpha-aware: where only one of the overlapping pixels has any
+alpha, it is used as is, so stitching onto a transparent canvas does not fade
+the edges of the first tiles.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StitchOptions {
+    /// The width in pixels of the overlap that tiles are feathered across
+    pub overlap: u32,
+    /// How the overlap is blended
+    pub blend: SeamBlend,
+}
+
+impl Default for StitchOptions {
+    fn default() -> Self {
+        StitchOptions {
+            overlap: 16,
+            blend: SeamBlend::Linear,
+        }
+    }
+}
+
+impl StitchOptions {
+    /// Create new `StitchOptions` with the given overlap and linear blending.
+    pub fn new(overlap: u32) -> Self {
+        StitchOptions {
+            overlap,
+            blend: SeamBlend::Linear,
+        }
+    }
+    /// Set how the overlap is blended.
+    pub fn blend(mut self, blend: SeamBlend) -> Self {
+        self.blend = blend;
+        self
+    }
+}
+
+impl RenderBuffer {
+    /**
+    Blend a tile into the buffer with its top-left corner at the given
+    coordinates, feathering its edges across the overlap.
+
+    Edges of the tile that lie on the edges of the buffer are not feathered.
+    Parts of the tile outside of the buffer are ignored.
+    */
+    pub fn blit_stitched(&mut self, tile: &RenderBuffer, x: i64, y: i64, options: StitchOptions) {
+        self.stitch(tile, x, y, options, [true; 4]);
+    }
+    /**
+    Draw in square tiles like `render_tiled`, but with each tile extended by
+    the overlap on every side and stitched into the buffer with feathered seams.
+
+    This hides seams when the drawing function does not produce exactly
+    matching results along tile edges, such as with per-tile exposure or
+    procedural content.
+    */
+    pub fn render_tiled_stitched<D, P>(
+        &mut self,
+        tile_size: u32,
+        options: StitchOptions,
+        mut draw: D,
+        mut progress: P,
+    ) where
+        D: FnMut(Matrix2d, &mut RenderBuffer),
+        P: FnMut(Progress),
+    {
+        let tile_size = tile_size.max(1);
+        let overlap = options.overlap;
+        let (width, height) = self.inner.dimensions();
+        let columns = width.div_ceil(tile_size);
+        let rows = height.div_ceil(tile_size);
+        let total = u64::from(columns) * u64::from(rows);
+        let mut done = 0;
+        for row in 0..rows {
+            for column in 0..columns {
+                let x = (column * tile_size).saturating_sub(overlap);
+                let y = (row * tile_size).saturating_sub(overlap);
+                let right = ((column + 1) * tile_size + overlap).min(width);
+                let bottom = ((row + 1) * tile_size + overlap).min(height);
+                let mut tile = self.crop(x, y, right - x, bottom - y);
+                draw(IDENTITY.trans(-f64::from(x), -f64::from(y)), &mut tile);
+                // Only feather into tiles that have already been drawn
+                let feather = [column > 0, row > 0, false, false];
+                self.stitch(&tile, i64::from(x), i64::from(y), options, feather);
+                self.pixels_written += tile.pixels_written;
+                done += 1;
+                progress(Progress {
+                    done,
+                    total: Some(total),
+                });
+            }
+        }
+    }
+    /// Blend a tile into the buffer, feathering the left, top, right and bottom edges as given
+    fn stitch(
+        &mut self,
+        tile: &RenderBuffer,
+        x: i64,
+        y: i64,
+        options: StitchOptions,
+        feather: [bool; 4],
+    ) {
+        let (width, height) = self.inner.dimensions();
+        let left = x.max(0);
+        let top = y.max(0);
+        let right = (x + i64::from(tile.width())).min(i64::from(width));
+        let bottom = (y + i64::from(tile.height())).min(i64::from(height));
+        if left >= right || top >= bottom {
+            return;
+        }
+        let (w, h) = ((right - left) as usize, (bottom - top) as usize);
+        let overlap = options.overlap as f32;
+        // Only feather edges that are inside the buffer
+        let feather = [
+            feather[0] && x > 0,
+            feather[1] && y > 0,
+            feather[2] && right < i64::from(width),
+            feather[3] && bottom < i64::from(height),
+        ];
+        let edge_weight = |distance: i64, feathered: bool| {
+            if !feathered || overlap <= 0.0 {
+                1.0
+            } else {
+                ((distance as f32 + 0.5) / overlap).clamp(0.0, 1.0)
+            }
+        };
+        let mut dst = Plane::new(w, h);
+        let mut src = Plane::new(w, h);
+        let mut mask = Plane::new(w, h);
+        for j in 0..h {
+            for i in 0..w {
+                let (bx, by) = (left + i as i64, top + j as i64);
+                let (tx, ty) = (bx - x, by - y);
+                let d = premultiply(&self.pixel(bx as u32, by as u32));
+                let s = premultiply(&tile.pixel(tx as u32, ty as u32));
+                let weight = edge_weight(tx, feather[0])
+                    * edge_weight(ty, feather[1])
+                    * edge_weight(i64::from(tile.width()) - 1 - tx, feather[2])
+                    * edge_weight(i64::from(tile.height()) - 1 - ty, feather[3]);
+                // Where only one side has content, it is used as is
+                let weight = if d[3] <= 0.0 {
+                    1.0
+                } else if s[3] <= 0.0 {
+                    0.0
+                } else {
+                    weight
+                };
+                dst.set(i, j, d);
+                src.set(i, j, s);
+                mask.set(i, j, [weight; 4]);
+            }
+        }
+        let blended = match options.blend {
+            SeamBlend::Linear => blend_planes(&src, &dst, &mask),
+            SeamBlend::Multiband { levels } => multiband(&src, &dst, &mask, levels.max(1)),
+        };
+        for j in 0..h {
+            for i in 0..w {
+                // Pyramid reconstruction can overshoot slightly
+                let color = blended.get(i, j).map(|c| c.clamp(0.0, 1.0));
+                self.set_pixel(
+                    (left + i as i64) as u32,
+                    (top + j as i64) as u32,
+                    unpremultiply(&color),
+                );
+            }
+        }
+    }
+}
+
+/**
+Warp and composite many buffers into one large canvas.
+
+Each buffer is drawn as a textured quad with its transform, which maps its
+pixel coordinates to canvas coordinates, and is alpha blended over the buffers
+before it. The canvas is just large enough to hold every transformed buffer,
+and is translated so that its top-left corner is the smallest transformed
+coordinate. Buffers are sampled with their own texture filters.
+*/
+pub fn mosaic(buffers: &[(&RenderBuffer, Matrix2d)]) -> RenderBuffer {
+    let corners = |buffer: &RenderBuffer, transform: Matrix2d| {
+        let (width, height) = (f64::from(buffer.width()), f64::from(buffer.height()));
+        [[0.0, 0.0], [width, 0.0], [width, height], [0.0, height]]
+            .map(|corner| math::transform_pos(transform, corner))
+    };
+    let mut min = [f64::INFINITY; 2];
+    let mut max = [f64::NEG_INFINITY; 2];
+    for &(buffer, transform) in buffers {
+        for [x, y] in corners(buffer, transform) {
+            min = [min[0].min(x), min[1].min(y)];
+            max = [max[0].max(x), max[1].max(y)];
+        }
+    }
+    if buffers.is_empty() || !(min[0].is_finite() && min[1].is_finite()) {
+        return RenderBuffer::new(0, 0);
+    }
+    let (min, max) = (
+        [min[0].floor(), min[1].floor()],
+        [max[0].ceil(), max[1].ceil()],
+    );
+    let mut canvas = RenderBuffer::new((max[0] - min[0]) as u32, (max[1] - min[1]) as u32);
+    let params = DrawParams {
+        blend: Some(Blend::Alpha),
+        ..DrawParams::default()
+    };
+    for &(buffer, transform) in buffers {
+        let transform = IDENTITY.trans(-min[0], -min[1]).append_transform(transform);
+        let [a, b, c, d] = corners(buffer, transform).map(|[x, y]| [x as f32, y as f32]);
+        let vertices = [a, b, c, a, c, d];
+        let uvs = [
+            [0.0, 0.0],
+            [1.0, 0.0],
+            [1.0, 1.0],
+            [0.0, 0.0],
+            [1.0, 1.0],
+            [0.0, 1.0],
+        ];
+        canvas.draw_tris_uv(params, &[1.0; 4], buffer, |f| f(&vertices, &uvs));
+    }
+    canvas
+}
+
+/// A grid of premultiplied colors used for blending
+#[derive(Clone)]
+struct Plane {
+    width: usize,
+    height: usize,
+    data: Vec<[f32; 4]>,
+}
+
+impl Plane {
+    fn new(width: usize, height: usize) -> Self {
+        Plane {
+            width,
+            height,
+            data: vec![[0.0; 4]; width * height],
+        }
+    }
+    fn get(&self, x: usize, y: usize) -> [f32; 4] {
+        self.data[y * self.width + x]
+    }
+    fn set(&mut self, x: usize, y: usize, color: [f32; 4]) {
+        self.data[y * self.width + x] = color;
+    }
+    /// Halve the size by averaging blocks of 2x2 values
+    fn downsample(&self) -> Plane {
+        let (width, height) = (self.width.div_ceil(2), self.height.div_ceil(2));
+        let mut down = Plane::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0.0; 4];
+                let mut count = 0.0;
+                for (sx, sy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let (sx, sy) = (x * 2 + sx, y * 2 + sy);
+                    if sx < self.width && sy < self.height {
+                        let value = self.get(sx, sy);
+                        for (s, v) in sum.iter_mut().zip(value) {
+                            *s += v;
+                        }
+                        count += 1.0;
+                    }
+                }
+                down.set(x, y, sum.map(|s| s / count));
+            }
+        }
+        down
+    }
+    /// Scale up to the given size with bilinear filtering
+    fn upsample(&self, width: usize, height: usize) -> Plane {
+        let mut up = Plane::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let fx = ((x as f32 + 0.5) / 2.0 - 0.5).clamp(0.0, (self.width - 1) as f32);
+                let fy = ((y as f32 + 0.5) / 2.0 - 0.5).clamp(0.0, (self.height - 1) as f32);
+                let (x0, y0) = (fx.floor() as usize, fy.floor() as usize);
+                let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+                let (tx, ty) = (fx - x0 as f32, fy - y0 as f32);
+                let (a, b, c, d) = (
+                    self.get(x0, y0),
+                    self.get(x1, y0),
+                    self.get(x0, y1),
+                    self.get(x1, y1),
+                );
+                let lerp = |i: usize| {
+                    let top = a[i] + (b[i] - a[i]) * tx;
+                    let bottom = c[i] + (d[i] - c[i]) * tx;
+                    top + (bottom - top) * ty
+                };
+                up.set(x, y, [lerp(0), lerp(1), lerp(2), lerp(3)]);
+            }
+        }
+        up
+    }
+    /// Combine two planes of the same size value by value
+    fn zip(&self, other: &Plane, f: impl Fn(f32, f32) -> f32) -> Plane {
+        let data = self
+            .data
+            .iter()
+            .zip(&other.data)
+            .map(|(a, b)| [f(a[0], b[0]), f(a[1], b[1]), f(a[2], b[2]), f(a[3], b[3])])
+            .collect();
+        Plane {
+            width: self.width,
+            height: self.height,
+            data,
+        }
+    }
+}
+
+/// Mix two planes, taking `src` where the mask is 1 and `dst` where it is 0
+fn blend_planes(src: &Plane, dst: &Plane, mask: &Plane) -> Plane {
+    let data = src
+        .data
+        .iter()
+        .zip(&dst.data)
+        .zip(&mask.data)
+        .map(|((s, d), m)| [0, 1, 2, 3].map(|i| d[i] + (s[i] - d[i]) * m[i]))
+        .collect();
+    Plane {
+        width: src.width,
+        height: src.height,
+        data,
+    }
+}
+
+/// Blend two planes band by band with Laplacian pyramids
+fn multiband(src: &Plane, dst: &Plane, mask: &Plane, levels: u32) -> Plane {
+    let mut src_level = src.clone();
+    let mut dst_level = dst.clone();
+    let mut mask_level = mask.clone();
+    let mut bands = Vec::new();
+    for _ in 1..levels {
+        if src_level.width <= 1 && src_level.height <= 1 {
+            break;
+        }
+        let (src_down, dst_down) = (src_level.downsample(), dst_level.downsample());
+        let (width, height) = (src_level.width, src_level.height);
+        let src_band = src_level.zip(&src_down.upsample(width, height), |a, b| a - b);
+        let dst_band = dst_level.zip(&dst_down.upsample(width, height), |a, b| a - b);
+        bands.push(blend_planes(&src_band, &dst_band, &mask_level));
+        src_level = src_down;
+        dst_level = dst_down;
+        mask_level = mask_level.downsample();
+    }
+    let mut result = blend_planes(&src_level, &dst_level, &mask_level);
+    while let Some(band) = bands.pop() {
+        result = band.zip(&result.upsample(band.width, band.height), |a, b| a + b);
+    }
+    result
+}
@@ -0,0 +1,361 @@
+use std::sync::Arc;
+
+use graphics::{draw_state::DrawState, types::Color, Graphics};
+use image::{GrayImage, Rgba};
+
+use crate::{
+    draw_state::{self, ColorSpace, DrawParams},
+    union_rect, BufferPixel, Canvas, Error, Origin, OverdrawMask, PixelImage, Rasterizer,
+    RenderBuffer, ScanlineRasterizer,
+};
+
+/**
+Memory that a `RenderTarget` draws RGBA8 pixels into.
+
+Pixels are stored row by row from the top, with 4 bytes per pixel. Implement
+this for memory-mapped files, GPU staging buffers or an engine's own
+allocations to have the rasterizer write directly into them.
+
+Stores of other `BufferPixel` layouts hold the channels of each pixel in
+order, and measure their stride in channels rather than bytes.
+*/
+pub trait PixelStore<P: BufferPixel = Rgba<u8>>: Send + Sync {
+    /// Get the width and height in pixels.
+    fn dimensions(&self) -> (u32, u32);
+    /// Get the number of bytes from the start of one row to the start of the next.
+    ///
+    /// The default is 4 bytes per pixel with no padding.
+    fn stride(&self) -> usize {
+        self.dimensions().0 as usize * usize::from(P::CHANNEL_COUNT)
+    }
+    /// Get the pixel bytes.
+    fn bytes(&self) -> &[P::Subpixel];
+    /// Get the pixel bytes mutably.
+    fn bytes_mut(&mut self) -> &mut [P::Subpixel];
+}
+
+impl<P: BufferPixel> PixelStore<P> for PixelImage<P> {
+    fn dimensions(&self) -> (u32, u32) {
+        self.dimensions()
+    }
+    fn bytes(&self) -> &[P::Subpixel] {
+        self
+    }
+    fn bytes_mut(&mut self) -> &mut [P::Subpixel] {
+        self
+    }
+}
+
+/// The number of channels a store with the given dimensions, stride and channels per pixel must have
+pub(crate) fn required_store_len(width: u32, height: u32, stride: usize, channels: usize) -> usize {
+    match height {
+        0 => 0,
+        height => stride * (height as usize - 1) + width as usize * channels,
+    }
+}
+
+/// A `PixelStore` backed by a caller-provided slice.
+#[derive(Debug)]
+pub struct SliceStore<'a> {
+    bytes: &'a mut [u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+}
+
+impl<'a> SliceStore<'a> {
+    /// Wrap a slice of tightly packed RGBA8 pixels with the given dimensions.
+    pub fn new(bytes: &'a mut [u8], width: u32, height: u32) -> Result<Self, Error> {
+        SliceStore::with_stride(bytes, width, height, width as usize * 4)
+    }
+    /**
+    Wrap a slice of RGBA8 pixels with the given dimensions, where each row
+    starts `stride` bytes after the previous one.
+
+    Returns an error if `stride` is less than `width * 4`, or if the slice is
+    too small for the dimensions.
+    */
+    pub fn with_stride(
+        bytes: &'a mut [u8],
+        width: u32,
+        height: u32,
+        stride: usize,
+    ) -> Result<Self, Error> {
+        let row = width as usize * 4;
+        if stride < row {
+            return Err(Error::InvalidStride(stride, row));
+        }
+        if bytes.len() < required_store_len(width, height, stride, 4) {
+            return Err(Error::SizeMismatch(
+                bytes.len(),
+                width as usize * height as usize,
+            ));
+        }
+        Ok(SliceStore {
+            bytes,
+            width,
+            height,
+            stride,
+        })
+    }
+}
+
+impl PixelStore for SliceStore<'_> {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+    fn stride(&self) -> usize {
+        self.stride
+    }
+    fn bytes(&self) -> &[u8] {
+        &*self.bytes
+    }
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        &mut *self.bytes
+    }
+}
+
+/**
+A render target that draws into any `PixelStore`.
+
+It supports the same drawing as a `RenderBuffer`, but leaves the allocation of
+its pixels to the caller. `RenderBuffer`s can still be used as textures.
+*/
+#[derive(Debug, Clone)]
+pub struct RenderTarget<S> {
+    store: S,
+    used: OverdrawMask,
+    rasterizer: Arc<dyn Rasterizer>,
+    origin: Origin,
+    color_space: ColorSpace,
+    prevent_overdraw: bool,
+    /// Only allocated once stencil operations are used
+    stencil: Option<GrayImage>,
+    pixels_written: usize,
+    dirty: Option<[u32; 4]>,
+    #[cfg(feature = "parallel")]
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+}
+
+impl<S: PixelStore> RenderTarget<S> {
+    /// Create a new `RenderTarget` that draws into the given store.
+    pub fn new(store: S) -> Self {
+        let (width, height) = store.dimensions();
+        RenderTarget {
+            store,
+            used: OverdrawMask::default(),
+            rasterizer: Arc::new(ScanlineRasterizer),
+            origin: Origin::TopLeft,
+            color_space: ColorSpace::default(),
+            prevent_overdraw: true,
+            stencil: None,
+            pixels_written: 0,
+            dirty: union_rect(None, [0, 0, width, height]),
+            #[cfg(feature = "parallel")]
+            thread_pool: None,
+        }
+    }
+    /// Get the store.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+    /// Get the store mutably.
+    pub fn store_mut(&mut self) -> &mut S {
+        &mut self.store
+    }
+    /// Take the store out of the target.
+    pub fn into_store(self) -> S {
+        self.store
+    }
+    /// Set the rasterizer used to draw triangles.
+    ///
+    /// The default is `ScanlineRasterizer`.
+    pub fn set_rasterizer<R: Rasterizer + 'static>(&mut self, rasterizer: R) {
+        self.rasterizer = Arc::new(rasterizer);
+    }
+    /// Set the rasterizer used to draw triangles.
+    pub fn with_rasterizer<R: Rasterizer + 'static>(mut self, rasterizer: R) -> Self {
+        self.set_rasterizer(rasterizer);
+        self
+    }
+    /// Set the origin convention used for drawing.
+    ///
+    /// The default is `Origin::TopLeft`.
+    pub fn set_origin(&mut self, origin: Origin) {
+        self.origin = origin;
+    }
+    /// Set the origin convention used for drawing.
+    pub fn with_origin(mut self, origin: Origin) -> Self {
+        self.set_origin(origin);
+        self
+    }
+    /// Set whether pixels are kept from being drawn more than once per draw call.
+    ///
+    /// The default is `true`. See `RenderBuffer::set_overdraw_prevention`.
+    pub fn set_overdraw_prevention(&mut self, prevent: bool) {
+        self.prevent_overdraw = prevent;
+    }
+    /// Set whether pixels are kept from being drawn more than once per draw call.
+    pub fn with_overdraw_prevention(mut self, prevent: bool) -> Self {
+        self.set_overdraw_prevention(prevent);
+        self
+    }
+    /// Draw on a rayon thread pool instead of the global one.
+    ///
+    /// See `RenderBuffer::set_thread_pool`.
+    #[cfg(feature = "parallel")]
+    pub fn set_thread_pool(&mut self, pool: Option<Arc<rayon::ThreadPool>>) {
+        self.thread_pool = pool;
+    }
+    /// Draw on a rayon thread pool instead of the global one.
+    #[cfg(feature = "parallel")]
+    pub fn with_thread_pool(mut self, pool: Arc<rayon::ThreadPool>) -> Self {
+        self.set_thread_pool(Some(pool));
+        self
+    }
+    /// Get the number of pixels written by draw calls.
+    pub fn pixels_written(&self) -> usize {
+        self.pixels_written
+    }
+    /// Get the region changed since the dirty rectangle was last taken, and reset it.
+    ///
+    /// See `RenderBuffer::take_dirty_rect`.
+    pub fn take_dirty_rect(&mut self) -> Option<[u32; 4]> {
+        self.dirty.take()
+    }
+    pub(crate) fn clear_stencil_plane(&mut self, value: u8) {
+        draw_state::fill_stencil(&mut self.stencil, self.store.dimensions(), value);
+    }
+    /// Get the canvas for a draw, allocating the stencil plane if the draw uses it
+    pub(crate) fn draw_canvas(&mut self, params: DrawParams) -> Canvas<'_> {
+        draw_state::prepare_stencil(&mut self.stencil, self.store.dimensions(), params);
+        self.canvas()
+    }
+    pub(crate) fn canvas(&mut self) -> Canvas<'_> {
+        Canvas {
+            pixels: &mut self.store,
+            used: &mut self.used,
+            coverage: None,
+            stencil: self.stencil.as_mut(),
+            color_space: self.color_space,
+            ids: None,
+            current_id: 0,
+            rasterizer: &*self.rasterizer,
+            origin: self.origin,
+            prevent_overdraw: self.prevent_overdraw,
+            pixels_written: &mut self.pixels_written,
+            dirty: &mut self.dirty,
+            journal: None,
+            #[cfg(feature = "parallel")]
+            thread_pool: self.thread_pool.as_deref(),
+        }
+    }
+}
+
+impl<S: PixelStore> Graphics for RenderTarget<S> {
+    type Texture = RenderBuffer;
+    fn clear_color(&mut self, color: Color) {
+        self.canvas().clear(color);
+    }
+    fn clear_stencil(&mut self, value: u8) {
+        self.clear_stencil_plane(value);
+    }
+    fn tri_list<F>(&mut self, draw_state: &DrawState, color: &[f32; 4], f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]])),
+    {
+        let params = draw_state.into();
+        self.draw_canvas(params).draw_tris(params, color, f);
+    }
+    fn tri_list_uv<F>(
+        &mut self,
+        draw_state: &DrawState,
+        color: &[f32; 4],
+        texture: &Self::Texture,
+        f: F,
+    ) where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]])),
+    {
+        let params = draw_state.into();
+        self.draw_canvas(params)
+            .draw_tris_uv(params, color, texture, f);
+    }
+    fn tri_list_c<F>(&mut self, draw_state: &DrawState, f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 4]])),
+    {
+        let params = draw_state.into();
+        self.draw_canvas(params).draw_tris_c(params, f);
+    }
+    fn tri_list_uv_c<F>(&mut self, draw_state: &DrawState, texture: &Self::Texture, f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]], &[[f32; 4]])),
+    {
+        let params = draw_state.into();
+        self.draw_canvas(params).draw_tris_uv_c(params, texture, f);
+    }
+}
+
+impl RenderBuffer {
+    /**
+    Split the buffer into bands of rows that can be drawn to independently.
+
+    Each band is `rows` tall, except possibly the last, and is a `RenderTarget`
+    that draws directly into the buffer's pixels. Bands are ordered from the
+    buffer's origin, so with `Origin::BottomLeft` the first band is at the
+    bottom of the stored image, and each band's own origin is its bottom-left
+    corner. Since they share no pixels, they can be sent to separate threads,
+    and the finished image is in the buffer once they are dropped. This lets
+    very large images be rendered as independent strips.
+
+    Bands use the buffer's rasterizer, origin, color settings, overdraw
+    prevention and thread pool, but do not track coverage or object IDs.
+    Band `i` starts at row `i * rows`, so translating drawing up by that much
+    draws a scene that spans the whole buffer into the band.
+
+    # Panics
+
+    Panics if `rows` is 0.
+    */
+    pub fn split_rows_mut(&mut self, rows: u32) -> Vec<RenderTarget<SliceStore<'_>>> {
+        assert!(rows > 0, "Bands must be at least one row tall");
+        let (width, height) = self.inner.dimensions();
+        if width == 0 || height == 0 {
+            return Vec::new();
+        }
+        self.mark_all_dirty();
+        let rasterizer = Arc::clone(&self.rasterizer);
+        let origin = self.origin;
+        let color_space = self.color_space();
+        let prevent_overdraw = self.prevent_overdraw;
+        #[cfg(feature = "parallel")]
+        let thread_pool = self.thread_pool.clone();
+        let stride = width as usize * 4;
+        let band_len = stride * rows as usize;
+        let bands: Vec<&mut [u8]> = match origin {
+            Origin::TopLeft => self.inner.chunks_mut(band_len).collect(),
+            Origin::BottomLeft => self.inner.rchunks_mut(band_len).collect(),
+        };
+        bands
+            .into_iter()
+            .map(|bytes| {
+                let store = SliceStore {
+                    width,
+                    height: (bytes.len() / stride) as u32,
+                    stride,
+                    bytes,
+                };
+                let mut band = RenderTarget::new(store);
+                band.rasterizer = Arc::clone(&rasterizer);
+                band.origin = origin;
+                band.color_space = color_space;
+                band.prevent_overdraw = prevent_overdraw;
+                #[cfg(feature = "parallel")]
+                {
+                    band.thread_pool = thread_pool.clone();
+                }
+                band
+            })
+            .collect()
+    }
+}
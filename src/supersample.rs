@@ -0,0 +1,165 @@
+use std::path::Path;
+
+use graphics::{draw_state::DrawState, types::Color, Graphics};
+use image::{ImageResult, Rgba, RgbaImage};
+
+use crate::{color_rgba_f32, RenderBuffer};
+
+/**
+A render target that draws at a higher resolution and downsamples the result,
+for smooth edges on everything drawn.
+
+Each final pixel is the average of `samples` × `samples` subpixels, so memory
+use and drawing time grow with the square of `samples`. Use `resolve` to get
+the final `RenderBuffer`.
+*/
+#[derive(Debug, Clone)]
+pub struct Supersampled {
+    buffer: RenderBuffer,
+    width: u32,
+    height: u32,
+    samples: u32,
+}
+
+impl Supersampled {
+    /// Create a new `Supersampled` target with the given final size and number of
+    /// samples along each axis of a pixel.
+    pub fn new(width: u32, height: u32, samples: u32) -> Self {
+        let samples = samples.max(1);
+        Supersampled {
+            buffer: RenderBuffer::new(width * samples, height * samples),
+            width,
+            height,
+            samples,
+        }
+    }
+    /// Get the number of samples along each axis of a pixel.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+    /// Get the final width.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    /// Get the final height.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    /// Get the final width and height.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+    /// Get the oversized buffer that is drawn into.
+    pub fn oversized(&self) -> &RenderBuffer {
+        &self.buffer
+    }
+    /// Get the oversized buffer that is drawn into mutably.
+    pub fn oversized_mut(&mut self) -> &mut RenderBuffer {
+        &mut self.buffer
+    }
+    /// Clear the buffer with a color.
+    pub fn clear(&mut self, color: [f32; 4]) {
+        self.buffer.clear(color);
+    }
+    /// Returns the downsampled color of the pixel at the given coordinates.
+    pub fn pixel(&self, x: u32, y: u32) -> [f32; 4] {
+        color_rgba_f32(self.resolve_pixel(x, y))
+    }
+    /// Downsample the oversized buffer into a new `RenderBuffer` of the final size.
+    pub fn resolve(&self) -> RenderBuffer {
+        RgbaImage::from_fn(self.width, self.height, |x, y| self.resolve_pixel(x, y)).into()
+    }
+    /// Downsample the buffer and save it to a file, with the format deduced from the extension.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        self.resolve().save(path)
+    }
+    /// Average a block of subpixels, weighting colors by alpha so that
+    /// transparent subpixels do not darken edges
+    fn resolve_pixel(&self, x: u32, y: u32) -> Rgba<u8> {
+        let n = self.samples;
+        let mut sums = [0u32; 4];
+        for sy in y * n..(y + 1) * n {
+            for sx in x * n..(x + 1) * n {
+                let pixel = self.buffer.get_pixel(sx, sy);
+                let alpha = u32::from(pixel[3]);
+                for (sum, &value) in sums.iter_mut().zip(&pixel.0[..3]) {
+                    *sum += u32::from(value) * alpha;
+                }
+                sums[3] += alpha;
+            }
+        }
+        let count = n * n;
+        if sums[3] == 0 {
+            return Rgba([0, 0, 0, 0]);
+        }
+        Rgba([
+            ((sums[0] + sums[3] / 2) / sums[3]) as u8,
+            ((sums[1] + sums[3] / 2) / sums[3]) as u8,
+            ((sums[2] + sums[3] / 2) / sums[3]) as u8,
+            ((sums[3] + count / 2) / count) as u8,
+        ])
+    }
+}
+
+impl RenderBuffer {
+    /// Create a new supersampling render target with the given final size and
+    /// number of samples along each axis of a pixel.
+    pub fn new_antialiased(width: u32, height: u32, samples: u32) -> Supersampled {
+        Supersampled::new(width, height, samples)
+    }
+}
+
+impl From<Supersampled> for RenderBuffer {
+    fn from(supersampled: Supersampled) -> Self {
+        supersampled.resolve()
+    }
+}
+
+impl Graphics for Supersampled {
+    type Texture = RenderBuffer;
+    fn clear_color(&mut self, color: Color) {
+        self.buffer.clear_color(color);
+    }
+    fn clear_stencil(&mut self, value: u8) {
+        self.buffer.clear_stencil(value);
+    }
+    fn tri_list<F>(&mut self, draw_state: &DrawState, color: &[f32; 4], f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]])),
+    {
+        let samples = f64::from(self.samples);
+        self.buffer
+            .scale_aware(samples)
+            .tri_list(draw_state, color, f);
+    }
+    fn tri_list_c<F>(&mut self, draw_state: &DrawState, f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 4]])),
+    {
+        let samples = f64::from(self.samples);
+        self.buffer.scale_aware(samples).tri_list_c(draw_state, f);
+    }
+    fn tri_list_uv<F>(
+        &mut self,
+        draw_state: &DrawState,
+        color: &[f32; 4],
+        texture: &Self::Texture,
+        f: F,
+    ) where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]])),
+    {
+        let samples = f64::from(self.samples);
+        self.buffer
+            .scale_aware(samples)
+            .tri_list_uv(draw_state, color, texture, f);
+    }
+    fn tri_list_uv_c<F>(&mut self, draw_state: &DrawState, texture: &Self::Texture, f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]], &[[f32; 4]])),
+    {
+        let samples = f64::from(self.samples);
+        self.buffer
+            .scale_aware(samples)
+            .tri_list_uv_c(draw_state, texture, f);
+    }
+}
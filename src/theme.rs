@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use graphics::{
+    character::CharacterCache, draw_state::DrawState, math::Matrix2d, rectangle::Border,
+    types::Color, Graphics, Line, Rectangle, Text,
+};
+
+#[cfg(feature = "text")]
+use crate::{buffer_glyphs_from_bytes, BufferGlyphs, FontError};
+
+/// The named colors of a `Theme`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    /// The color behind everything
+    pub background: Color,
+    /// The fill color of panels
+    pub surface: Color,
+    /// The color of text and outlines
+    pub foreground: Color,
+    /// The main accent color
+    pub primary: Color,
+    /// A secondary accent color
+    pub secondary: Color,
+    /// A muted color for dividers and subtle details
+    pub muted: Color,
+}
+
+/**
+A set of styles that the theme drawing helpers consult.
+
+Imagery drawn with a `Theme` stays visually consistent, and can be re-skinned
+by swapping one struct.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    /// The colors
+    pub palette: Palette,
+    /// The default font data, such as the contents of a `.ttf` file
+    pub font: Option<Arc<[u8]>>,
+    /// The default font size
+    pub font_size: u32,
+    /// The width of outlines and lines
+    pub stroke_width: f64,
+    /// The corner radius of panels
+    pub corner_radius: f64,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::light()
+    }
+}
+
+impl Theme {
+    /// A theme with dark text on a light background.
+    pub fn light() -> Self {
+        Theme {
+            palette: Palette {
+                background: [0.96, 0.96, 0.96, 1.0],
+                surface: [1.0, 1.0, 1.0, 1.0],
+                foreground: [0.13, 0.13, 0.13, 1.0],
+                primary: [0.16, 0.44, 0.85, 1.0],
+                secondary: [0.85, 0.35, 0.16, 1.0],
+                muted: [0.75, 0.75, 0.75, 1.0],
+            },
+            font: None,
+            font_size: 16,
+            stroke_width: 1.0,
+            corner_radius: 4.0,
+        }
+    }
+    /// A theme with light text on a dark background.
+    pub fn dark() -> Self {
+        Theme {
+            palette: Palette {
+                background: [0.09, 0.09, 0.1, 1.0],
+                surface: [0.16, 0.16, 0.18, 1.0],
+                foreground: [0.92, 0.92, 0.92, 1.0],
+                primary: [0.38, 0.62, 1.0, 1.0],
+                secondary: [1.0, 0.55, 0.35, 1.0],
+                muted: [0.35, 0.35, 0.38, 1.0],
+            },
+            ..Theme::light()
+        }
+    }
+    /// Set the default font data.
+    pub fn with_font<F: Into<Arc<[u8]>>>(mut self, font: F) -> Self {
+        self.font = Some(font.into());
+        self
+    }
+    /// Load a glyph cache for the default font, if there is one.
+    #[cfg(feature = "text")]
+    pub fn glyphs(&self) -> Option<Result<BufferGlyphs<'_>, FontError>> {
+        self.font.as_deref().map(buffer_glyphs_from_bytes)
+    }
+    /// Clear with the background color.
+    pub fn clear<G: Graphics>(&self, g: &mut G) {
+        g.clear_color(self.palette.background);
+    }
+    /// Draw a panel with the surface color, rounded corners and an outline.
+    pub fn panel<G: Graphics>(&self, rect: [f64; 4], transform: Matrix2d, g: &mut G) {
+        Rectangle::new_round(self.palette.surface, self.corner_radius)
+            .border(Border {
+                color: self.palette.muted,
+                radius: self.stroke_width / 2.0,
+            })
+            .draw(rect, &DrawState::default(), transform, g);
+    }
+    /// Draw a filled, rounded rectangle with the primary color, such as for a button or bar.
+    pub fn highlight<G: Graphics>(&self, rect: [f64; 4], transform: Matrix2d, g: &mut G) {
+        Rectangle::new_round(self.palette.primary, self.corner_radius).draw(
+            rect,
+            &DrawState::default(),
+            transform,
+            g,
+        );
+    }
+    /// Draw a line with the muted color, such as for a divider.
+    pub fn divider<G: Graphics>(&self, line: [f64; 4], transform: Matrix2d, g: &mut G) {
+        Line::new(self.palette.muted, self.stroke_width / 2.0).draw(
+            line,
+            &DrawState::default(),
+            transform,
+            g,
+        );
+    }
+    /// Draw text with the foreground color and default font size.
+    pub fn text<C, G>(
+        &self,
+        text: &str,
+        glyphs: &mut C,
+        transform: Matrix2d,
+        g: &mut G,
+    ) -> Result<(), C::Error>
+    where
+        C: CharacterCache,
+        G: Graphics<Texture = C::Texture>,
+    {
+        Text::new_color(self.palette.foreground, self.font_size).draw(
+            text,
+            glyphs,
+            &DrawState::default(),
+            transform,
+            g,
+        )
+    }
+}
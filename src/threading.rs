@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+
+use crate::RenderBuffer;
+
+impl RenderBuffer {
+    /// Get the rayon thread pool the buffer draws on, if it does not use the global one.
+    pub fn thread_pool(&self) -> Option<&Arc<ThreadPool>> {
+        self.thread_pool.as_ref()
+    }
+    /**
+    Draw on a rayon thread pool instead of the global one, or go back to the
+    global pool with `None`.
+
+    This lets applications that manage their own pools keep rendering off of
+    the global pool. The pool can be shared between many buffers. Filters and
+    other operations that are not draw calls are not affected.
+    */
+    pub fn set_thread_pool(&mut self, pool: Option<Arc<ThreadPool>>) {
+        self.thread_pool = pool;
+    }
+    /// Draw on a rayon thread pool instead of the global one.
+    pub fn with_thread_pool(mut self, pool: Arc<ThreadPool>) -> Self {
+        self.set_thread_pool(Some(pool));
+        self
+    }
+    /**
+    Draw on a new thread pool with the given number of threads.
+
+    `0` uses one thread per core. A single thread draws tiles one at a time,
+    on the thread in the pool.
+    */
+    pub fn set_threads(&mut self, threads: usize) -> Result<(), ThreadPoolBuildError> {
+        let pool = ThreadPoolBuilder::new().num_threads(threads).build()?;
+        self.set_thread_pool(Some(Arc::new(pool)));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::hash_map::DefaultHasher,
+        hash::{Hash, Hasher},
+    };
+
+    use graphics::{ellipse, line, polygon, rectangle, Context};
+
+    use super::*;
+
+    /// Render overlapping translucent shapes on some number of threads and hash the pixels
+    fn render_hash(threads: usize) -> u64 {
+        let mut buffer = RenderBuffer::new(300, 300);
+        buffer.set_threads(threads).unwrap();
+        let transform = Context::new().transform;
+        for i in 0..20 {
+            let t = f64::from(i);
+            let color = [0.05 * i as f32, 0.3, 1.0 - 0.05 * i as f32, 0.4];
+            rectangle(
+                color,
+                [t * 7.0, t * 11.0, 150.0, 90.0],
+                transform,
+                &mut buffer,
+            );
+            ellipse(
+                color,
+                [280.0 - t * 13.0, t * 5.0, 70.0, 200.0],
+                transform,
+                &mut buffer,
+            );
+            polygon(
+                color,
+                &[[t * 15.0, 0.0], [300.0, t * 9.0], [t * 3.0, 300.0]],
+                transform,
+                &mut buffer,
+            );
+            line(
+                color,
+                2.5,
+                [0.0, t * 15.0, 300.0, 300.0 - t * 4.0],
+                transform,
+                &mut buffer,
+            );
+        }
+        let mut hasher = DefaultHasher::new();
+        buffer.as_raw().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn render_is_independent_of_thread_count() {
+        let single = render_hash(1);
+        for threads in [2, 3, 8] {
+            assert_eq!(render_hash(threads), single, "{} threads", threads);
+        }
+    }
+}
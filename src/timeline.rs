@@ -0,0 +1,267 @@
+//! Keyframes, easing and interpolation for rendering baked animations.
+
+use std::f64::consts::PI;
+
+use crate::{Animation, Error, RenderBuffer};
+
+/// An easing function that shapes the progress of a transition between two keyframes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Easing {
+    /// Constant speed
+    #[default]
+    Linear,
+    /// Jump to the next value at the end of the transition
+    Step,
+    /// Start slow, quadratic
+    QuadIn,
+    /// End slow, quadratic
+    QuadOut,
+    /// Start and end slow, quadratic
+    QuadInOut,
+    /// Start slow, cubic
+    CubicIn,
+    /// End slow, cubic
+    CubicOut,
+    /// Start and end slow, cubic
+    CubicInOut,
+    /// Start and end slow, sinusoidal
+    SineInOut,
+}
+
+impl Easing {
+    /// Map linear progress in `[0, 1]` to eased progress.
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::Step => {
+                if t < 1.0 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => t * (2.0 - t),
+            Easing::QuadInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::SineInOut => -((PI * t).cos() - 1.0) / 2.0,
+        }
+    }
+}
+
+/// A value that can be interpolated between keyframes.
+pub trait Tween: Clone {
+    /// Interpolate between `self` and `other`, where `t` is in `[0, 1]`.
+    fn tween(&self, other: &Self, t: f64) -> Self;
+}
+
+impl Tween for f64 {
+    fn tween(&self, other: &Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Tween for f32 {
+    fn tween(&self, other: &Self, t: f64) -> Self {
+        self + (other - self) * t as f32
+    }
+}
+
+macro_rules! array_tween {
+    ($($n:literal),*) => {
+        $(
+            impl<T: Tween + Copy> Tween for [T; $n] {
+                fn tween(&self, other: &Self, t: f64) -> Self {
+                    let mut result = *self;
+                    for (r, o) in result.iter_mut().zip(other) {
+                        *r = r.tween(o, t);
+                    }
+                    result
+                }
+            }
+        )*
+    };
+}
+
+array_tween!(2, 3, 4);
+
+/// A single keyframe in a `Track`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframe<T> {
+    /// The time of the keyframe in seconds
+    pub time: f64,
+    /// The value at this keyframe
+    pub value: T,
+    /// The easing used for the transition leading into this keyframe
+    pub easing: Easing,
+}
+
+/**
+The keyframes of a single animated property.
+
+Before the first keyframe the track holds the first value,
+and after the last keyframe it holds the last value.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track<T> {
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T> Default for Track<T> {
+    fn default() -> Self {
+        Track {
+            keyframes: Vec::new(),
+        }
+    }
+}
+
+impl<T: Tween> Track<T> {
+    /// Create a new `Track` with no keyframes.
+    pub fn new() -> Track<T> {
+        Track::default()
+    }
+    /// Add a keyframe to the track, keeping keyframes sorted by time.
+    pub fn key(mut self, time: f64, value: T, easing: Easing) -> Self {
+        self.insert(time, value, easing);
+        self
+    }
+    /// Add a keyframe to the track, keeping keyframes sorted by time.
+    pub fn insert(&mut self, time: f64, value: T, easing: Easing) {
+        let index = self
+            .keyframes
+            .iter()
+            .position(|k| k.time > time)
+            .unwrap_or(self.keyframes.len());
+        self.keyframes.insert(
+            index,
+            Keyframe {
+                time,
+                value,
+                easing,
+            },
+        );
+    }
+    /// Get the keyframes of the track.
+    pub fn keyframes(&self) -> &[Keyframe<T>] {
+        &self.keyframes
+    }
+    /// Get the interpolated value at the given time.
+    ///
+    /// Returns `None` if the track has no keyframes.
+    pub fn value_at(&self, time: f64) -> Option<T> {
+        let first = self.keyframes.first()?;
+        if time <= first.time {
+            return Some(first.value.clone());
+        }
+        for pair in self.keyframes.windows(2) {
+            let (from, to) = (&pair[0], &pair[1]);
+            if time < to.time {
+                let t = (time - from.time) / (to.time - from.time);
+                return Some(from.value.tween(&to.value, to.easing.apply(t)));
+            }
+        }
+        self.keyframes.last().map(|k| k.value.clone())
+    }
+}
+
+/**
+A fixed-length timeline that renders an animation frame by frame.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timeline {
+    duration: f64,
+    fps: f64,
+}
+
+impl Timeline {
+    /**
+    Create a new `Timeline` with the given duration in seconds and frames per second.
+
+    Returns an error if `fps` is not a positive, finite number, or if `duration`
+    is not a non-negative, finite number or would have more than `u32::MAX` frames.
+    */
+    pub fn new(duration: f64, fps: f64) -> Result<Timeline, Error> {
+        if !(fps > 0.0 && fps.is_finite()) {
+            return Err(Error::InvalidFrameRate(fps));
+        }
+        if !(duration >= 0.0 && (duration * fps).ceil() <= u32::MAX as f64) {
+            return Err(Error::InvalidDuration(duration));
+        }
+        Ok(Timeline { duration, fps })
+    }
+    /// Get the duration of the timeline in seconds.
+    pub fn duration(&self) -> f64 {
+        self.duration
+    }
+    /// Get the number of frames per second.
+    pub fn fps(&self) -> f64 {
+        self.fps
+    }
+    /// Get the number of frames in the timeline.
+    pub fn frame_count(&self) -> usize {
+        (self.duration * self.fps).ceil().max(1.0) as usize
+    }
+    /// Get the time in seconds of each frame.
+    pub fn times(&self) -> impl Iterator<Item = f64> {
+        let fps = self.fps;
+        (0..self.frame_count()).map(move |i| i as f64 / fps)
+    }
+    /**
+    Render every frame of the timeline into an `Animation`.
+
+    For each frame, a new `RenderBuffer` of the given size is created and passed
+    to `draw` along with the frame's time in seconds.
+    */
+    pub fn render<F>(&self, width: u32, height: u32, mut draw: F) -> Animation
+    where
+        F: FnMut(f64, &mut RenderBuffer),
+    {
+        let mut animation = Animation::new((1000.0 / self.fps).round() as u32);
+        for time in self.times() {
+            let mut buffer = RenderBuffer::new(width, height);
+            draw(time, &mut buffer);
+            animation.push(buffer);
+        }
+        animation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_rejects_invalid_timings() {
+        for fps in [0.0, -30.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                Timeline::new(1.0, fps),
+                Err(Error::InvalidFrameRate(_))
+            ));
+        }
+        for duration in [-1.0, f64::NAN, f64::INFINITY, 1e300] {
+            assert!(matches!(
+                Timeline::new(duration, 30.0),
+                Err(Error::InvalidDuration(_))
+            ));
+        }
+        let timeline = Timeline::new(0.0, 30.0).unwrap();
+        assert_eq!(timeline.frame_count(), 1);
+        let timeline = Timeline::new(1.5, 30.0).unwrap();
+        assert_eq!(timeline.frame_count(), 45);
+    }
+}
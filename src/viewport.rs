@@ -0,0 +1,282 @@
+use graphics::{draw_state::DrawState, types::Color, Graphics, ImageSize};
+use image::Luma;
+
+use crate::{draw_state, draw_state::DrawParams, intersect_bounds, RenderBuffer};
+
+/**
+A rectangular region of a `RenderBuffer` that can be drawn to as if it were
+a buffer of its own.
+
+Drawing is offset so that the corner of the region is the origin, and
+anything outside of the region is clipped. Pixels are drawn directly into the
+parent buffer with its settings, so no intermediate buffer or copy is needed.
+This makes split-screen views and tiled compositions straightforward.
+
+Create one with `RenderBuffer::viewport`.
+*/
+#[derive(Debug)]
+pub struct Viewport<'a> {
+    buffer: &'a mut RenderBuffer,
+    /// `[x, y, width, height]` in top-left coordinates of the parent buffer
+    rect: [u32; 4],
+}
+
+impl RenderBuffer {
+    /**
+    Get a render target for a rectangular region of the buffer.
+
+    The region is clamped to the bounds of the buffer. If the buffer's origin
+    is `Origin::BottomLeft`, `y` is measured up from the bottom of the buffer to
+    the bottom of the region, and drawing within the region is bottom-left too.
+    */
+    pub fn viewport(&mut self, x: u32, y: u32, width: u32, height: u32) -> Viewport<'_> {
+        let (buffer_width, buffer_height) = self.dimensions();
+        let rect = self.origin.apply_rect([x, y, width, height], buffer_height);
+        Viewport {
+            rect: intersect_bounds([0, 0, buffer_width, buffer_height], rect),
+            buffer: self,
+        }
+    }
+}
+
+impl Viewport<'_> {
+    /**
+    Get a render target for a rectangular region of the viewport.
+
+    The region is relative to the viewport, and is clamped to its bounds.
+    */
+    pub fn viewport(&mut self, x: u32, y: u32, width: u32, height: u32) -> Viewport<'_> {
+        let [left, bottom_or_top, _, _] = self.origin_rect();
+        let rect = [
+            x.saturating_add(left),
+            y.saturating_add(bottom_or_top),
+            width,
+            height,
+        ];
+        let rect = self.buffer.origin.apply_rect(rect, self.buffer.height());
+        Viewport {
+            rect: intersect_bounds(self.rect, rect),
+            buffer: self.buffer,
+        }
+    }
+    /// Get the rectangle of the viewport as `[x, y, width, height]` in the rows of the parent buffer.
+    pub fn rect(&self) -> [u32; 4] {
+        self.rect
+    }
+    /// Get the width and height of the viewport.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.rect[2], self.rect[3])
+    }
+    /// Get the buffer the viewport draws into.
+    pub fn buffer(&self) -> &RenderBuffer {
+        self.buffer
+    }
+    /// Get the rectangle of the viewport in the parent buffer's origin convention
+    fn origin_rect(&self) -> [u32; 4] {
+        // Flipping a rectangle within the buffer is its own inverse
+        self.buffer
+            .origin
+            .apply_rect(self.rect, self.buffer.height())
+    }
+    /// Get the corner that drawing is offset by
+    fn offset(&self) -> [f32; 2] {
+        let [x, y, _, _] = self.origin_rect();
+        [x as f32, y as f32]
+    }
+    /// Offset the scissor rectangle of a draw and clip it to the viewport
+    fn params(&self, params: DrawParams) -> DrawParams {
+        let rect = self.origin_rect();
+        let scissor = match params.scissor {
+            Some([x, y, width, height]) => intersect_bounds(
+                rect,
+                [
+                    x.saturating_add(rect[0]),
+                    y.saturating_add(rect[1]),
+                    width,
+                    height,
+                ],
+            ),
+            None => rect,
+        };
+        DrawParams {
+            scissor: Some(scissor),
+            ..params
+        }
+    }
+    pub(crate) fn clear_pixels(&mut self, color: Color) {
+        let rect = self.rect;
+        self.buffer.canvas().clear_rect(color, rect);
+        let [x, y, width, height] = rect;
+        let buffer_width = self.buffer.width() as usize;
+        for row in y as usize..(y + height) as usize {
+            let (start, end) = (row * buffer_width + x as usize, width as usize);
+            if let Some(coverage) = &mut self.buffer.coverage {
+                for value in &mut (**coverage)[start..][..end] {
+                    *value = 0;
+                }
+            }
+            if let Some(ids) = &mut self.buffer.ids {
+                for id in &mut ids[start..][..end] {
+                    *id = 0;
+                }
+            }
+        }
+    }
+    pub(crate) fn clear_stencil_plane(&mut self, value: u8) {
+        let size = self.buffer.dimensions();
+        let fits = self
+            .buffer
+            .stencil
+            .as_ref()
+            .is_some_and(|stencil| stencil.dimensions() == size);
+        if !fits {
+            draw_state::fill_stencil(&mut self.buffer.stencil, size, 0);
+            self.buffer.track_memory();
+        }
+        if let Some(stencil) = &mut self.buffer.stencil {
+            let [x, y, width, height] = self.rect;
+            for py in y..y + height {
+                for px in x..x + width {
+                    stencil.put_pixel(px, py, Luma([value]));
+                }
+            }
+        }
+    }
+    pub(crate) fn draw_tris<F>(&mut self, params: DrawParams, color: &[f32; 4], mut f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]])),
+    {
+        let (params, offset) = (self.params(params), self.offset());
+        let mut shifted = Vec::new();
+        self.buffer.draw_tris(params, color, |draw| {
+            f(&mut |vertices| draw(offset_vertices(vertices, offset, &mut shifted)))
+        });
+    }
+    pub(crate) fn draw_tris_uv<F>(
+        &mut self,
+        params: DrawParams,
+        color: &[f32; 4],
+        texture: &RenderBuffer,
+        mut f: F,
+    ) where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]])),
+    {
+        let (params, offset) = (self.params(params), self.offset());
+        let mut shifted = Vec::new();
+        self.buffer.draw_tris_uv(params, color, texture, |draw| {
+            f(&mut |vertices, tex_vertices| {
+                draw(
+                    offset_vertices(vertices, offset, &mut shifted),
+                    tex_vertices,
+                )
+            })
+        });
+    }
+    pub(crate) fn draw_tris_c<F>(&mut self, params: DrawParams, mut f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 4]])),
+    {
+        let (params, offset) = (self.params(params), self.offset());
+        let mut shifted = Vec::new();
+        self.buffer.draw_tris_c(params, |draw| {
+            f(
+                &mut |vertices, colors| {
+                    draw(offset_vertices(vertices, offset, &mut shifted), colors)
+                },
+            )
+        });
+    }
+    pub(crate) fn draw_tris_uv_c<F>(&mut self, params: DrawParams, texture: &RenderBuffer, mut f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]], &[[f32; 4]])),
+    {
+        let (params, offset) = (self.params(params), self.offset());
+        let mut shifted = Vec::new();
+        self.buffer.draw_tris_uv_c(params, texture, |draw| {
+            f(&mut |vertices, tex_vertices, colors| {
+                draw(
+                    offset_vertices(vertices, offset, &mut shifted),
+                    tex_vertices,
+                    colors,
+                )
+            })
+        });
+    }
+}
+
+/// Offset vertices into a reused buffer
+fn offset_vertices<'a>(
+    vertices: &[[f32; 2]],
+    [dx, dy]: [f32; 2],
+    shifted: &'a mut Vec<[f32; 2]>,
+) -> &'a [[f32; 2]] {
+    shifted.clear();
+    shifted.extend(vertices.iter().map(|[x, y]| [x + dx, y + dy]));
+    shifted
+}
+
+impl ImageSize for Viewport<'_> {
+    fn get_size(&self) -> (u32, u32) {
+        self.dimensions()
+    }
+}
+
+impl Graphics for Viewport<'_> {
+    type Texture = RenderBuffer;
+    fn clear_color(&mut self, color: Color) {
+        self.clear_pixels(color);
+    }
+    fn clear_stencil(&mut self, value: u8) {
+        self.clear_stencil_plane(value);
+    }
+    fn tri_list<F>(&mut self, draw_state: &DrawState, color: &[f32; 4], f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]])),
+    {
+        self.draw_tris(draw_state.into(), color, f);
+    }
+    fn tri_list_uv<F>(
+        &mut self,
+        draw_state: &DrawState,
+        color: &[f32; 4],
+        texture: &Self::Texture,
+        f: F,
+    ) where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]])),
+    {
+        self.draw_tris_uv(draw_state.into(), color, texture, f);
+    }
+    fn tri_list_c<F>(&mut self, draw_state: &DrawState, f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 4]])),
+    {
+        self.draw_tris_c(draw_state.into(), f);
+    }
+    fn tri_list_uv_c<F>(&mut self, draw_state: &DrawState, texture: &Self::Texture, f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]], &[[f32; 4]])),
+    {
+        self.draw_tris_uv_c(draw_state.into(), texture, f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graphics::{rectangle, Context};
+
+    use super::*;
+
+    #[test]
+    fn clear_resets_coverage_inside_the_viewport() {
+        let mut buffer = RenderBuffer::new(8, 8);
+        buffer.set_coverage_tracking(true);
+        let transform = Context::new().transform;
+        rectangle([1.0; 4], [0.0, 0.0, 8.0, 8.0], transform, &mut buffer);
+        buffer.viewport(2, 3, 4, 2).clear_color([0.0; 4]);
+        let coverage = buffer.coverage_buffer();
+        for (x, y, value) in coverage.enumerate_pixels() {
+            let inside = (2..6).contains(&x) && (3..5).contains(&y);
+            assert_eq!(value[0], if inside { 0 } else { 255 }, "({}, {})", x, y);
+        }
+    }
+}
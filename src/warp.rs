@@ -0,0 +1,140 @@
+use graphics::math::{Matrix2d, Vec2d};
+use image::RgbaImage;
+
+use crate::{color_f32_rgba, RenderBuffer, Texels, TextureFilter, WrapMode};
+
+impl RenderBuffer {
+    /**
+    Create a new buffer with this buffer transformed by an affine matrix.
+
+    Every pixel keeps the coordinates the matrix maps it to, so the result can
+    be composited directly onto a buffer in the same space. The result is just
+    large enough to hold the transformed buffer, and anything mapped to
+    negative coordinates is cropped. Edges are antialiased.
+    */
+    pub fn warp_affine(&self, matrix: Matrix2d, filter: TextureFilter) -> RenderBuffer {
+        let [[a, b, c], [d, e, f]] = matrix;
+        let det = a * e - b * d;
+        let (width, height) = (f64::from(self.width()), f64::from(self.height()));
+        let corners = [[0.0, 0.0], [width, 0.0], [width, height], [0.0, height]]
+            .map(|p| [a * p[0] + b * p[1] + c, d * p[0] + e * p[1] + f]);
+        if det == 0.0 || !det.is_finite() {
+            return self.warp_with(corners, filter, |_| None);
+        }
+        self.warp_with(corners, filter, |[x, y]| {
+            let (x, y) = (x - c, y - f);
+            Some([(e * x - b * y) / det, (a * y - d * x) / det])
+        })
+    }
+    /**
+    Create a new buffer with this buffer stretched onto a quadrilateral.
+
+    `quad` holds the points the top-left, top-right, bottom-right, and
+    bottom-left corners are mapped to, and the buffer is warped with the
+    perspective projection between them. This is useful for placing a
+    screenshot onto an angled device frame.
+
+    As with `warp_affine`, pixels keep the coordinates they are mapped to and
+    anything at negative coordinates is cropped. The buffer is sampled with
+    its min or mag filter, depending on whether the quad is smaller or larger
+    than the buffer.
+    */
+    pub fn warp_perspective(&self, quad: [Vec2d; 4]) -> RenderBuffer {
+        let (width, height) = self.dimensions();
+        let [p0, p1, p2, p3] = quad;
+        let tri = [p0, p1, p2].map(|p| [p[0] as f32, p[1] as f32]);
+        let tex_tri = [
+            [0.0, 0.0],
+            [width as f32, 0.0],
+            [width as f32, height as f32],
+        ];
+        let filter = Texels::new(self).filter_for(&tri, &tex_tri);
+        // Map the unit square onto the quad
+        let (dx1, dx2, dx3) = (p1[0] - p2[0], p3[0] - p2[0], p0[0] - p1[0] + p2[0] - p3[0]);
+        let (dy1, dy2, dy3) = (p1[1] - p2[1], p3[1] - p2[1], p0[1] - p1[1] + p2[1] - p3[1]);
+        let det = dx1 * dy2 - dx2 * dy1;
+        let (g, h) = if dx3 == 0.0 && dy3 == 0.0 {
+            (0.0, 0.0)
+        } else if det == 0.0 {
+            return self.warp_with(quad, filter, |_| None);
+        } else {
+            ((dx3 * dy2 - dx2 * dy3) / det, (dx1 * dy3 - dx3 * dy1) / det)
+        };
+        let m = [
+            [p1[0] - p0[0] + g * p1[0], p3[0] - p0[0] + h * p3[0], p0[0]],
+            [p1[1] - p0[1] + g * p1[1], p3[1] - p0[1] + h * p3[1], p0[1]],
+            [g, h, 1.0],
+        ];
+        // Invert it to map pixels back onto the buffer
+        let adj = [
+            [
+                m[1][1] * m[2][2] - m[1][2] * m[2][1],
+                m[0][2] * m[2][1] - m[0][1] * m[2][2],
+                m[0][1] * m[1][2] - m[0][2] * m[1][1],
+            ],
+            [
+                m[1][2] * m[2][0] - m[1][0] * m[2][2],
+                m[0][0] * m[2][2] - m[0][2] * m[2][0],
+                m[0][2] * m[1][0] - m[0][0] * m[1][2],
+            ],
+            [
+                m[1][0] * m[2][1] - m[1][1] * m[2][0],
+                m[0][1] * m[2][0] - m[0][0] * m[2][1],
+                m[0][0] * m[1][1] - m[0][1] * m[1][0],
+            ],
+        ];
+        let (width, height) = (f64::from(width), f64::from(height));
+        // Points past the horizon map back onto the buffer with the opposite sign
+        let center = [
+            (p0[0] + p1[0] + p2[0] + p3[0]) / 4.0,
+            (p0[1] + p1[1] + p2[1] + p3[1]) / 4.0,
+        ];
+        let sign = adj[2][0] * center[0] + adj[2][1] * center[1] + adj[2][2];
+        self.warp_with(quad, filter, |[x, y]| {
+            let [u, v, w] = adj.map(|row| row[0] * x + row[1] * y + row[2]);
+            if w.abs() < f64::EPSILON || w * sign < 0.0 {
+                return None;
+            }
+            Some([u / w * width, v / w * height])
+        })
+    }
+    /// Fill the bounds of the mapped corners by sampling the buffer at the points
+    /// `inverse` maps each pixel center back to
+    fn warp_with<F>(&self, corners: [Vec2d; 4], filter: TextureFilter, inverse: F) -> RenderBuffer
+    where
+        F: Fn(Vec2d) -> Option<Vec2d>,
+    {
+        let max = corners
+            .iter()
+            .fold([0.0f64; 2], |max, p| [max[0].max(p[0]), max[1].max(p[1])]);
+        if !(max[0].is_finite() && max[1].is_finite()) {
+            return RenderBuffer::new(0, 0);
+        }
+        let mut result = RgbaImage::new(max[0].ceil() as u32, max[1].ceil() as u32);
+        let (width, height) = (f64::from(self.width()), f64::from(self.height()));
+        if width == 0.0 || height == 0.0 {
+            return result.into();
+        }
+        // Sampling past the edges would pick up the other side of repeating textures
+        let texels = Texels {
+            wrap: [WrapMode::ClampToEdge; 2],
+            ..Texels::new(self)
+        };
+        for (x, y, pixel) in result.enumerate_pixels_mut() {
+            let [u, v] = match inverse([f64::from(x) + 0.5, f64::from(y) + 0.5]) {
+                Some(point) => point,
+                None => continue,
+            };
+            // Fade out over the last pixel inside the edges
+            let inside = u.min(v).min(width - u).min(height - v);
+            let coverage = (inside + 0.5).clamp(0.0, 1.0) as f32;
+            if coverage <= 0.0 {
+                continue;
+            }
+            let mut color = texels.sample([(u - 0.5) as f32, (v - 0.5) as f32], filter);
+            color[3] *= coverage;
+            *pixel = color_f32_rgba(&color);
+        }
+        result.into()
+    }
+}